@@ -0,0 +1,652 @@
+use crate::diagnostic::{codes, Diagnostic};
+use crate::parser::ast::{
+    BinaryOp, Expr, ExprKind, FunctionDecl, Item, Literal, Program, Stmt, StmtKind, Type, UnaryOp,
+    VarDecl,
+};
+use crate::span::Span;
+use crate::symbol::Symbol;
+use crate::vm::{Chunk, Function, NativeRegistry, OpCode, ScalarType, Value};
+use std::collections::HashSet;
+use std::rc::Rc;
+
+const LOCALS_MAX: usize = u8::MAX as usize + 1;
+
+struct Local {
+    name: Symbol,
+    depth: usize,
+}
+
+// The innermost loops being compiled, for `break` and `continue`.
+struct Loop {
+    // Locals deeper than this are popped when jumping out of the body.
+    scope_depth: usize,
+    // Where `continue` jumps back to, unless the target comes after the body.
+    start: Option<usize>,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+// Per-function state, swapped out while a function body is compiled.
+#[derive(Default)]
+struct FunctionState {
+    chunk: Chunk,
+    locals: Vec<Local>,
+    scope_depth: usize,
+    loops: Vec<Loop>,
+}
+
+/// Lowers a parsed `Program` to bytecode.
+///
+/// The result is a script function that defines every function, then every
+/// global in source order, then calls `main` and halts with its return value.
+pub struct Compiler {
+    state: FunctionState,
+    globals: HashSet<Symbol>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Compiler::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Compiler {
+        Compiler {
+            state: FunctionState::default(),
+            globals: HashSet::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Makes the natives resolvable as globals. The VM running the result
+    /// must define them, see [`crate::vm::Vm::with_natives`].
+    pub fn with_natives(mut self, natives: &NativeRegistry) -> Compiler {
+        self.globals
+            .extend(natives.iter().map(|native| Symbol::intern(&native.name)));
+        self
+    }
+
+    pub fn compile(&mut self, program: &Program) -> (Rc<Function>, Vec<Diagnostic>) {
+        self.compile_items(program);
+        let main = program.items.iter().find_map(|item| match item {
+            Item::Function(function) if function.name == "main" => Some(function.span),
+            _ => None,
+        });
+        match main {
+            Some(span) => {
+                self.emit_global(OpCode::GetGlobal, Symbol::intern("main"), span);
+                self.emit_with_byte(OpCode::Call, 0, span);
+                self.emit(OpCode::Halt, span);
+            }
+            None => self.error(
+                codes::MISSING_MAIN,
+                "`main` function not found",
+                Span::default(),
+            ),
+        }
+        self.finish()
+    }
+
+    /// Compiles a script that only defines the program's globals and
+    /// functions. Globals stay known to later `compile_*` calls, so a REPL
+    /// can keep one `Compiler` and `Vm` across inputs.
+    pub fn compile_declarations(&mut self, program: &Program) -> (Rc<Function>, Vec<Diagnostic>) {
+        self.compile_items(program);
+        self.emit(OpCode::Void, Span::default());
+        self.emit(OpCode::Halt, Span::default());
+        self.finish()
+    }
+
+    /// Compiles statements into a script that runs them directly. A `return`
+    /// ends the script with that value, otherwise it halts with `void`.
+    pub fn compile_statements(&mut self, stmts: &[Stmt]) -> (Rc<Function>, Vec<Diagnostic>) {
+        self.state.scope_depth = 1;
+        for stmt in stmts {
+            self.compile_statement(stmt);
+        }
+        let end = stmts.last().map_or(Span::default(), |stmt| {
+            Span::new(stmt.span.end, stmt.span.end)
+        });
+        self.emit(OpCode::Void, end);
+        self.emit(OpCode::Halt, end);
+        self.finish()
+    }
+
+    fn compile_items(&mut self, program: &Program) {
+        // functions and globals may be referenced before their definition
+        for item in &program.items {
+            let name = match item {
+                Item::Function(function) => function.name,
+                Item::Prototype(prototype) => prototype.name,
+                Item::Global(global) => global.name,
+            };
+            self.globals.insert(name);
+        }
+
+        // functions first, so initializers may call any of them; globals then
+        // follow in source order, each initializer reading only earlier ones
+        for item in &program.items {
+            if let Item::Function(function) = item {
+                let compiled = self.compile_function(function);
+                self.emit_constant(Value::Function(compiled), function.span);
+                self.emit_global(OpCode::DefineGlobal, function.name, function.span);
+            }
+        }
+        for item in &program.items {
+            if let Item::Global(global) = item {
+                self.compile_initializer(global);
+                self.emit_global(OpCode::DefineGlobal, global.name, global.span);
+            }
+        }
+    }
+
+    // Takes the script compiled so far, leaving the compiler ready for the next.
+    fn finish(&mut self) -> (Rc<Function>, Vec<Diagnostic>) {
+        let state = std::mem::take(&mut self.state);
+        let script = Function {
+            name: "<script>".to_string(),
+            arity: 0,
+            chunk: state.chunk,
+        };
+        (Rc::new(script), std::mem::take(&mut self.diagnostics))
+    }
+
+    fn compile_function(&mut self, function: &FunctionDecl) -> Rc<Function> {
+        let arity = u8::try_from(function.params.len()).unwrap_or_else(|_| {
+            self.error(
+                codes::TOO_MANY_PARAMETERS,
+                "a function can have at most 255 parameters",
+                function.span,
+            );
+            u8::MAX
+        });
+        let enclosing = std::mem::take(&mut self.state);
+        self.state.scope_depth = 1;
+        for param in &function.params {
+            self.declare_local(param.name, param.span);
+        }
+
+        for stmt in &function.body {
+            self.compile_statement(stmt);
+        }
+
+        // falling off the end of `main` returns 0, as in C
+        let end = Span::new(function.span.end, function.span.end);
+        if function.name == "main" {
+            self.emit_constant(Value::Int(0), end);
+        } else {
+            self.emit(OpCode::Void, end);
+        }
+        self.emit(OpCode::Return, end);
+
+        let state = std::mem::replace(&mut self.state, enclosing);
+        Rc::new(Function {
+            name: function.name.to_string(),
+            arity,
+            chunk: state.chunk,
+        })
+    }
+
+    fn compile_statement(&mut self, stmt: &Stmt) {
+        let span = stmt.span;
+        match &stmt.kind {
+            StmtKind::VarDecl(decl) => {
+                self.compile_initializer(decl);
+                self.declare_local(decl.name, decl.span);
+            }
+            StmtKind::Assign { target, op, value } => {
+                self.compile_assign(target, *op, value, span);
+                self.emit(OpCode::Pop, span);
+            }
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.compile_expression(condition);
+                let else_jump = self.emit_jump(OpCode::JumpIfFalse, span);
+                self.compile_statement(then_branch);
+                match else_branch {
+                    Some(else_branch) => {
+                        let end_jump = self.emit_jump(OpCode::Jump, span);
+                        self.patch_jump(else_jump, span);
+                        self.compile_statement(else_branch);
+                        self.patch_jump(end_jump, span);
+                    }
+                    None => self.patch_jump(else_jump, span),
+                }
+            }
+            StmtKind::While { condition, body } => {
+                let loop_start = self.state.chunk.len();
+                self.compile_expression(condition);
+                let exit_jump = self.emit_jump(OpCode::JumpIfFalse, span);
+                self.begin_loop(Some(loop_start));
+                self.compile_statement(body);
+                let breaks = self.end_loop(span);
+                self.emit_loop(loop_start, span);
+                self.patch_jump(exit_jump, span);
+                self.patch_jumps(breaks, span);
+            }
+            StmtKind::DoWhile { body, condition } => {
+                let loop_start = self.state.chunk.len();
+                self.begin_loop(None);
+                self.compile_statement(body);
+                let breaks = self.end_loop(span);
+                self.compile_expression(condition);
+                let exit_jump = self.emit_jump(OpCode::JumpIfFalse, span);
+                self.emit_loop(loop_start, span);
+                self.patch_jump(exit_jump, span);
+                self.patch_jumps(breaks, span);
+            }
+            StmtKind::For {
+                init,
+                condition,
+                step,
+                body,
+            } => {
+                self.begin_scope();
+                if let Some(init) = init {
+                    self.compile_statement(init);
+                }
+                let loop_start = self.state.chunk.len();
+                let exit_jump = condition.as_ref().map(|condition| {
+                    self.compile_expression(condition);
+                    self.emit_jump(OpCode::JumpIfFalse, span)
+                });
+                self.begin_loop(None);
+                self.compile_statement(body);
+                let breaks = self.end_loop(span);
+                if let Some(step) = step {
+                    self.compile_statement(step);
+                }
+                self.emit_loop(loop_start, span);
+                if let Some(exit_jump) = exit_jump {
+                    self.patch_jump(exit_jump, span);
+                }
+                self.patch_jumps(breaks, span);
+                self.end_scope(span);
+            }
+            StmtKind::Return(value) => {
+                match value {
+                    Some(value) => self.compile_expression(value),
+                    None => self.emit(OpCode::Void, span),
+                }
+                self.emit(OpCode::Return, span);
+            }
+            StmtKind::Break | StmtKind::Continue => self.compile_jump(stmt),
+            StmtKind::Block(stmts) => {
+                self.begin_scope();
+                for stmt in stmts {
+                    self.compile_statement(stmt);
+                }
+                self.end_scope(span);
+            }
+            StmtKind::Expr(expr) => {
+                self.compile_expression(expr);
+                self.emit(OpCode::Pop, span);
+            }
+        }
+    }
+
+    // Leaves the declared variable's initial value on the stack.
+    fn compile_initializer(&mut self, decl: &VarDecl) {
+        match (&decl.init, default_value(&decl.ty)) {
+            (Some(init), _) => self.compile_expression(init),
+            (None, Some(value)) => self.emit_constant(value, decl.span),
+            (None, None) => self.unsupported("arrays", decl.span),
+        }
+    }
+
+    fn compile_expression(&mut self, expr: &Expr) {
+        let span = expr.span;
+        match &expr.kind {
+            ExprKind::Binary {
+                op: BinaryOp::And,
+                left,
+                right,
+            } => {
+                self.compile_expression(left);
+                let false_jump = self.emit_jump(OpCode::JumpIfFalse, span);
+                self.compile_condition(right, span);
+                let end_jump = self.emit_jump(OpCode::Jump, span);
+                self.patch_jump(false_jump, span);
+                self.emit_constant(Value::Bool(false), span);
+                self.patch_jump(end_jump, span);
+            }
+            ExprKind::Binary {
+                op: BinaryOp::Or,
+                left,
+                right,
+            } => {
+                self.compile_expression(left);
+                let right_jump = self.emit_jump(OpCode::JumpIfFalse, span);
+                self.emit_constant(Value::Bool(true), span);
+                let end_jump = self.emit_jump(OpCode::Jump, span);
+                self.patch_jump(right_jump, span);
+                self.compile_condition(right, span);
+                self.patch_jump(end_jump, span);
+            }
+            ExprKind::Binary { op, left, right } => {
+                self.compile_expression(left);
+                self.compile_expression(right);
+                self.emit(binary_opcode(*op), span);
+            }
+            ExprKind::Unary { op, operand } => {
+                self.compile_expression(operand);
+                let opcode = match op {
+                    UnaryOp::Neg => OpCode::Negate,
+                    UnaryOp::Not => OpCode::Not,
+                    UnaryOp::BitNot => OpCode::BitNot,
+                };
+                self.emit(opcode, span);
+            }
+            ExprKind::Assign { target, op, value } => self.compile_assign(target, *op, value, span),
+            ExprKind::Call { callee, args } => {
+                self.compile_expression(callee);
+                for arg in args {
+                    self.compile_expression(arg);
+                }
+                match u8::try_from(args.len()) {
+                    Ok(argc) => self.emit_with_byte(OpCode::Call, argc, span),
+                    Err(_) => self.error(
+                        codes::TOO_MANY_ARGUMENTS,
+                        "a call can pass at most 255 arguments",
+                        span,
+                    ),
+                }
+            }
+            ExprKind::Literal(literal) => {
+                let value = match literal {
+                    Literal::Int(value) => Value::Int(*value),
+                    Literal::Float(value) => Value::Float(*value),
+                    Literal::Char(value) => Value::Char(*value),
+                    Literal::Bool(value) => Value::Bool(*value),
+                    Literal::String(value) => Value::Str(value.as_ref().into()),
+                };
+                self.emit_constant(value, span);
+            }
+            ExprKind::Var(name) => match self.resolve_local(*name) {
+                Some(slot) => self.emit_with_byte(OpCode::GetLocal, slot, span),
+                None if self.globals.contains(name) => {
+                    self.emit_global(OpCode::GetGlobal, *name, span)
+                }
+                None => self.undefined(*name, span),
+            },
+            ExprKind::Index { .. } => self.unsupported("indexing", span),
+            ExprKind::Convert { to, operand } => {
+                self.compile_expression(operand);
+                // pointers share one representation, only scalars need converting
+                if let Some(to) = scalar_type(to) {
+                    self.emit_with_byte(OpCode::Convert, to as u8, span);
+                }
+            }
+        }
+    }
+
+    // Compiles `expr` and normalizes it to a bool, for the operands of `&&` and `||`.
+    fn compile_condition(&mut self, expr: &Expr, span: Span) {
+        self.compile_expression(expr);
+        self.emit(OpCode::Not, span);
+        self.emit(OpCode::Not, span);
+    }
+
+    // Leaves the assigned value on the stack.
+    fn compile_assign(&mut self, target: &Expr, op: Option<BinaryOp>, value: &Expr, span: Span) {
+        let name = match target.kind {
+            ExprKind::Var(name) => name,
+            _ => return self.unsupported("assignment through indexing", target.span),
+        };
+        let slot = self.resolve_local(name);
+        if slot.is_none() && !self.globals.contains(&name) {
+            return self.undefined(name, target.span);
+        }
+
+        if let Some(op) = op {
+            self.compile_expression(target);
+            self.compile_expression(value);
+            self.emit(binary_opcode(op), span);
+        } else {
+            self.compile_expression(value);
+        }
+
+        match slot {
+            Some(slot) => self.emit_with_byte(OpCode::SetLocal, slot, span),
+            None => self.emit_global(OpCode::SetGlobal, name, span),
+        }
+    }
+
+    fn begin_loop(&mut self, start: Option<usize>) {
+        self.state.loops.push(Loop {
+            scope_depth: self.state.scope_depth,
+            start,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+        });
+    }
+
+    // Ends the innermost loop's body: `continue` jumps land here. Returns the
+    // `break` jumps, to be patched once the loop's exit is known.
+    fn end_loop(&mut self, span: Span) -> Vec<usize> {
+        let finished = self.state.loops.pop().expect("loop to end");
+        self.patch_jumps(finished.continues, span);
+        finished.breaks
+    }
+
+    fn compile_jump(&mut self, stmt: &Stmt) {
+        let span = stmt.span;
+        let Some(innermost) = self.state.loops.last() else {
+            let keyword = if stmt.kind == StmtKind::Break {
+                "break"
+            } else {
+                "continue"
+            };
+            self.error(
+                codes::BREAK_OUTSIDE_LOOP,
+                format!("`{}` outside of a loop", keyword),
+                span,
+            );
+            return;
+        };
+        // leave the scopes of the body without forgetting their locals, the
+        // code after the jump still runs with them
+        let depth = innermost.scope_depth;
+        let start = innermost.start;
+        let body_locals = self
+            .state
+            .locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth > depth)
+            .count();
+        for _ in 0..body_locals {
+            self.emit(OpCode::Pop, span);
+        }
+        match (&stmt.kind, start) {
+            (StmtKind::Continue, Some(start)) => self.emit_loop(start, span),
+            (StmtKind::Continue, None) => {
+                let jump = self.emit_jump(OpCode::Jump, span);
+                self.innermost_loop().continues.push(jump);
+            }
+            _ => {
+                let jump = self.emit_jump(OpCode::Jump, span);
+                self.innermost_loop().breaks.push(jump);
+            }
+        }
+    }
+
+    fn innermost_loop(&mut self) -> &mut Loop {
+        self.state.loops.last_mut().expect("inside a loop")
+    }
+
+    fn begin_scope(&mut self) {
+        self.state.scope_depth += 1;
+    }
+
+    fn end_scope(&mut self, span: Span) {
+        self.state.scope_depth -= 1;
+        while let Some(local) = self.state.locals.last() {
+            if local.depth <= self.state.scope_depth {
+                break;
+            }
+            self.state.locals.pop();
+            self.emit(OpCode::Pop, Span::new(span.end, span.end));
+        }
+    }
+
+    fn declare_local(&mut self, name: Symbol, span: Span) {
+        if self.state.locals.len() == LOCALS_MAX {
+            self.error(
+                codes::TOO_MANY_LOCALS,
+                format!("a function can have at most {} locals", LOCALS_MAX),
+                span,
+            );
+            return;
+        }
+        self.state.locals.push(Local {
+            name,
+            depth: self.state.scope_depth,
+        });
+    }
+
+    fn resolve_local(&self, name: Symbol) -> Option<u8> {
+        self.state
+            .locals
+            .iter()
+            .rposition(|local| local.name == name)
+            .map(|slot| slot as u8)
+    }
+
+    fn emit(&mut self, op: OpCode, span: Span) {
+        self.state.chunk.write_op(op, span);
+    }
+
+    fn emit_with_byte(&mut self, op: OpCode, operand: u8, span: Span) {
+        self.emit(op, span);
+        self.state.chunk.write(operand, span);
+    }
+
+    fn emit_with_u16(&mut self, op: OpCode, operand: u16, span: Span) {
+        self.emit(op, span);
+        self.state.chunk.write_u16(operand, span);
+    }
+
+    fn emit_constant(&mut self, value: Value, span: Span) {
+        let index = self.make_constant(value, span);
+        self.emit_with_u16(OpCode::Constant, index, span);
+    }
+
+    fn emit_global(&mut self, op: OpCode, name: Symbol, span: Span) {
+        let index = self.make_constant(Value::Str(name.as_str().into()), span);
+        self.emit_with_u16(op, index, span);
+    }
+
+    fn make_constant(&mut self, value: Value, span: Span) -> u16 {
+        self.state.chunk.add_constant(value).unwrap_or_else(|| {
+            self.error(
+                codes::TOO_MANY_CONSTANTS,
+                "too many constants in one function",
+                span,
+            );
+            0
+        })
+    }
+
+    // Emits a jump with a placeholder offset and returns the operand's offset.
+    fn emit_jump(&mut self, op: OpCode, span: Span) -> usize {
+        self.emit_with_u16(op, u16::MAX, span);
+        self.state.chunk.len() - 2
+    }
+
+    fn patch_jump(&mut self, operand: usize, span: Span) {
+        let distance = self.state.chunk.len() - operand - 2;
+        let distance = self.jump_distance(distance, span);
+        self.state.chunk.patch_u16(operand, distance);
+    }
+
+    fn patch_jumps(&mut self, operands: Vec<usize>, span: Span) {
+        for operand in operands {
+            self.patch_jump(operand, span);
+        }
+    }
+
+    fn emit_loop(&mut self, loop_start: usize, span: Span) {
+        // the offset is relative to the end of the three byte `Loop` instruction
+        let distance = self.state.chunk.len() + 3 - loop_start;
+        let distance = self.jump_distance(distance, span);
+        self.emit_with_u16(OpCode::Loop, distance, span);
+    }
+
+    fn jump_distance(&mut self, distance: usize, span: Span) -> u16 {
+        u16::try_from(distance).unwrap_or_else(|_| {
+            self.error(codes::JUMP_TOO_LARGE, "too much code to jump over", span);
+            0
+        })
+    }
+
+    fn undefined(&mut self, name: Symbol, span: Span) {
+        self.error(
+            codes::UNDEFINED_NAME,
+            format!("cannot find `{}` in this scope", name),
+            span,
+        );
+    }
+
+    fn unsupported(&mut self, what: &str, span: Span) {
+        self.error(
+            codes::UNSUPPORTED,
+            format!("{} are not supported by the compiler yet", what),
+            span,
+        );
+    }
+
+    fn error(&mut self, code: &'static str, message: impl Into<String>, span: Span) {
+        self.diagnostics
+            .push(Diagnostic::error(code, message, span));
+    }
+}
+
+fn binary_opcode(op: BinaryOp) -> OpCode {
+    match op {
+        BinaryOp::Add => OpCode::Add,
+        BinaryOp::Sub => OpCode::Sub,
+        BinaryOp::Mul => OpCode::Mul,
+        BinaryOp::Div => OpCode::Div,
+        BinaryOp::Mod => OpCode::Mod,
+        BinaryOp::Equal => OpCode::Equal,
+        BinaryOp::NotEqual => OpCode::NotEqual,
+        BinaryOp::Less => OpCode::Less,
+        BinaryOp::LessEqual => OpCode::LessEqual,
+        BinaryOp::Greater => OpCode::Greater,
+        BinaryOp::GreaterEqual => OpCode::GreaterEqual,
+        BinaryOp::BitAnd => OpCode::BitAnd,
+        BinaryOp::BitOr => OpCode::BitOr,
+        BinaryOp::BitXor => OpCode::BitXor,
+        BinaryOp::Shl => OpCode::Shl,
+        BinaryOp::Shr => OpCode::Shr,
+        BinaryOp::And | BinaryOp::Or => unreachable!("short-circuit operators are jumps"),
+    }
+}
+
+// The value of a variable declared without an initializer.
+fn default_value(ty: &Type) -> Option<Value> {
+    match ty {
+        Type::Int | Type::Pointer(_) => Some(Value::Int(0)),
+        Type::Char => Some(Value::Char('\0')),
+        Type::Bool => Some(Value::Bool(false)),
+        Type::Float => Some(Value::Float(0.0)),
+        Type::Void => Some(Value::Void),
+        Type::Array(..) | Type::Function(..) => None,
+    }
+}
+
+fn scalar_type(ty: &Type) -> Option<ScalarType> {
+    match ty {
+        Type::Int => Some(ScalarType::Int),
+        Type::Float => Some(ScalarType::Float),
+        Type::Char => Some(ScalarType::Char),
+        Type::Bool => Some(ScalarType::Bool),
+        _ => None,
+    }
+}
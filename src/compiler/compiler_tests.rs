@@ -0,0 +1,375 @@
+use super::*;
+use crate::diagnostic::{codes, Diagnostic};
+use crate::parser::Parser;
+use crate::semantic::{Resolver, TypeChecker};
+use crate::tokenizer::Tokenizer;
+use crate::vm::{Function, NativeRegistry, RuntimeErrorKind, SharedBuffer, Value, Vm, FRAMES_MAX};
+use std::io::Cursor;
+use std::rc::Rc;
+
+fn compile(input: &str) -> (Rc<Function>, Vec<Diagnostic>) {
+    let (program, diagnostics) = Parser::new(Tokenizer::new(input)).parse();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    Compiler::new().compile(&program)
+}
+
+fn run(input: &str) -> Value {
+    let (script, diagnostics) = compile(input);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    Vm::new().run(script).expect("runtime error")
+}
+
+// Runs `input` as the body of `main`.
+fn run_body(input: &str) -> Value {
+    run(&format!("int main() {{ {} }}", input))
+}
+
+fn compile_err(input: &str) -> Diagnostic {
+    let (_, mut diagnostics) = compile(input);
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    diagnostics.remove(0)
+}
+
+#[test]
+fn test_compile_main() {
+    assert_eq!(
+        run("int main() { int a = 10; return a + 1; }"),
+        Value::Int(11)
+    );
+}
+
+#[test]
+fn test_main_returns_zero_by_default() {
+    assert_eq!(run("int main() { int a = 1; }"), Value::Int(0));
+}
+
+#[test]
+fn test_expressions() {
+    assert_eq!(run_body("return 2 + 3 * 4 - 10 / 5;"), Value::Int(12));
+    assert_eq!(run_body("return -(7 % 4) << 2;"), Value::Int(-12));
+    assert_eq!(run_body("return ~0 ^ 5 | 2 & 3;"), Value::Int(-6));
+    assert_eq!(run_body("return 1 < 2 == 3 >= 3;"), Value::Bool(true));
+    assert_eq!(run_body("return !0;"), Value::Bool(true));
+}
+
+#[test]
+fn test_short_circuit() {
+    // the right-hand side would divide by zero if it were evaluated
+    assert_eq!(
+        run_body("int z = 0; return z && 1 / z;"),
+        Value::Bool(false)
+    );
+    assert_eq!(run_body("int z = 0; return 1 || 1 / z;"), Value::Bool(true));
+    assert_eq!(run_body("return 2 && 3;"), Value::Bool(true));
+    assert_eq!(run_body("return 0 || 0;"), Value::Bool(false));
+}
+
+#[test]
+fn test_assignment() {
+    assert_eq!(
+        run_body("int a = 1; a = a + 1; a += 5; a -= 2; a++; return a;"),
+        Value::Int(6)
+    );
+    assert_eq!(
+        run_body("int a; int b; a = b = 4; return a * b;"),
+        Value::Int(16)
+    );
+}
+
+#[test]
+fn test_if_else() {
+    let program = "int main() { int a = 5; if (a > 3) { a = 1; } else { a = 2; } return a; }";
+    assert_eq!(run(program), Value::Int(1));
+    let program = "int main() { int a = 0; if (a > 3) a = 1; else if (a == 0) a = 7; return a; }";
+    assert_eq!(run(program), Value::Int(7));
+    assert_eq!(
+        run_body("int a = 3; if (0) a = 4; return a;"),
+        Value::Int(3)
+    );
+}
+
+#[test]
+fn test_loops() {
+    assert_eq!(
+        run_body("int i = 0; int sum = 0; while (i < 10) { sum += i; i++; } return sum;"),
+        Value::Int(45)
+    );
+    assert_eq!(
+        run_body(
+            "int sum = 0; for (int i = 1; i <= 5; i++) { int sq = i * i; sum += sq; } return sum;"
+        ),
+        Value::Int(55)
+    );
+    assert_eq!(
+        run_body("int n = 0; for (;;) { n++; if (n == 3) return n; }"),
+        Value::Int(3)
+    );
+}
+
+#[test]
+fn test_do_while_runs_its_body_first() {
+    assert_eq!(
+        run_body("int n = 10; int runs = 0; do { runs++; n++; } while (n < 5); return runs;"),
+        Value::Int(1)
+    );
+    assert_eq!(
+        run_body("int n = 0; do n += 2; while (n < 7); return n;"),
+        Value::Int(8)
+    );
+}
+
+#[test]
+fn test_break_and_continue() {
+    // odd numbers below 10, stopping at 7
+    let body = "\
+int sum = 0;
+for (int i = 0; i < 10; i++) {
+  int odd = i % 2;
+  if (!odd) continue;
+  if (i == 7) break;
+  sum += i;
+}
+return sum;";
+    assert_eq!(run_body(body), Value::Int(1 + 3 + 5));
+
+    let body = "\
+int i = 0; int hits = 0;
+while (1) {
+  i++;
+  int skip = i < 3;
+  if (skip) continue;
+  { int done = i >= 6; if (done) break; }
+  hits++;
+}
+return hits * 100 + i;";
+    assert_eq!(run_body(body), Value::Int(306));
+
+    let body = "\
+int n = 0; int inner = 0;
+do {
+  n++;
+  for (int j = 0; ; j++) { if (j == 2) break; inner++; }
+  if (n < 3) continue;
+  break;
+} while (1);
+return n * 10 + inner;";
+    assert_eq!(run_body(body), Value::Int(36));
+}
+
+#[test]
+fn test_block_scopes() {
+    assert_eq!(
+        run_body("int a = 1; { int a = 2; a = a + 10; } { int b = 5; a += b; } return a;"),
+        Value::Int(6)
+    );
+}
+
+#[test]
+fn test_function_calls() {
+    let program = "
+        int add(int a, int b) { return a + b; }
+        int twice(int x) { return add(x, x); }
+        int main() { return twice(add(1, 2)) * 2; }
+    ";
+    assert_eq!(run(program), Value::Int(12));
+}
+
+#[test]
+fn test_initializers_may_call_later_functions() {
+    let program = "
+        int base = 40;
+        int answer = add_two(base);
+        int add_two(int n) { return n + 2; }
+        int main() { return answer; }
+    ";
+    assert_eq!(run(program), Value::Int(42));
+}
+
+#[test]
+fn test_recursion_and_forward_references() {
+    let program = "
+        int main() { return fib(10); }
+        int fib(int n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+    ";
+    assert_eq!(run(program), Value::Int(55));
+}
+
+#[test]
+fn test_prototypes_allow_mutual_recursion() {
+    let program = "
+        int fact(int n);
+        int is_odd(int n);
+        int is_even(int n) { if (n == 0) return 1; return is_odd(n - 1); }
+        int main() { return fact(5) + is_even(10) * 1000; }
+        int is_odd(int n) { if (n == 0) return 0; return is_even(n - 1); }
+        int fact(int n) { if (n < 2) return 1; return n * fact(n - 1); }
+    ";
+    assert_eq!(run(program), Value::Int(1120));
+}
+
+#[test]
+fn test_max_call_depth() {
+    let program = "
+        int depth(int n) { if (n == 0) return 0; return 1 + depth(n - 1); }
+        int main() { return depth(400); }
+    ";
+    let (script, _) = compile(program);
+    // the default limit is too low, the script and `main` take a frame each
+    let err = Vm::new().run(Rc::clone(&script)).unwrap_err();
+    assert_eq!(err.kind, RuntimeErrorKind::StackOverflow);
+    assert_eq!(err.backtrace.len(), FRAMES_MAX);
+
+    let mut vm = Vm::new().with_max_call_depth(403);
+    assert_eq!(vm.run(Rc::clone(&script)), Ok(Value::Int(400)));
+    let err = Vm::new().with_max_call_depth(402).run(script).unwrap_err();
+    assert_eq!(err.kind, RuntimeErrorKind::StackOverflow);
+}
+
+#[test]
+fn test_standard_library() {
+    let program = "
+        int main() {
+            int n = read_int();
+            int total = 0;
+            for (int i = 0; i < n; i++) total += read_int();
+            printf(\"sum of %d: %d (%x)\\n\", n, total, total);
+            for (int c = getchar(); c != -1; c = getchar()) print_char(c + 1);
+            puts(\"\");
+            exit(total % 7);
+            puts(\"unreachable\");
+            return 0;
+        }
+    ";
+    let natives = NativeRegistry::with_stdlib();
+    let (mut program, diagnostics) = Parser::new(Tokenizer::new(program)).parse();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let mut diagnostics = Resolver::new().with_natives(&natives).resolve(&program);
+    diagnostics.extend(
+        TypeChecker::new()
+            .with_natives(&natives)
+            .check(&mut program),
+    );
+    assert_eq!(diagnostics, vec![]);
+    let (script, diagnostics) = Compiler::new().with_natives(&natives).compile(&program);
+    assert_eq!(diagnostics, vec![]);
+
+    let output = SharedBuffer::default();
+    let mut vm = Vm::new()
+        .with_natives(&natives)
+        .with_output(output.clone())
+        .with_input(Cursor::new("3 10 20 -4\nHAL"));
+    assert_eq!(vm.run(script), Ok(Value::Int(5)));
+    assert_eq!(output.take(), "sum of 3: 26 (1a)\n\x0bIBM\n");
+}
+
+#[test]
+fn test_deep_recursion_has_a_short_backtrace() {
+    let program = "
+        int is_odd(int n);
+        int is_even(int n) { if (n == 0) return 1; return is_odd(n - 1); }
+        int is_odd(int n) { if (n == 0) return 0; return is_even(n - 1); }
+        int main() { return is_even(100000); }
+    ";
+    let (script, _) = compile(program);
+    let err = Vm::new().with_max_call_depth(1000).run(script).unwrap_err();
+    assert_eq!(err.kind, RuntimeErrorKind::StackOverflow);
+    assert_eq!(err.backtrace.len(), 1000);
+    let notes = err.to_diagnostic().notes;
+    assert_eq!(notes.len(), 11);
+    assert_eq!(notes[0], "in function `is_odd`");
+    assert_eq!(notes[4], "in function `is_odd`");
+    assert_eq!(notes[5], "... 990 more frame(s) ...");
+    assert_eq!(
+        notes[6..],
+        [
+            "in function `is_even`",
+            "in function `is_odd`",
+            "in function `is_even`",
+            "in function `main`",
+            "in function `<script>`",
+        ]
+    );
+}
+
+#[test]
+fn test_globals() {
+    let program = "
+        int counter = 10;
+        void bump() { counter += 1; }
+        int main() { bump(); bump(); return counter; }
+    ";
+    assert_eq!(run(program), Value::Int(12));
+}
+
+#[test]
+fn test_runtime_error_points_at_source() {
+    let (script, diagnostics) = compile("int main() {\n  int z = 0;\n  return 1 / z;\n}");
+    assert!(diagnostics.is_empty());
+    let err = Vm::new().run(script).unwrap_err();
+    assert_eq!(err.kind, RuntimeErrorKind::DivisionByZero);
+    assert_eq!(err.line(), 3);
+    assert_eq!(err.span.start.column, 10);
+    assert_eq!(err.backtrace, vec!["main", "<script>"]);
+}
+
+#[test]
+fn test_undefined_name() {
+    let err = compile_err("int main() { return a; }");
+    assert_eq!(err.code, codes::UNDEFINED_NAME);
+    assert_eq!(err.message, "cannot find `a` in this scope");
+    assert_eq!(err.span.start.column, 21);
+
+    let err = compile_err("int main() { { int a = 1; } a += 1; return 0; }");
+    assert_eq!(err.code, codes::UNDEFINED_NAME);
+}
+
+#[test]
+fn test_too_many_parameters() {
+    let function = |count: usize| {
+        let params: Vec<String> = (0..count).map(|i| format!("int p{}", i)).collect();
+        format!(
+            "int f({}) {{ return 0; }} int main() {{ return 0; }}",
+            params.join(", ")
+        )
+    };
+    let (_, diagnostics) = compile(&function(255));
+    assert_eq!(diagnostics, vec![]);
+
+    let err = compile_err(&function(256));
+    assert_eq!(err.code, codes::TOO_MANY_PARAMETERS);
+    assert_eq!(err.message, "a function can have at most 255 parameters");
+}
+
+#[test]
+fn test_missing_main() {
+    let err = compile_err("int f() { return 0; }");
+    assert_eq!(err.code, codes::MISSING_MAIN);
+}
+
+#[test]
+fn test_string_and_char_literals() {
+    assert_eq!(run_body(r"return 'a' + 1;"), Value::Int(98));
+    assert_eq!(run_body(r"return '\n';"), Value::Char('\n'));
+
+    // equal strings share one constant pool entry
+    let (script, _) = compile("int main() { \"hi\\n\"; \"hi\\n\"; \"other\"; return 0; }");
+    let main = script
+        .chunk
+        .constants
+        .iter()
+        .find_map(|value| match value {
+            Value::Function(function) => Some(function),
+            _ => None,
+        })
+        .unwrap();
+    let strings: Vec<&Value> = main
+        .chunk
+        .constants
+        .iter()
+        .filter(|value| matches!(value, Value::Str(_)))
+        .collect();
+    assert_eq!(
+        strings,
+        vec![&Value::Str("hi\n".into()), &Value::Str("other".into())]
+    );
+}
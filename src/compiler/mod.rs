@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+mod compiler;
+#[cfg(test)]
+mod compiler_tests;
+
+pub use compiler::Compiler;
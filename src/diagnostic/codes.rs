@@ -0,0 +1,60 @@
+//! Stable diagnostic codes, grouped by the stage that reports them.
+
+// tokenizer
+pub const UNEXPECTED_CHARACTER: &str = "E0001";
+pub const INTEGER_OVERFLOW: &str = "E0002";
+pub const UNTERMINATED_COMMENT: &str = "E0003";
+pub const INVALID_NUMBER: &str = "E0004";
+pub const UNTERMINATED_LITERAL: &str = "E0005";
+pub const INVALID_ESCAPE: &str = "E0006";
+pub const INVALID_CHAR_LITERAL: &str = "E0007";
+
+// parser
+pub const UNEXPECTED_CLOSING_DELIMITER: &str = "E0100";
+pub const UNCLOSED_DELIMITER: &str = "E0101";
+pub const MISMATCHED_DELIMITER: &str = "E0102";
+pub const UNEXPECTED_TOKEN: &str = "E0103";
+pub const EXPECTED_EXPRESSION: &str = "E0104";
+pub const INVALID_ASSIGNMENT_TARGET: &str = "E0105";
+pub const DECLARATION_AS_BODY: &str = "E0106";
+
+// name resolution
+pub const UNDECLARED_IDENTIFIER: &str = "E0200";
+pub const REDECLARATION: &str = "E0201";
+pub const BREAK_OUTSIDE_LOOP: &str = "E0202";
+pub const UNDEFINED_FUNCTION: &str = "E0203";
+pub const USE_BEFORE_DEFINITION: &str = "E0204";
+
+// types
+pub const MISMATCHED_TYPES: &str = "E0300";
+pub const INVALID_OPERANDS: &str = "E0301";
+pub const WRONG_ARGUMENT_COUNT: &str = "E0302";
+pub const NOT_A_FUNCTION: &str = "E0303";
+pub const INVALID_RETURN: &str = "E0304";
+pub const VOID_VARIABLE: &str = "E0305";
+pub const NOT_ASSIGNABLE: &str = "E0306";
+pub const NOT_INDEXABLE: &str = "E0307";
+pub const CONFLICTING_TYPES: &str = "E0308";
+pub const ARRAYS_UNSUPPORTED: &str = "E0309";
+
+// compiler
+pub const UNDEFINED_NAME: &str = "E0400";
+pub const MISSING_MAIN: &str = "E0401";
+pub const TOO_MANY_LOCALS: &str = "E0402";
+pub const TOO_MANY_CONSTANTS: &str = "E0403";
+pub const TOO_MANY_ARGUMENTS: &str = "E0404";
+pub const JUMP_TOO_LARGE: &str = "E0405";
+pub const UNSUPPORTED: &str = "E0406";
+pub const TOO_MANY_PARAMETERS: &str = "E0407";
+
+// runtime
+pub const DIVISION_BY_ZERO: &str = "E0500";
+pub const STACK_OVERFLOW: &str = "E0501";
+pub const INVALID_BYTECODE: &str = "E0502";
+pub const TYPE_MISMATCH_AT_RUNTIME: &str = "E0503";
+pub const UNDEFINED_GLOBAL: &str = "E0504";
+pub const NOT_CALLABLE: &str = "E0505";
+pub const ARITY_MISMATCH_AT_RUNTIME: &str = "E0506";
+pub const NATIVE_ERROR: &str = "E0507";
+pub const ABORTED: &str = "E0508";
+pub const INVALID_ARGUMENT: &str = "E0509";
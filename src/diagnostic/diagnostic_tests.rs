@@ -0,0 +1,96 @@
+use super::*;
+use crate::span::{Position, Span};
+
+fn span(offset: usize, line: usize, column: usize, len: usize) -> Span {
+    Span::new(
+        Position::new(offset, line, column),
+        Position::new(offset + len, line, column + len),
+    )
+}
+
+#[test]
+fn test_diagnostic_builder() {
+    let diagnostic = Diagnostic::error(codes::UNEXPECTED_CHARACTER, "bad", span(0, 1, 1, 1))
+        .with_label(span(4, 1, 5, 2), "here")
+        .with_note("a note");
+
+    assert!(diagnostic.is_error());
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(diagnostic.labels.len(), 1);
+    assert_eq!(diagnostic.labels[0].message, "here");
+    assert_eq!(diagnostic.notes, vec!["a note".to_string()]);
+    assert_eq!(diagnostic.to_string(), "error[E0001]: bad at 1:1");
+}
+
+#[test]
+fn test_render_primary_span() {
+    let source = "int a;\nint @ = 1;";
+    let diagnostic = Diagnostic::error(
+        codes::UNEXPECTED_CHARACTER,
+        "unexpected character `@`",
+        span(11, 2, 5, 1),
+    );
+
+    let expected = "\
+error[E0001]: unexpected character `@`
+ --> main.c:2:5
+  |
+2 | int @ = 1;
+  |     ^
+";
+    assert_eq!(render(&diagnostic, source, "main.c"), expected);
+}
+
+#[test]
+fn test_render_labels_and_notes() {
+    let source = "int main() {\n  return 10 +;\n}";
+    let diagnostic = Diagnostic::error("E9999", "expected expression", span(25, 2, 13, 1))
+        .with_label(span(22, 2, 10, 2), "left operand")
+        .with_label(span(11, 1, 12, 1), "inside this block")
+        .with_note("operators need two operands");
+
+    let expected = "\
+error[E9999]: expected expression
+ --> test.c:2:13
+  |
+1 | int main() {
+  |            - inside this block
+2 |   return 10 +;
+  |             ^
+  |          -- left operand
+  = note: operators need two operands
+";
+    assert_eq!(render(&diagnostic, source, "test.c"), expected);
+}
+
+#[test]
+fn test_render_expands_tabs() {
+    let source = "\tx @";
+    let diagnostic = Diagnostic::warning("E9999", "odd", span(3, 1, 4, 1));
+
+    let expected = "\
+warning[E9999]: odd
+ --> a.c:1:4
+  |
+1 |     x @
+  |       ^
+";
+    assert_eq!(render(&diagnostic, source, "a.c"), expected);
+}
+
+#[test]
+fn test_render_multi_line_span_underlines_first_line() {
+    let source = "foo(1,\n  2)";
+    let diagnostic = Diagnostic::error(
+        "E9999",
+        "call",
+        Span::new(Position::new(0, 1, 1), Position::new(10, 2, 5)),
+    );
+
+    let rendered = render(&diagnostic, source, "a.c");
+    assert!(
+        rendered.contains("1 | foo(1,\n  | ^^^^^^\n"),
+        "{}",
+        rendered
+    );
+}
@@ -0,0 +1,9 @@
+pub mod codes;
+#[allow(clippy::module_inception)]
+mod diagnostic;
+#[cfg(test)]
+mod diagnostic_tests;
+mod render;
+
+pub use diagnostic::{Diagnostic, Label, Severity};
+pub use render::render;
@@ -0,0 +1,124 @@
+use super::Diagnostic;
+use crate::span::Span;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+const TAB_WIDTH: usize = 4;
+
+struct Annotation<'a> {
+    span: Span,
+    marker: char,
+    message: &'a str,
+}
+
+/// Renders a diagnostic as a rustc-style snippet:
+///
+/// ```text
+/// error[E0001]: unexpected character `@`
+///  --> main.c:2:9
+///   |
+/// 2 |     int @ = 1;
+///   |         ^
+/// ```
+pub fn render(diagnostic: &Diagnostic, source: &str, file_name: &str) -> String {
+    let mut annotations_by_line: BTreeMap<usize, Vec<Annotation>> = BTreeMap::new();
+    annotations_by_line
+        .entry(diagnostic.span.start.line)
+        .or_default()
+        .push(Annotation {
+            span: diagnostic.span,
+            marker: '^',
+            message: "",
+        });
+    for label in &diagnostic.labels {
+        annotations_by_line
+            .entry(label.span.start.line)
+            .or_default()
+            .push(Annotation {
+                span: label.span,
+                marker: '-',
+                message: &label.message,
+            });
+    }
+
+    let last_line = annotations_by_line.keys().last().copied().unwrap_or(1);
+    let gutter = " ".repeat(last_line.to_string().len());
+    let lines: Vec<&str> = source.lines().collect();
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{}[{}]: {}",
+        diagnostic.severity, diagnostic.code, diagnostic.message
+    );
+    let _ = writeln!(
+        out,
+        "{}--> {}:{}:{}",
+        gutter, file_name, diagnostic.span.start.line, diagnostic.span.start.column
+    );
+    let _ = writeln!(out, "{} |", gutter);
+
+    let mut previous_line: Option<usize> = None;
+    for (line, annotations) in &annotations_by_line {
+        if matches!(previous_line, Some(previous) if line - previous > 1) {
+            let _ = writeln!(out, "...");
+        }
+        previous_line = Some(*line);
+
+        let text = lines.get(line - 1).copied().unwrap_or("");
+        let _ = writeln!(
+            out,
+            "{:>width$} | {}",
+            line,
+            expand_tabs(text),
+            width = gutter.len()
+        );
+        for annotation in annotations {
+            let (padding, length) = underline(text, annotation.span);
+            let mut underline_row = format!(
+                "{} | {}{}",
+                gutter,
+                " ".repeat(padding),
+                annotation.marker.to_string().repeat(length)
+            );
+            if !annotation.message.is_empty() {
+                underline_row.push(' ');
+                underline_row.push_str(annotation.message);
+            }
+            let _ = writeln!(out, "{}", underline_row);
+        }
+    }
+
+    for note in &diagnostic.notes {
+        let _ = writeln!(out, "{} = note: {}", gutter, note);
+    }
+    out
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+// Returns the display offset and width of the underline for `span` on its first line.
+fn underline(text: &str, span: Span) -> (usize, usize) {
+    let start = span.start.column - 1;
+    let end = if span.end.line == span.start.line {
+        span.end.column - 1
+    } else {
+        text.chars().count()
+    };
+
+    let prefix: String = text.chars().take(start).collect();
+    let underlined: String = text
+        .chars()
+        .skip(start)
+        .take(end.saturating_sub(start))
+        .collect();
+    (display_width(&prefix), display_width(&underlined).max(1))
+}
@@ -0,0 +1,141 @@
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+usage: uvm <command> [options] <file>
+
+commands:
+    repl                    start an interactive session
+    run <file>              compile and run a C source or .uvmb bytecode file
+    check <file>            report diagnostics without running
+    build <file> [-o <out>] compile to bytecode, written to <out> or <file>.uvmb
+
+options:
+    --emit <stage>          print a stage to stdout: tokens, ast, rpn or bytecode
+    --max-call-depth <n>    limit nested calls when running, 256 by default
+    -h, --help              print this message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Run,
+    Check,
+    Build,
+}
+
+/// A compilation stage to dump with `--emit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    Tokens,
+    Ast,
+    Rpn,
+    Bytecode,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Args {
+    pub command: Command,
+    pub input: PathBuf,
+    pub output: Option<PathBuf>,
+    pub emit: Option<Emit>,
+    pub max_call_depth: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Parsed {
+    Args(Args),
+    Repl,
+    Help,
+}
+
+/// Parses the arguments after the program name.
+pub fn parse_args(args: &[String]) -> Result<Parsed, String> {
+    if args.first().map(String::as_str) == Some("repl") {
+        return match args.get(1).map(String::as_str) {
+            None => Ok(Parsed::Repl),
+            Some("-h" | "--help") => Ok(Parsed::Help),
+            Some(arg) => Err(format!("unexpected argument `{}`", arg)),
+        };
+    }
+
+    let mut command = None;
+    let mut input = None;
+    let mut output = None;
+    let mut emit = None;
+    let mut max_call_depth = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Parsed::Help),
+            "-o" => {
+                let path = args.next().ok_or("`-o` expects a file name")?;
+                output = Some(PathBuf::from(path));
+            }
+            "--emit" => {
+                let stage = args.next().ok_or("`--emit` expects a stage")?;
+                emit = Some(parse_emit(stage)?);
+            }
+            flag if flag.starts_with("--emit=") => {
+                emit = Some(parse_emit(&flag["--emit=".len()..])?);
+            }
+            "--max-call-depth" => {
+                let depth = args.next().ok_or("`--max-call-depth` expects a number")?;
+                max_call_depth = Some(parse_depth(depth)?);
+            }
+            flag if flag.starts_with("--max-call-depth=") => {
+                max_call_depth = Some(parse_depth(&flag["--max-call-depth=".len()..])?);
+            }
+            flag if flag.starts_with('-') && flag != "-" => {
+                return Err(format!("unknown option `{}`", flag));
+            }
+            _ if command.is_none() => {
+                command = Some(match arg.as_str() {
+                    "run" => Command::Run,
+                    "check" => Command::Check,
+                    "build" => Command::Build,
+                    other => return Err(format!("unknown command `{}`", other)),
+                });
+            }
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+
+    let command = command.ok_or("missing command")?;
+    let input = input.ok_or("missing input file")?;
+    if output.is_some() && command != Command::Build {
+        return Err("`-o` is only supported by `build`".to_string());
+    }
+    if max_call_depth.is_some() && command != Command::Run {
+        return Err("`--max-call-depth` is only supported by `run`".to_string());
+    }
+    Ok(Parsed::Args(Args {
+        command,
+        input,
+        output,
+        emit,
+        max_call_depth,
+    }))
+}
+
+fn parse_depth(depth: &str) -> Result<usize, String> {
+    match depth.parse() {
+        Ok(depth) if depth > 0 => Ok(depth),
+        _ => Err(format!(
+            "invalid call depth `{}`, expected a positive number",
+            depth
+        )),
+    }
+}
+
+fn parse_emit(stage: &str) -> Result<Emit, String> {
+    match stage {
+        "tokens" => Ok(Emit::Tokens),
+        "ast" => Ok(Emit::Ast),
+        "rpn" => Ok(Emit::Rpn),
+        "bytecode" => Ok(Emit::Bytecode),
+        other => Err(format!(
+            "unknown stage `{}`, expected tokens, ast, rpn or bytecode",
+            other
+        )),
+    }
+}
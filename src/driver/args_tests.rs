@@ -0,0 +1,121 @@
+use super::args::*;
+use std::path::PathBuf;
+
+fn parse(args: &[&str]) -> Result<Parsed, String> {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    parse_args(&args)
+}
+
+fn parse_ok(args: &[&str]) -> Args {
+    match parse(args) {
+        Ok(Parsed::Args(args)) => args,
+        other => panic!("expected arguments, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_commands() {
+    let args = parse_ok(&["run", "main.c"]);
+    assert_eq!(args.command, Command::Run);
+    assert_eq!(args.input, PathBuf::from("main.c"));
+    assert_eq!(args.output, None);
+    assert_eq!(args.emit, None);
+    assert_eq!(args.max_call_depth, None);
+
+    assert_eq!(parse_ok(&["check", "main.c"]).command, Command::Check);
+}
+
+#[test]
+fn test_build_output() {
+    let args = parse_ok(&["build", "main.c", "-o", "out.uvmb"]);
+    assert_eq!(args.command, Command::Build);
+    assert_eq!(args.output, Some(PathBuf::from("out.uvmb")));
+
+    // options may come before the file
+    let args = parse_ok(&["build", "-o", "out.uvmb", "main.c"]);
+    assert_eq!(args.input, PathBuf::from("main.c"));
+    assert_eq!(args.output, Some(PathBuf::from("out.uvmb")));
+}
+
+#[test]
+fn test_emit() {
+    assert_eq!(
+        parse_ok(&["run", "--emit=tokens", "main.c"]).emit,
+        Some(Emit::Tokens)
+    );
+    assert_eq!(
+        parse_ok(&["check", "main.c", "--emit", "ast"]).emit,
+        Some(Emit::Ast)
+    );
+    assert_eq!(
+        parse_ok(&["build", "main.c", "--emit=rpn"]).emit,
+        Some(Emit::Rpn)
+    );
+    assert_eq!(
+        parse_ok(&["run", "main.c", "--emit=bytecode"]).emit,
+        Some(Emit::Bytecode)
+    );
+}
+
+#[test]
+fn test_max_call_depth() {
+    assert_eq!(
+        parse_ok(&["run", "--max-call-depth", "1000", "main.c"]).max_call_depth,
+        Some(1000)
+    );
+    assert_eq!(
+        parse_ok(&["run", "main.c", "--max-call-depth=8"]).max_call_depth,
+        Some(8)
+    );
+}
+
+#[test]
+fn test_repl() {
+    assert_eq!(parse(&["repl"]), Ok(Parsed::Repl));
+    assert_eq!(
+        parse(&["repl", "main.c"]),
+        Err("unexpected argument `main.c`".to_string())
+    );
+}
+
+#[test]
+fn test_help() {
+    assert_eq!(parse(&["--help"]), Ok(Parsed::Help));
+    assert_eq!(parse(&["run", "-h"]), Ok(Parsed::Help));
+}
+
+#[test]
+fn test_errors() {
+    let cases: [(&[&str], &str); 12] = [
+        (&[], "missing command"),
+        (&["run"], "missing input file"),
+        (&["compile", "main.c"], "unknown command `compile`"),
+        (&["run", "a.c", "b.c"], "unexpected argument `b.c`"),
+        (&["run", "--fast", "a.c"], "unknown option `--fast`"),
+        (&["build", "a.c", "-o"], "`-o` expects a file name"),
+        (
+            &["run", "a.c", "-o", "b"],
+            "`-o` is only supported by `build`",
+        ),
+        (&["run", "a.c", "--emit"], "`--emit` expects a stage"),
+        (
+            &["run", "a.c", "--emit=llvm"],
+            "unknown stage `llvm`, expected tokens, ast, rpn or bytecode",
+        ),
+        (
+            &["run", "a.c", "--max-call-depth"],
+            "`--max-call-depth` expects a number",
+        ),
+        (
+            &["run", "a.c", "--max-call-depth=0"],
+            "invalid call depth `0`, expected a positive number",
+        ),
+        (
+            &["check", "a.c", "--max-call-depth=9"],
+            "`--max-call-depth` is only supported by `run`",
+        ),
+    ];
+    for (args, message) in cases {
+        assert_eq!(parse(args), Err(message.to_string()), "args: {:?}", args);
+    }
+}
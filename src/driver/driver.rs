@@ -0,0 +1,184 @@
+use super::args::{parse_args, Args, Command, Emit, Parsed, USAGE};
+use super::emit::{dump_rpn, dump_tokens};
+use super::repl::Repl;
+use std::fs;
+use std::io;
+use uvm::diagnostic::{codes, render, Diagnostic};
+use uvm::parser::{dump_ast, Parser};
+use uvm::span::Span;
+use uvm::tokenizer::Tokenizer;
+use uvm::vm::{bytecode, FRAMES_MAX};
+use uvm::{Engine, Program};
+
+pub const EXIT_SUCCESS: i32 = 0;
+/// Compile errors, runtime errors and I/O failures.
+pub const EXIT_FAILURE: i32 = 1;
+/// Bad command-line arguments.
+pub const EXIT_USAGE: i32 = 2;
+
+/// Runs the `uvm` command line and returns the process exit status.
+pub fn main(args: &[String]) -> i32 {
+    let args = match parse_args(args) {
+        Ok(Parsed::Args(args)) => args,
+        Ok(Parsed::Repl) => {
+            let mut stdout = io::stdout();
+            return match Repl::new().run(io::stdin().lock(), &mut stdout) {
+                Ok(()) => EXIT_SUCCESS,
+                Err(err) => {
+                    eprintln!("error: {}", err);
+                    EXIT_FAILURE
+                }
+            };
+        }
+        Ok(Parsed::Help) => {
+            println!("{}", USAGE);
+            return EXIT_SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+
+    let mut engine = Engine::new().with_max_call_depth(args.max_call_depth.unwrap_or(FRAMES_MAX));
+    let file_name = args.input.display().to_string();
+    let bytes = match fs::read(&args.input) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("error: cannot read `{}`: {}", file_name, err);
+            return EXIT_FAILURE;
+        }
+    };
+
+    if bytecode::is_bytecode(&bytes) {
+        return match (args.command, args.emit) {
+            (_, Some(Emit::Tokens | Emit::Ast | Emit::Rpn)) => {
+                eprintln!(
+                    "error: `{}` is compiled, only bytecode can be emitted",
+                    file_name
+                );
+                EXIT_FAILURE
+            }
+            (Command::Run, emit) => match Program::from_bytecode(&bytes) {
+                Ok(program) => {
+                    if emit == Some(Emit::Bytecode) {
+                        print!("{}", program.disassemble());
+                    }
+                    execute(&mut engine, &program, None, &file_name)
+                }
+                Err(err) => {
+                    eprintln!("error: invalid bytecode in `{}`: {}", file_name, err);
+                    EXIT_FAILURE
+                }
+            },
+            _ => {
+                eprintln!("error: `{}` is already compiled", file_name);
+                EXIT_FAILURE
+            }
+        };
+    }
+
+    let source = match String::from_utf8(bytes) {
+        Ok(source) => source,
+        Err(_) => {
+            eprintln!("error: `{}` is not valid UTF-8", file_name);
+            return EXIT_FAILURE;
+        }
+    };
+
+    let (program, diagnostics) = compile(&engine, &source, args.emit);
+    report(&diagnostics, &source, &file_name);
+    let program = match program {
+        Some(program) => program,
+        None => return EXIT_FAILURE,
+    };
+
+    match args.command {
+        Command::Check => EXIT_SUCCESS,
+        Command::Run => execute(&mut engine, &program, Some(&source), &file_name),
+        Command::Build => build(&program, &args),
+    }
+}
+
+/// Compiles a program that can be run from `main`, printing the `emit` stage
+/// to stdout as it completes. The program is only produced when no stage
+/// reported an error.
+pub fn compile(
+    engine: &Engine,
+    source: &str,
+    emit: Option<Emit>,
+) -> (Option<Program>, Vec<Diagnostic>) {
+    match emit {
+        Some(Emit::Tokens) => {
+            let (tokens, _) = Tokenizer::new(source).tokenize();
+            print!("{}", dump_tokens(&tokens));
+        }
+        Some(Emit::Ast | Emit::Rpn) => {
+            let (program, _) = Parser::new(Tokenizer::new(source)).parse();
+            match emit {
+                Some(Emit::Ast) => print!("{}", dump_ast(&program)),
+                _ => print!("{}", dump_rpn(&program)),
+            }
+        }
+        _ => {}
+    }
+
+    let program = match engine.compile(source) {
+        Ok(program) => program,
+        Err(diagnostics) => return (None, diagnostics.iter().cloned().collect()),
+    };
+    let mut diagnostics = program.warnings().to_vec();
+    if !program.has_function("main") {
+        diagnostics.push(Diagnostic::error(
+            codes::MISSING_MAIN,
+            "`main` function not found",
+            Span::default(),
+        ));
+        return (None, diagnostics);
+    }
+    if emit == Some(Emit::Bytecode) {
+        print!("{}", program.disassemble());
+    }
+    (Some(program), diagnostics)
+}
+
+fn report(diagnostics: &[Diagnostic], source: &str, file_name: &str) {
+    for diagnostic in diagnostics {
+        eprintln!("{}", render(diagnostic, source, file_name));
+    }
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    if errors > 0 {
+        let plural = if errors == 1 { "" } else { "s" };
+        eprintln!(
+            "error: could not compile `{}` due to {} previous error{}",
+            file_name, errors, plural
+        );
+    }
+}
+
+fn execute(engine: &mut Engine, program: &Program, source: Option<&str>, file_name: &str) -> i32 {
+    match engine.run(program, "main", &[]) {
+        Ok(result) => result.exit_status(),
+        Err(err) => {
+            match source {
+                Some(source) => eprintln!("{}", render(&err.to_diagnostic(), source, file_name)),
+                None => eprintln!("{}", err),
+            }
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn build(program: &Program, args: &Args) -> i32 {
+    let output = match &args.output {
+        Some(output) => output.clone(),
+        None => args.input.with_extension("uvmb"),
+    };
+    match fs::write(&output, program.to_bytecode()) {
+        Ok(()) => EXIT_SUCCESS,
+        Err(err) => {
+            eprintln!("error: cannot write `{}`: {}", output.display(), err);
+            EXIT_FAILURE
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt::Write;
+use uvm::parser::ast::{Expr, ExprKind, Item, Program, Stmt, StmtKind};
+use uvm::parser::{convert_to_rpn, Rpn};
+use uvm::span::Span;
+use uvm::tokenizer::Token;
+
+/// One token per line: span, kind and lexeme.
+pub fn dump_tokens(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for token in tokens {
+        let span = token.span.to_string();
+        let kind = format!("{:?}", token.token_type);
+        let _ = writeln!(out, "{:<14} {:<28} {:?}", span, kind, token.lexeme);
+    }
+    out
+}
+
+/// The reverse Polish form of every expression, one statement per line.
+pub fn dump_rpn(program: &Program) -> String {
+    let mut out = String::new();
+    for item in &program.items {
+        match item {
+            Item::Function(function) => {
+                let _ = writeln!(out, "{}:", function.name);
+                for stmt in &function.body {
+                    dump_stmt(stmt, &mut out);
+                }
+            }
+            Item::Prototype(_) => {}
+            Item::Global(global) => {
+                let label = format!("global {}", global.name);
+                write_rpn(&mut out, global.span, &label, global.init.as_ref());
+            }
+        }
+    }
+    out
+}
+
+fn dump_stmt(stmt: &Stmt, out: &mut String) {
+    let span = stmt.span;
+    match &stmt.kind {
+        StmtKind::VarDecl(decl) => {
+            let label = format!("decl {}", decl.name);
+            write_rpn(out, span, &label, decl.init.as_ref());
+        }
+        StmtKind::Assign { target, op, value } => {
+            let assign = Expr::new(
+                ExprKind::Assign {
+                    target: Box::new(target.clone()),
+                    op: *op,
+                    value: Box::new(value.clone()),
+                },
+                span,
+            );
+            write_rpn(out, span, "assign", Some(&assign));
+        }
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            write_rpn(out, span, "if", Some(condition));
+            dump_stmt(then_branch, out);
+            if let Some(else_branch) = else_branch {
+                dump_stmt(else_branch, out);
+            }
+        }
+        StmtKind::While { condition, body } => {
+            write_rpn(out, span, "while", Some(condition));
+            dump_stmt(body, out);
+        }
+        StmtKind::DoWhile { body, condition } => {
+            dump_stmt(body, out);
+            write_rpn(out, span, "do-while", Some(condition));
+        }
+        StmtKind::For {
+            init,
+            condition,
+            step,
+            body,
+        } => {
+            if let Some(init) = init {
+                dump_stmt(init, out);
+            }
+            write_rpn(out, span, "for", condition.as_ref());
+            dump_stmt(body, out);
+            if let Some(step) = step {
+                dump_stmt(step, out);
+            }
+        }
+        StmtKind::Return(value) => write_rpn(out, span, "return", value.as_ref()),
+        StmtKind::Break => write_rpn(out, span, "break", None),
+        StmtKind::Continue => write_rpn(out, span, "continue", None),
+        StmtKind::Block(stmts) => {
+            for stmt in stmts {
+                dump_stmt(stmt, out);
+            }
+        }
+        StmtKind::Expr(expr) => write_rpn(out, span, "expr", Some(expr)),
+    }
+}
+
+fn write_rpn(out: &mut String, span: Span, label: &str, expr: Option<&Expr>) {
+    let rpn: Vec<String> = expr
+        .map(convert_to_rpn)
+        .unwrap_or_default()
+        .iter()
+        .map(Rpn::to_string)
+        .collect();
+    let location = span.start.to_string();
+    let _ = writeln!(out, "  {:<7} {:<12} {}", location, label, rpn.join(" "));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use uvm::parser::Parser;
+    use uvm::tokenizer::Tokenizer;
+
+    #[test]
+    fn dumps_tokens_one_per_line() {
+        let (tokens, _) = Tokenizer::new("int a;").tokenize();
+        let expected = "\
+1:1-1:4        Keyword(Int)                 \"int\"
+1:5-1:6        Identifier(\"a\")              \"a\"
+1:6-1:7        Punctuation(Semicolon)       \";\"
+1:7-1:7        Eof                          \"\"
+";
+        assert_eq!(dump_tokens(&tokens), expected);
+    }
+
+    #[test]
+    fn dumps_rpn_per_statement() {
+        let source =
+            "int main() {\n  int a = 1 + 2 * 3;\n  while (a) a -= 1;\n  return f(a, -1);\n}";
+        let (program, _) = Parser::new(Tokenizer::new(source)).parse();
+        let expected = "\
+main:
+  2:3     decl a       1 2 3 * +
+  3:3     while        a
+  3:13    assign       a 1 -=
+  4:3     return       f a 1 neg call/2
+";
+        assert_eq!(dump_rpn(&program), expected);
+    }
+}
@@ -0,0 +1,11 @@
+mod args;
+#[cfg(test)]
+mod args_tests;
+#[allow(clippy::module_inception)]
+mod driver;
+mod emit;
+mod repl;
+#[cfg(test)]
+mod repl_tests;
+
+pub use driver::main;
@@ -0,0 +1,240 @@
+use super::emit::dump_tokens;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+use uvm::compiler::Compiler;
+use uvm::diagnostic::{render, Diagnostic};
+use uvm::parser::ast::{Expr, Program, Stmt, StmtKind};
+use uvm::parser::{dump_ast, dump_stmts, Parser};
+use uvm::semantic::{Resolver, TypeChecker};
+use uvm::tokenizer::{Punctuation, Token, TokenType, Tokenizer};
+use uvm::vm::{disassemble, Function, NativeRegistry, Value, Vm};
+
+const PROMPT: &str = "uvm> ";
+const CONTINUATION: &str = "...> ";
+const FILE_NAME: &str = "<repl>";
+
+const HELP: &str = "\
+Enter declarations, statements or expressions. Globals persist between inputs.
+
+:tokens     show the tokens of the last input
+:ast        show the syntax tree of the last input
+:bytecode   show the bytecode of the last input
+:help       show this message
+:quit       leave the REPL";
+
+enum Input<'src> {
+    /// Globals and functions, starting with a type.
+    Items(Program<'src>),
+    /// A lone expression, whose value is printed.
+    Expr(Expr<'src>),
+    Statements(Vec<Stmt<'src>>),
+}
+
+// The most recent input, kept for the meta commands. Tokens and syntax trees
+// borrow from the source, so those dumps are rebuilt from it on demand.
+struct Last {
+    source: String,
+    script: Option<Rc<Function>>,
+}
+
+/// An interactive session. The resolver, type checker, compiler and VM are
+/// reused across inputs so globals declared in one are visible to the next.
+pub struct Repl {
+    resolver: Resolver,
+    checker: TypeChecker,
+    compiler: Compiler,
+    vm: Vm,
+    last: Option<Last>,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Repl::new()
+    }
+}
+
+impl Repl {
+    /// A session with the standard library, printing to standard out.
+    pub fn new() -> Repl {
+        Repl::with_vm(Vm::new(), &NativeRegistry::with_stdlib())
+    }
+
+    /// A session running inputs on `vm`, with `natives` callable from them.
+    pub fn with_vm(vm: Vm, natives: &NativeRegistry) -> Repl {
+        Repl {
+            resolver: Resolver::new().with_natives(natives),
+            checker: TypeChecker::new().with_natives(natives),
+            compiler: Compiler::new().with_natives(natives),
+            vm: vm.with_natives(natives),
+            last: None,
+        }
+    }
+
+    /// Reads inputs until end of file or `:quit`. An input continues over
+    /// several lines while it has unclosed delimiters.
+    pub fn run(&mut self, input: impl BufRead, out: &mut impl Write) -> io::Result<()> {
+        let mut lines = input.lines();
+        let mut buffer = String::new();
+        loop {
+            let prompt = if buffer.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION
+            };
+            write!(out, "{}", prompt)?;
+            out.flush()?;
+
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => break,
+            };
+            if buffer.is_empty() {
+                let trimmed = line.trim();
+                if trimmed.is_empty() {
+                    continue;
+                }
+                if trimmed.starts_with(':') {
+                    if !self.meta_command(trimmed, out)? {
+                        return Ok(());
+                    }
+                    continue;
+                }
+            }
+
+            buffer.push_str(&line);
+            buffer.push('\n');
+            if !has_unclosed_delimiters(&buffer) {
+                let source = std::mem::take(&mut buffer);
+                self.eval(&source, out)?;
+            }
+        }
+        writeln!(out)
+    }
+
+    /// Compiles and runs one complete input, printing its value or diagnostics.
+    pub fn eval(&mut self, source: &str, out: &mut impl Write) -> io::Result<()> {
+        let (tokens, mut diagnostics) = Tokenizer::new(source).tokenize();
+        let mut input = parse_input(&tokens, &mut diagnostics);
+
+        let mut script = None;
+        if !diagnostics.iter().any(Diagnostic::is_error) {
+            diagnostics.extend(match &input {
+                Input::Items(program) => self.resolver.resolve_declarations(program),
+                Input::Expr(expr) => {
+                    let stmt = Stmt::new(StmtKind::Expr(expr.clone()), expr.span);
+                    self.resolver.resolve_statements(&[stmt])
+                }
+                Input::Statements(stmts) => self.resolver.resolve_statements(stmts),
+            });
+        }
+        if !diagnostics.iter().any(Diagnostic::is_error) {
+            diagnostics.extend(match &mut input {
+                Input::Items(program) => self.checker.check_declarations(program),
+                Input::Expr(expr) => self.checker.check_expression(expr),
+                Input::Statements(stmts) => self.checker.check_statements(stmts),
+            });
+        }
+        if !diagnostics.iter().any(Diagnostic::is_error) {
+            let (compiled, compile_diagnostics) = match &input {
+                Input::Items(program) => self.compiler.compile_declarations(program),
+                Input::Expr(expr) => {
+                    let stmt = Stmt::new(StmtKind::Return(Some(expr.clone())), expr.span);
+                    self.compiler.compile_statements(&[stmt])
+                }
+                Input::Statements(stmts) => self.compiler.compile_statements(stmts),
+            };
+            diagnostics.extend(compile_diagnostics);
+            if !diagnostics.iter().any(Diagnostic::is_error) {
+                script = Some(compiled);
+            }
+        }
+
+        for diagnostic in &diagnostics {
+            writeln!(out, "{}", render(diagnostic, source, FILE_NAME))?;
+        }
+        if let Some(script) = &script {
+            match self.vm.run(Rc::clone(script)) {
+                Ok(Value::Void) => {}
+                Ok(value) => writeln!(out, "{}", value)?,
+                Err(err) => writeln!(out, "{}", render(&err.to_diagnostic(), source, FILE_NAME))?,
+            }
+        }
+
+        self.last = Some(Last {
+            source: source.to_string(),
+            script,
+        });
+        Ok(())
+    }
+
+    // Returns false when the session should end.
+    fn meta_command(&mut self, command: &str, out: &mut impl Write) -> io::Result<bool> {
+        match command {
+            ":quit" | ":q" => return Ok(false),
+            ":help" => writeln!(out, "{}", HELP)?,
+            ":tokens" | ":ast" | ":bytecode" => match &self.last {
+                None => writeln!(out, "no input yet")?,
+                Some(last) => {
+                    let (tokens, _) = Tokenizer::new(&last.source).tokenize();
+                    let dump = match command {
+                        ":tokens" => dump_tokens(&tokens),
+                        ":ast" => match parse_input(&tokens, &mut Vec::new()) {
+                            Input::Items(program) => dump_ast(&program),
+                            Input::Expr(expr) => {
+                                let span = expr.span;
+                                dump_stmts(&[Stmt::new(StmtKind::Expr(expr), span)])
+                            }
+                            Input::Statements(stmts) => dump_stmts(&stmts),
+                        },
+                        _ => match &last.script {
+                            Some(script) => disassemble(script),
+                            None => "the last input did not compile\n".to_string(),
+                        },
+                    };
+                    write!(out, "{}", dump)?;
+                }
+            },
+            other => writeln!(out, "unknown command `{}`, try :help", other)?,
+        }
+        Ok(true)
+    }
+}
+
+// Declarations start with a type; otherwise the input is an expression if it
+// parses as one in full, and a list of statements if not. Inputs are short, so
+// the tokens are kept to parse them a second time.
+fn parse_input<'src>(tokens: &[Token<'src>], diagnostics: &mut Vec<Diagnostic>) -> Input<'src> {
+    let mut parser = Parser::new(tokens.iter().cloned().map(Ok));
+    if parser.is_type_start() {
+        let (program, parse_diagnostics) = parser.parse();
+        diagnostics.extend(parse_diagnostics);
+        return Input::Items(program);
+    }
+    if let Ok(expr) = parser.parse_expression() {
+        if parser.is_at_end() {
+            return Input::Expr(expr);
+        }
+    }
+    let (stmts, parse_diagnostics) = Parser::new(tokens.iter().cloned().map(Ok)).parse_statements();
+    diagnostics.extend(parse_diagnostics);
+    Input::Statements(stmts)
+}
+
+fn has_unclosed_delimiters(source: &str) -> bool {
+    let (tokens, _) = Tokenizer::new(source).tokenize();
+    let mut depth = 0i32;
+    for token in &tokens {
+        if let TokenType::Punctuation(punctuation) = token.token_type {
+            match punctuation {
+                Punctuation::LeftBrace | Punctuation::LeftParen | Punctuation::LeftBracket => {
+                    depth += 1
+                }
+                Punctuation::RightBrace | Punctuation::RightParen | Punctuation::RightBracket => {
+                    depth -= 1
+                }
+                _ => {}
+            }
+        }
+    }
+    depth > 0
+}
@@ -0,0 +1,115 @@
+use super::repl::Repl;
+use std::io;
+use uvm::parser::ast::Type;
+use uvm::vm::{NativeRegistry, Signature, Value, Vm};
+
+// Feeds `input` to a fresh session and returns everything it printed.
+fn session(input: &str) -> String {
+    let mut out = Vec::new();
+    Repl::new().run(input.as_bytes(), &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+// The session output without prompts, one entry per printed line.
+fn outputs(input: &str) -> Vec<String> {
+    session(input)
+        .lines()
+        .map(|line| {
+            line.trim_start_matches("uvm> ")
+                .trim_start_matches("...> ")
+                .to_string()
+        })
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+#[test]
+fn test_expressions_print_their_value() {
+    assert_eq!(outputs("1 + 2 * 3\n7 / 2\n1 < 2\n"), vec!["7", "3", "true"]);
+}
+
+#[test]
+fn test_globals_persist() {
+    let input = "int x = 40;\nx = x + 1;\nx + 1\nint twice(int n) { return n * 2; }\ntwice(x)\n";
+    assert_eq!(outputs(input), vec!["42", "82"]);
+}
+
+#[test]
+fn test_statements() {
+    let input = "int sum = 0;\nfor (int i = 1; i <= 4; i++) sum += i;\nsum\n";
+    assert_eq!(outputs(input), vec!["10"]);
+}
+
+#[test]
+fn test_implicit_conversions() {
+    let input = "1.5 * 2\nchar c = 'a';\nc += 1;\nc\nint n = 7.9;\nn\nint bad = \"x\";\n";
+    let out = outputs(input);
+    assert_eq!(out[..3], ["3.0", "'b'", "7"]);
+    assert!(
+        out[3].contains("mismatched types: expected `int`, found `char*`"),
+        "{:?}",
+        out
+    );
+}
+
+#[test]
+fn test_natives() {
+    let mut natives = NativeRegistry::with_stdlib();
+    natives.register("answer", Signature::new(Type::Int, vec![]), |_, _| {
+        Ok(Value::Int(42))
+    });
+    // what the natives print is discarded, only results are shown
+    let mut repl = Repl::with_vm(Vm::new().with_output(io::sink()), &natives);
+    let input =
+        "answer() + 1\nprintf(\"%d\\n\", 12)\nint puts(char *s) { return 7; }\nputs(\"x\")\n";
+    let mut out = Vec::new();
+    repl.run(input.as_bytes(), &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(
+        out.ends_with("uvm> 43\nuvm> 3\nuvm> uvm> 7\nuvm> \n"),
+        "{}",
+        out
+    );
+}
+
+#[test]
+fn test_multi_line_input() {
+    let input =
+        "int fact(int n) {\n  if (n < 2) return 1;\n  return n * fact(n - 1);\n}\nfact(5)\n";
+    let out = session(input);
+    assert_eq!(out.matches("...> ").count(), 3);
+    assert!(out.ends_with("uvm> 120\nuvm> \n"), "{}", out);
+}
+
+#[test]
+fn test_errors_do_not_end_the_session() {
+    let out = session("1 / 0\ny + 1\n@\n2\n");
+    assert!(out.contains("error[E0500]: division by zero"), "{}", out);
+    assert!(out.contains("cannot find `y` in this scope"), "{}", out);
+    assert!(out.contains("unexpected character `@`"), "{}", out);
+    assert!(out.contains("--> <repl>:1:1"), "{}", out);
+    assert!(out.ends_with("uvm> 2\nuvm> \n"), "{}", out);
+}
+
+#[test]
+fn test_meta_commands() {
+    let out = session(":tokens\n1 + x\nint x = 2;\n:tokens\n:ast\n:bytecode\n:nope\n:quit\n3\n");
+    assert!(out.contains("no input yet"), "{}", out);
+    assert!(out.contains("Keyword(Int)"), "{}", out);
+    assert!(out.contains("Global x: int\n  Literal 2\n"), "{}", out);
+    assert!(out.contains("DefineGlobal"), "{}", out);
+    assert!(
+        out.contains("unknown command `:nope`, try :help"),
+        "{}",
+        out
+    );
+    // nothing runs after `:quit`
+    assert!(!out.contains("\n3\n"), "{}", out);
+}
+
+#[test]
+fn test_meta_commands_after_a_failed_compile() {
+    let out = session("y\n:ast\n:bytecode\n");
+    assert!(out.contains("ExprStmt\n  Var y\n"), "{}", out);
+    assert!(out.contains("the last input did not compile"), "{}", out);
+}
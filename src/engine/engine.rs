@@ -0,0 +1,322 @@
+use crate::compiler::Compiler;
+use crate::diagnostic::{render, Diagnostic};
+use crate::parser::ast::{Item, Type};
+use crate::parser::Parser;
+use crate::semantic::{Resolver, TypeChecker};
+use crate::span::Span;
+use crate::tokenizer::Tokenizer;
+use crate::vm::bytecode::{self, DecodeError};
+use crate::vm::{
+    disassemble, Function, NativeContext, NativeRegistry, NativeResult, RuntimeError,
+    RuntimeErrorKind, Signature, Value, Vm,
+};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+/// Compiles and runs programs, with the standard library and any host
+/// functions registered on it callable from C.
+///
+/// The engine keeps one VM whose globals can be read back once a run is done.
+/// Each run starts from globals holding only the registered natives, so
+/// nothing one program defines is seen by the next.
+///
+/// Natives and values are reference counted with `Rc`, so neither an engine
+/// nor a [`Program`] is `Send`: both stay on the thread that created them.
+pub struct Engine {
+    natives: NativeRegistry,
+    vm: Vm,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::new()
+    }
+}
+
+impl Engine {
+    /// An engine with the standard library, printing to standard out and
+    /// reading from standard in.
+    pub fn new() -> Engine {
+        Engine::with_natives(NativeRegistry::with_stdlib())
+    }
+
+    /// An engine whose programs may only call `natives`.
+    pub fn with_natives(natives: NativeRegistry) -> Engine {
+        let vm = Vm::new().with_natives(&natives);
+        Engine { natives, vm }
+    }
+
+    /// Limits how many calls may be active at once, see [`Vm::with_max_call_depth`].
+    pub fn with_max_call_depth(self, depth: usize) -> Engine {
+        self.map_vm(|vm| vm.with_max_call_depth(depth))
+    }
+
+    /// Sends what programs print to `output` instead of standard out.
+    pub fn with_output(self, output: impl Write + 'static) -> Engine {
+        self.map_vm(|vm| vm.with_output(output))
+    }
+
+    /// Makes programs read from `input` instead of standard in.
+    pub fn with_input(self, input: impl BufRead + 'static) -> Engine {
+        self.map_vm(|vm| vm.with_input(input))
+    }
+
+    fn map_vm(mut self, f: impl FnOnce(Vm) -> Vm) -> Engine {
+        self.vm = f(self.vm);
+        self
+    }
+
+    /// Makes a host function callable from programs compiled afterwards,
+    /// replacing any native of the same name.
+    pub fn register(
+        &mut self,
+        name: &str,
+        signature: Signature,
+        function: impl Fn(&mut NativeContext, &[Value]) -> NativeResult + 'static,
+    ) -> &mut Engine {
+        self.natives.register(name, signature, function);
+        self
+    }
+
+    pub fn natives(&self) -> &NativeRegistry {
+        &self.natives
+    }
+
+    /// Runs every stage from tokenizing to code generation, stopping after
+    /// the first one that reports an error. A program needs no `main`, any of
+    /// its functions can be the entry point of a run.
+    ///
+    /// Identifiers are interned for the life of the process, see
+    /// [`Symbol`](crate::symbol::Symbol): compiling keeps every new name in
+    /// memory for good, however many sources the engine sees.
+    pub fn compile(&self, source: &str) -> Result<Program, Diagnostics> {
+        let failed = |diagnostics| Diagnostics {
+            diagnostics,
+            source: source.to_string(),
+        };
+
+        let (mut program, mut diagnostics) = Parser::new(Tokenizer::new(source)).parse();
+        if diagnostics.iter().any(Diagnostic::is_error) {
+            return Err(failed(diagnostics));
+        }
+
+        diagnostics.extend(
+            Resolver::new()
+                .with_natives(&self.natives)
+                .resolve(&program),
+        );
+        if diagnostics.iter().any(Diagnostic::is_error) {
+            return Err(failed(diagnostics));
+        }
+
+        diagnostics.extend(
+            TypeChecker::new()
+                .with_natives(&self.natives)
+                .check(&mut program),
+        );
+        if diagnostics.iter().any(Diagnostic::is_error) {
+            return Err(failed(diagnostics));
+        }
+
+        let (script, compile_diagnostics) = Compiler::new()
+            .with_natives(&self.natives)
+            .compile_declarations(&program);
+        diagnostics.extend(compile_diagnostics);
+        if diagnostics.iter().any(Diagnostic::is_error) {
+            return Err(failed(diagnostics));
+        }
+        let params = program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Function(function) => Some((
+                    function.name.as_str().to_string(),
+                    function
+                        .params
+                        .iter()
+                        .map(|param| param.ty.clone())
+                        .collect(),
+                )),
+                _ => None,
+            })
+            .collect();
+        Ok(Program {
+            script,
+            params,
+            warnings: diagnostics,
+        })
+    }
+
+    /// Resets the globals to the natives and defines the program's own, then
+    /// calls its function `entry` with `args` and yields the result.
+    ///
+    /// Arguments are checked against the entry's parameters before anything
+    /// runs. Only their number is known for a program loaded from bytecode,
+    /// which is passed its arguments as they are.
+    pub fn run(
+        &mut self,
+        program: &Program,
+        entry: &str,
+        args: &[Value],
+    ) -> Result<Value, RuntimeError> {
+        program.check_arguments(entry, args)?;
+        self.vm.clear_globals();
+        self.vm.define_natives(&self.natives);
+        self.vm.run(Rc::clone(&program.script))?;
+        self.vm.call_global(entry, args)
+    }
+
+    /// The value of a global as the last run left it.
+    pub fn global(&self, name: &str) -> Option<&Value> {
+        self.vm.global(name)
+    }
+}
+
+/// A compiled program, independent of the engine that compiled it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    /// Defines every global and function, then halts.
+    script: Rc<Function>,
+    /// The parameter types of each function, empty when loaded from bytecode.
+    params: HashMap<String, Vec<Type>>,
+    warnings: Vec<Diagnostic>,
+}
+
+impl Program {
+    /// Loads a program written by [`Program::to_bytecode`].
+    pub fn from_bytecode(bytes: &[u8]) -> Result<Program, DecodeError> {
+        Ok(Program {
+            script: bytecode::decode(bytes)?,
+            params: HashMap::new(),
+            warnings: Vec::new(),
+        })
+    }
+
+    pub fn to_bytecode(&self) -> Vec<u8> {
+        bytecode::encode(&self.script)
+    }
+
+    /// Whether the program defines a function `name`, which it can be run from.
+    pub fn has_function(&self, name: &str) -> bool {
+        self.function(name).is_some()
+    }
+
+    fn function(&self, name: &str) -> Option<&Function> {
+        // the script loads each function it defines from its constant pool
+        self.script
+            .chunk
+            .constants
+            .iter()
+            .find_map(|constant| match constant {
+                Value::Function(function) if function.name == name => Some(function.as_ref()),
+                _ => None,
+            })
+    }
+
+    fn check_arguments(&self, entry: &str, args: &[Value]) -> Result<(), RuntimeError> {
+        let error = |kind| RuntimeError {
+            kind,
+            span: Span::default(),
+            backtrace: Vec::new(),
+        };
+        let function = self
+            .function(entry)
+            .ok_or_else(|| error(RuntimeErrorKind::UndefinedGlobal(entry.to_string())))?;
+        if args.len() != function.arity as usize {
+            return Err(error(RuntimeErrorKind::ArityMismatch {
+                name: entry.to_string(),
+                expected: function.arity as usize,
+                found: args.len(),
+            }));
+        }
+        let params = self.params.get(entry).map(Vec::as_slice).unwrap_or(&[]);
+        for (index, (param, arg)) in params.iter().zip(args).enumerate() {
+            if !has_type(arg, param) {
+                return Err(error(RuntimeErrorKind::InvalidArgument {
+                    name: entry.to_string(),
+                    index,
+                    expected: param.to_string(),
+                    found: arg.type_name(),
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Diagnostics that did not stop compilation.
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    /// A listing of the program's bytecode, function by function.
+    pub fn disassemble(&self) -> String {
+        disassemble(&self.script)
+    }
+}
+
+/// Whether `value` is what the compiled code expects of a parameter of type `ty`.
+fn has_type(value: &Value, ty: &Type) -> bool {
+    match (ty, value) {
+        (Type::Int, Value::Int(_))
+        | (Type::Float, Value::Float(_))
+        | (Type::Char, Value::Char(_))
+        | (Type::Bool, Value::Bool(_)) => true,
+        (Type::Pointer(pointee), Value::Str(_)) => **pointee == Type::Char,
+        _ => false,
+    }
+}
+
+/// Everything a failed compilation reported, along with the source it
+/// points into.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+    source: String,
+}
+
+impl Diagnostics {
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.iter().filter(|d| d.is_error()).count()
+    }
+
+    /// Renders each diagnostic with the lines it points at, the source being
+    /// called `file_name`.
+    pub fn render(&self, file_name: &str) -> String {
+        let rendered: Vec<String> = self
+            .iter()
+            .map(|diagnostic| render(diagnostic, &self.source, file_name))
+            .collect();
+        rendered.join("\n")
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.render("<source>"))
+    }
+}
+
+impl std::error::Error for Diagnostics {}
@@ -0,0 +1,242 @@
+use super::*;
+use crate::diagnostic::codes;
+use crate::parser::ast::Type;
+use crate::vm::{NativeRegistry, RuntimeErrorKind, SharedBuffer, Signature, Value};
+use std::cell::RefCell;
+use std::io::Cursor;
+use std::rc::Rc;
+
+#[test]
+fn test_run_any_function_with_arguments() {
+    let source = "
+        int calls;
+        float scale = 1.5;
+        float area(int w, int h) { calls++; return w * h * scale; }
+    ";
+    let mut engine = Engine::new();
+    let program = engine.compile(source).unwrap();
+    assert!(program.has_function("area"));
+    assert!(!program.has_function("main"));
+
+    let args = [Value::Int(2), Value::Int(3)];
+    assert_eq!(engine.run(&program, "area", &args), Ok(Value::Float(9.0)));
+    assert_eq!(engine.global("calls"), Some(&Value::Int(1)));
+    // each run starts from the program's initial globals
+    engine.run(&program, "area", &args).unwrap();
+    assert_eq!(engine.global("calls"), Some(&Value::Int(1)));
+    assert_eq!(engine.global("missing"), None);
+}
+
+#[test]
+fn test_programs_do_not_share_globals() {
+    let output = SharedBuffer::default();
+    let mut engine = Engine::new().with_output(output.clone());
+    let first = engine
+        .compile(
+            "int count = 1; int puts(char *s) { return count; } int main() { return puts(\"a\"); }",
+        )
+        .unwrap();
+    let second = engine
+        .compile("int main() { puts(\"b\"); return 0; }")
+        .unwrap();
+
+    assert_eq!(engine.run(&first, "main", &[]), Ok(Value::Int(1)));
+    assert_eq!(engine.global("count"), Some(&Value::Int(1)));
+    // the standard `puts` is back and `count` is gone
+    assert_eq!(engine.run(&second, "main", &[]), Ok(Value::Int(0)));
+    assert_eq!(output.take(), "b\n");
+    assert_eq!(engine.global("count"), None);
+}
+
+#[test]
+fn test_runtime_errors() {
+    let mut engine = Engine::new().with_max_call_depth(16);
+    let program = engine
+        .compile(
+            "int down(int n) { return down(n + 1); }\nint div(int a, int b) {\n  return a / b;\n}",
+        )
+        .unwrap();
+
+    let err = engine
+        .run(&program, "div", &[Value::Int(1), Value::Int(0)])
+        .unwrap_err();
+    assert_eq!(err.kind, RuntimeErrorKind::DivisionByZero);
+    assert_eq!(err.line(), 3);
+    assert_eq!(err.backtrace, vec!["div"]);
+
+    let err = engine.run(&program, "down", &[Value::Int(0)]).unwrap_err();
+    assert_eq!(err.kind, RuntimeErrorKind::StackOverflow);
+    assert_eq!(err.backtrace.len(), 16);
+
+    assert_eq!(
+        engine.run(&program, "up", &[]).unwrap_err().kind,
+        RuntimeErrorKind::UndefinedGlobal("up".to_string())
+    );
+    assert_eq!(
+        engine.run(&program, "div", &[]).unwrap_err().kind,
+        RuntimeErrorKind::ArityMismatch {
+            name: "div".to_string(),
+            expected: 2,
+            found: 0
+        }
+    );
+}
+
+#[test]
+fn test_arguments_are_checked_before_running() {
+    let output = SharedBuffer::default();
+    let mut engine = Engine::new().with_output(output.clone());
+    let program = engine
+        .compile("int ran = puts(\"ran\"); float half(float x) { return x / 2.0; }")
+        .unwrap();
+
+    let err = engine.run(&program, "half", &[Value::Int(3)]).unwrap_err();
+    assert_eq!(
+        err.kind,
+        RuntimeErrorKind::InvalidArgument {
+            name: "half".to_string(),
+            index: 0,
+            expected: "float".to_string(),
+            found: "int"
+        }
+    );
+    assert_eq!(err.code(), codes::INVALID_ARGUMENT);
+    assert_eq!(
+        engine.run(&program, "half", &[]).unwrap_err().kind,
+        RuntimeErrorKind::ArityMismatch {
+            name: "half".to_string(),
+            expected: 1,
+            found: 0
+        }
+    );
+    // neither got as far as defining the globals
+    assert!(output.is_empty());
+
+    assert_eq!(
+        engine.run(&program, "half", &[Value::Float(3.0)]),
+        Ok(Value::Float(1.5))
+    );
+
+    // a loaded program only knows how many arguments its functions take
+    let loaded = Program::from_bytecode(&program.to_bytecode()).unwrap();
+    assert_eq!(
+        engine.run(&loaded, "half", &[]).unwrap_err().kind,
+        RuntimeErrorKind::ArityMismatch {
+            name: "half".to_string(),
+            expected: 1,
+            found: 0
+        }
+    );
+}
+
+#[test]
+fn test_host_functions() {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut engine = Engine::new();
+    let entries = Rc::clone(&log);
+    engine.register(
+        "record",
+        Signature::new(Type::Void, vec![Type::Int]),
+        move |_, args| {
+            entries.borrow_mut().push(args[0].clone());
+            Ok(Value::Void)
+        },
+    );
+    let program = engine
+        .compile("void count(int n) { for (int i = 0; i < n; i++) record(i * i); }")
+        .unwrap();
+    engine.run(&program, "count", &[Value::Int(4)]).unwrap();
+    assert_eq!(
+        *log.borrow(),
+        vec![Value::Int(0), Value::Int(1), Value::Int(4), Value::Int(9)]
+    );
+
+    // host functions are checked like any other
+    let err = engine.compile("int main() { record(\"x\"); }").unwrap_err();
+    assert_eq!(err.iter().next().unwrap().code, codes::MISMATCHED_TYPES);
+
+    // without the standard library only registered natives exist
+    let engine = Engine::with_natives(NativeRegistry::new());
+    let err = engine.compile("int main() { puts(\"x\"); }").unwrap_err();
+    assert_eq!(
+        err.iter().next().unwrap().code,
+        codes::UNDECLARED_IDENTIFIER
+    );
+}
+
+#[test]
+fn test_standard_streams() {
+    let output = SharedBuffer::default();
+    let mut engine = Engine::new()
+        .with_output(output.clone())
+        .with_input(Cursor::new("5 7"));
+    let program = engine
+        .compile("int main() { int a = read_int(); printf(\"%d\\n\", a * read_int()); return 0; }")
+        .unwrap();
+    assert_eq!(engine.run(&program, "main", &[]), Ok(Value::Int(0)));
+    assert_eq!(output.take(), "35\n");
+}
+
+#[test]
+fn test_diagnostics() {
+    let engine = Engine::new();
+    let err = engine
+        .compile("int main() {\n  return missing;\n}")
+        .unwrap_err();
+    assert_eq!(err.len(), 1);
+    assert_eq!(err.error_count(), 1);
+    let diagnostic = err.iter().next().unwrap();
+    assert_eq!(diagnostic.code, codes::UNDECLARED_IDENTIFIER);
+    assert!(err.render("main.c").contains("--> main.c:2:10"));
+    assert!(err.to_string().contains("return missing;"));
+
+    // later stages do not run once one reports an error
+    let err = engine.compile("int main() { return 1 +; }").unwrap_err();
+    assert!(err.iter().all(|d| d.code.starts_with("E01")), "{:?}", err);
+}
+
+#[test]
+fn test_pointers_as_conditions() {
+    let mut engine = Engine::new();
+    let program = engine
+        .compile(
+            "int main() {
+                char *s = \"hi\";
+                char *null;
+                int n = !s;
+                if (s) n += 1;
+                if (!null) n += 10;
+                if (s && !null) n += 100;
+                while (null || !s) return -1;
+                return n;
+            }",
+        )
+        .unwrap();
+    assert_eq!(engine.run(&program, "main", &[]), Ok(Value::Int(111)));
+}
+
+#[test]
+fn test_a_declaration_is_not_a_body() {
+    let engine = Engine::new();
+    // once compiled, these left the stack out of step with the locals
+    for source in [
+        "int main() { int c = 0; if (c) int x = 5; int y = 7; return y; }",
+        "int main() { int i = 0; while (i < 100000) int z = i = i + 1; return i; }",
+    ] {
+        let err = engine.compile(source).unwrap_err();
+        assert_eq!(err.len(), 1, "{}", err);
+        assert_eq!(err.iter().next().unwrap().code, codes::DECLARATION_AS_BODY);
+    }
+}
+
+#[test]
+fn test_bytecode_round_trip() {
+    let mut engine = Engine::new();
+    let program = engine
+        .compile("int base = 40; int main() { return base + 2; }")
+        .unwrap();
+    let loaded = Program::from_bytecode(&program.to_bytecode()).unwrap();
+    assert_eq!(loaded.to_bytecode(), program.to_bytecode());
+    assert_eq!(engine.run(&loaded, "main", &[]), Ok(Value::Int(42)));
+    assert!(loaded.disassemble().contains("== main/0 =="));
+}
@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+mod engine;
+#[cfg(test)]
+mod engine_tests;
+
+pub use engine::{Diagnostics, Engine, Program};
@@ -0,0 +1,43 @@
+//! A compiler and virtual machine for a small subset of C.
+//!
+//! [`Engine`] is the way in for embedders: it compiles source to a
+//! [`Program`], runs any of its functions with arguments from the host, lets
+//! the host register functions of its own and reads globals back afterwards.
+//!
+//! ```
+//! use uvm::{Engine, Signature, Type, Value};
+//!
+//! let mut engine = Engine::new();
+//! engine.register("double", Signature::new(Type::Int, vec![Type::Int]), |_, args| {
+//!     match args {
+//!         [Value::Int(n)] => Ok(Value::Int(n * 2)),
+//!         _ => Err("expected an integer".to_string()),
+//!     }
+//! });
+//! let program = engine
+//!     .compile("int total; int add(int n) { total = double(n) + 1; return total; }")
+//!     .unwrap();
+//! assert_eq!(engine.run(&program, "add", &[Value::Int(20)]), Ok(Value::Int(41)));
+//! assert_eq!(engine.global("total"), Some(&Value::Int(41)));
+//! ```
+//!
+//! The stages the engine runs are public too, for tools that need the tokens,
+//! the syntax tree or the bytecode along the way.
+
+// Diagnostics are returned by value; they are only built on the error path.
+#![allow(clippy::result_large_err)]
+
+pub mod compiler;
+pub mod diagnostic;
+mod engine;
+pub mod parser;
+pub mod semantic;
+pub mod span;
+pub mod symbol;
+pub mod tokenizer;
+pub mod vm;
+
+pub use diagnostic::Diagnostic;
+pub use engine::{Diagnostics, Engine, Program};
+pub use parser::ast::Type;
+pub use vm::{NativeContext, NativeRegistry, NativeResult, RuntimeError, Signature, Value};
@@ -1,4 +1,6 @@
-mod parser;
-mod tokenizer;
+mod driver;
 
-fn main() {}
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    std::process::exit(driver::main(&args));
+}
@@ -0,0 +1,326 @@
+use crate::span::Span;
+use crate::symbol::Symbol;
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Char,
+    Bool,
+    Float,
+    Void,
+    Pointer(Box<Type>),
+    Array(Box<Type>, usize),
+    /// A function's return type and parameter types.
+    Function(Box<Type>, Vec<Type>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program<'src> {
+    pub items: Vec<Item<'src>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item<'src> {
+    Function(FunctionDecl<'src>),
+    Prototype(Prototype),
+    Global(VarDecl<'src>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDecl<'src> {
+    pub ret_ty: Type,
+    pub name: Symbol,
+    pub params: Vec<Param>,
+    pub body: Vec<Stmt<'src>>,
+    pub span: Span,
+}
+
+/// A function declared without a body, `int f(int n);`, so it can be called
+/// before its definition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prototype {
+    pub ret_ty: Type,
+    pub name: Symbol,
+    pub params: Vec<Param>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub ty: Type,
+    pub name: Symbol,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarDecl<'src> {
+    pub ty: Type,
+    pub name: Symbol,
+    pub init: Option<Expr<'src>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt<'src> {
+    pub kind: StmtKind<'src>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind<'src> {
+    VarDecl(VarDecl<'src>),
+    /// An assignment used as a statement: `target = value`, or `target op= value`
+    /// when `op` is set. `x++` and `x--` are parsed as `x += 1` and `x -= 1`.
+    /// Assignments nested inside expressions stay `ExprKind::Assign`.
+    Assign {
+        target: Expr<'src>,
+        op: Option<BinaryOp>,
+        value: Expr<'src>,
+    },
+    If {
+        condition: Expr<'src>,
+        then_branch: Box<Stmt<'src>>,
+        else_branch: Option<Box<Stmt<'src>>>,
+    },
+    While {
+        condition: Expr<'src>,
+        body: Box<Stmt<'src>>,
+    },
+    DoWhile {
+        body: Box<Stmt<'src>>,
+        condition: Expr<'src>,
+    },
+    For {
+        init: Option<Box<Stmt<'src>>>,
+        condition: Option<Expr<'src>>,
+        step: Option<Box<Stmt<'src>>>,
+        body: Box<Stmt<'src>>,
+    },
+    Return(Option<Expr<'src>>),
+    Break,
+    Continue,
+    Block(Vec<Stmt<'src>>),
+    Expr(Expr<'src>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr<'src> {
+    pub kind: ExprKind<'src>,
+    pub span: Span,
+    /// Filled in by the type checker.
+    pub ty: Option<Type>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind<'src> {
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr<'src>>,
+        right: Box<Expr<'src>>,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr<'src>>,
+    },
+    Assign {
+        target: Box<Expr<'src>>,
+        op: Option<BinaryOp>,
+        value: Box<Expr<'src>>,
+    },
+    Call {
+        callee: Box<Expr<'src>>,
+        args: Vec<Expr<'src>>,
+    },
+    Literal(Literal<'src>),
+    Var(Symbol),
+    Index {
+        array: Box<Expr<'src>>,
+        index: Box<Expr<'src>>,
+    },
+    /// An implicit conversion made explicit by the type checker.
+    Convert {
+        to: Type,
+        operand: Box<Expr<'src>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal<'src> {
+    Int(i64),
+    Float(f64),
+    Char(char),
+    Bool(bool),
+    String(Cow<'src, str>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    BitNot,
+}
+
+impl<'src> Expr<'src> {
+    pub fn new(kind: ExprKind<'src>, span: Span) -> Expr<'src> {
+        Expr {
+            kind,
+            span,
+            ty: None,
+        }
+    }
+}
+
+impl<'src> Stmt<'src> {
+    pub fn new(kind: StmtKind<'src>, span: Span) -> Stmt<'src> {
+        Stmt { kind, span }
+    }
+}
+
+impl Type {
+    /// `int`, `char`, `bool` and `float`, which convert into each other implicitly.
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(self, Type::Int | Type::Char | Type::Bool | Type::Float)
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Type::Int | Type::Char | Type::Bool)
+    }
+
+    /// Types that can be tested for truth.
+    pub fn is_scalar(&self) -> bool {
+        self.is_arithmetic() || matches!(self, Type::Pointer(_))
+    }
+
+    /// Arrays used as values decay to a pointer to their first element.
+    pub fn decay(self) -> Type {
+        match self {
+            Type::Array(inner, _) => Type::Pointer(inner),
+            other => other,
+        }
+    }
+}
+
+impl BinaryOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+            BinaryOp::Equal => "==",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::Less => "<",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::Greater => ">",
+            BinaryOp::GreaterEqual => ">=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "^",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+        }
+    }
+}
+
+impl UnaryOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "!",
+            UnaryOp::BitNot => "~",
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Char => write!(f, "char"),
+            Type::Bool => write!(f, "bool"),
+            Type::Float => write!(f, "float"),
+            Type::Void => write!(f, "void"),
+            Type::Pointer(inner) => write!(f, "{}*", inner),
+            Type::Array(inner, len) => write!(f, "{}[{}]", inner, len),
+            Type::Function(ret, params) => {
+                write!(f, "{}(", ret)?;
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", param)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl Display for Literal<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::Int(value) => write!(f, "{}", value),
+            Literal::Float(value) => write!(f, "{:?}", value),
+            Literal::Char(value) => write!(f, "{:?}", value),
+            Literal::Bool(value) => write!(f, "{}", value),
+            Literal::String(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+/// Prints the expression fully parenthesised, e.g. `(a = (b + (c * d)))`.
+impl Display for Expr<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ExprKind::Binary { op, left, right } => {
+                write!(f, "({} {} {})", left, op.symbol(), right)
+            }
+            ExprKind::Unary { op, operand } => write!(f, "({}{})", op.symbol(), operand),
+            ExprKind::Assign { target, op, value } => {
+                let op = op.map_or("", BinaryOp::symbol);
+                write!(f, "({} {}= {})", target, op, value)
+            }
+            ExprKind::Call { callee, args } => {
+                write!(f, "{}(", callee)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+            ExprKind::Literal(literal) => write!(f, "{}", literal),
+            ExprKind::Var(name) => write!(f, "{}", name),
+            ExprKind::Index { array, index } => write!(f, "{}[{}]", array, index),
+            ExprKind::Convert { to, operand } => write!(f, "(({}) {})", to, operand),
+        }
+    }
+}
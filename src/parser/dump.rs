@@ -0,0 +1,262 @@
+use crate::parser::ast::{
+    Expr, ExprKind, FunctionDecl, Item, Program, Prototype, Stmt, StmtKind, VarDecl,
+};
+use std::fmt::Write;
+
+/// Renders the program as an indented tree, one node per line:
+///
+/// ```text
+/// Function main -> int
+///   Return
+///     Binary +
+///       Var a
+///       Literal 1
+/// ```
+pub fn dump_ast(program: &Program) -> String {
+    let mut dumper = Dumper {
+        out: String::new(),
+        depth: 0,
+    };
+    for item in &program.items {
+        match item {
+            Item::Function(function) => dumper.function(function),
+            Item::Prototype(prototype) => dumper.prototype(prototype),
+            Item::Global(global) => dumper.var_decl("Global", global),
+        }
+    }
+    dumper.out
+}
+
+/// Renders top-level statements, as typed into the REPL, like `dump_ast`.
+pub fn dump_stmts(stmts: &[Stmt]) -> String {
+    let mut dumper = Dumper {
+        out: String::new(),
+        depth: 0,
+    };
+    for stmt in stmts {
+        dumper.stmt(stmt);
+    }
+    dumper.out
+}
+
+struct Dumper {
+    out: String,
+    depth: usize,
+}
+
+impl Dumper {
+    fn line(&mut self, text: std::fmt::Arguments) {
+        let _ = writeln!(self.out, "{:indent$}{}", "", text, indent = self.depth * 2);
+    }
+
+    fn nested(&mut self, f: impl FnOnce(&mut Dumper)) {
+        self.depth += 1;
+        f(self);
+        self.depth -= 1;
+    }
+
+    fn function(&mut self, function: &FunctionDecl) {
+        self.line(format_args!(
+            "Function {} -> {}",
+            function.name, function.ret_ty
+        ));
+        self.nested(|d| {
+            for param in &function.params {
+                d.line(format_args!("Param {}: {}", param.name, param.ty));
+            }
+            for stmt in &function.body {
+                d.stmt(stmt);
+            }
+        });
+    }
+
+    fn prototype(&mut self, prototype: &Prototype) {
+        self.line(format_args!(
+            "Prototype {} -> {}",
+            prototype.name, prototype.ret_ty
+        ));
+        self.nested(|d| {
+            for param in &prototype.params {
+                d.line(format_args!("Param {}: {}", param.name, param.ty));
+            }
+        });
+    }
+
+    fn var_decl(&mut self, label: &str, decl: &VarDecl) {
+        self.line(format_args!("{} {}: {}", label, decl.name, decl.ty));
+        if let Some(init) = &decl.init {
+            self.nested(|d| d.expr(init));
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::VarDecl(decl) => self.var_decl("VarDecl", decl),
+            StmtKind::Assign { target, op, value } => {
+                let op = op.map_or("", |op| op.symbol());
+                self.line(format_args!("Assign {}=", op));
+                self.nested(|d| {
+                    d.expr(target);
+                    d.expr(value);
+                });
+            }
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.line(format_args!("If"));
+                self.nested(|d| {
+                    d.expr(condition);
+                    d.stmt(then_branch);
+                    if let Some(else_branch) = else_branch {
+                        d.line(format_args!("Else"));
+                        d.nested(|d| d.stmt(else_branch));
+                    }
+                });
+            }
+            StmtKind::While { condition, body } => {
+                self.line(format_args!("While"));
+                self.nested(|d| {
+                    d.expr(condition);
+                    d.stmt(body);
+                });
+            }
+            StmtKind::DoWhile { body, condition } => {
+                self.line(format_args!("DoWhile"));
+                self.nested(|d| {
+                    d.stmt(body);
+                    d.expr(condition);
+                });
+            }
+            StmtKind::For {
+                init,
+                condition,
+                step,
+                body,
+            } => {
+                self.line(format_args!("For"));
+                self.nested(|d| {
+                    if let Some(init) = init {
+                        d.line(format_args!("Init"));
+                        d.nested(|d| d.stmt(init));
+                    }
+                    if let Some(condition) = condition {
+                        d.line(format_args!("Condition"));
+                        d.nested(|d| d.expr(condition));
+                    }
+                    if let Some(step) = step {
+                        d.line(format_args!("Step"));
+                        d.nested(|d| d.stmt(step));
+                    }
+                    d.stmt(body);
+                });
+            }
+            StmtKind::Return(value) => {
+                self.line(format_args!("Return"));
+                if let Some(value) = value {
+                    self.nested(|d| d.expr(value));
+                }
+            }
+            StmtKind::Break => self.line(format_args!("Break")),
+            StmtKind::Continue => self.line(format_args!("Continue")),
+            StmtKind::Block(stmts) => {
+                self.line(format_args!("Block"));
+                self.nested(|d| {
+                    for stmt in stmts {
+                        d.stmt(stmt);
+                    }
+                });
+            }
+            StmtKind::Expr(expr) => {
+                self.line(format_args!("ExprStmt"));
+                self.nested(|d| d.expr(expr));
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Binary { op, left, right } => {
+                self.line(format_args!("Binary {}", op.symbol()));
+                self.nested(|d| {
+                    d.expr(left);
+                    d.expr(right);
+                });
+            }
+            ExprKind::Unary { op, operand } => {
+                self.line(format_args!("Unary {}", op.symbol()));
+                self.nested(|d| d.expr(operand));
+            }
+            ExprKind::Assign { target, op, value } => {
+                let op = op.map_or("", |op| op.symbol());
+                self.line(format_args!("Assign {}=", op));
+                self.nested(|d| {
+                    d.expr(target);
+                    d.expr(value);
+                });
+            }
+            ExprKind::Call { callee, args } => {
+                self.line(format_args!("Call"));
+                self.nested(|d| {
+                    d.expr(callee);
+                    for arg in args {
+                        d.expr(arg);
+                    }
+                });
+            }
+            ExprKind::Literal(literal) => self.line(format_args!("Literal {}", literal)),
+            ExprKind::Var(name) => self.line(format_args!("Var {}", name)),
+            ExprKind::Index { array, index } => {
+                self.line(format_args!("Index"));
+                self.nested(|d| {
+                    d.expr(array);
+                    d.expr(index);
+                });
+            }
+            ExprKind::Convert { to, operand } => {
+                self.line(format_args!("Convert {}", to));
+                self.nested(|d| d.expr(operand));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::dump_ast;
+    use crate::parser::Parser;
+    use crate::tokenizer::Tokenizer;
+
+    #[test]
+    fn dump_is_indented_by_depth() {
+        let source = "int g = 2;\nint main(int n) { if (n > 0) { n += g; } else return -n; return f(n, 1); }";
+        let (program, diagnostics) = Parser::new(Tokenizer::new(source)).parse();
+        assert!(diagnostics.is_empty());
+
+        let expected = "\
+Global g: int
+  Literal 2
+Function main -> int
+  Param n: int
+  If
+    Binary >
+      Var n
+      Literal 0
+    Block
+      Assign +=
+        Var n
+        Var g
+    Else
+      Return
+        Unary -
+          Var n
+  Return
+    Call
+      Var f
+      Var n
+      Literal 1
+";
+        assert_eq!(dump_ast(&program), expected);
+    }
+}
@@ -1,5 +1,13 @@
+pub mod ast;
+mod dump;
+#[allow(clippy::module_inception)]
 mod parser;
 #[cfg(test)]
 mod parser_tests;
+mod precedence;
+mod rpn;
 
-pub use parser::{AstNode, Parser};
+pub use ast::Program;
+pub use dump::{dump_ast, dump_stmts};
+pub use parser::Parser;
+pub use rpn::{convert_to_rpn, Rpn};
@@ -1,229 +1,797 @@
-use crate::parser::AstNode::{Atom, List};
-use crate::tokenizer::{Token, TokenType};
-use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
-
-#[derive(Debug)]
-pub enum AstNode {
-    Atom(Token),
-    List(Vec<AstNode>),
+use crate::diagnostic::{codes, Diagnostic};
+use crate::parser::ast::{
+    BinaryOp, Expr, ExprKind, FunctionDecl, Item, Literal, Param, Program, Prototype, Stmt,
+    StmtKind, Type, UnaryOp, VarDecl,
+};
+use crate::parser::precedence::{infix_operator, Infix, InfixOperator, LOWEST_PRECEDENCE};
+use crate::span::Span;
+use crate::symbol::Symbol;
+use crate::tokenizer::{Keyword, Operator, Punctuation, Token, TokenType};
+use std::iter::Peekable;
+
+/// A recursive descent parser. Tokens are pulled from the stream only as the
+/// parser reaches them, keeping one token under the cursor plus a peekable
+/// second for the rare place that needs it. Errors in the stream are collected
+/// along with the parser's own.
+pub struct Parser<'src, I>
+where
+    I: Iterator<Item = Result<Token<'src>, Diagnostic>>,
+{
+    tokens: Peekable<I>,
+    current: Option<Token<'src>>,
+    previous_span: Span,
+    diagnostics: Vec<Diagnostic>,
 }
 
-impl Display for AstNode {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Atom(token) => write!(f, "{}", token.lexeme),
-            List(l) => {
-                write!(f, "(")?;
-                for (i, node) in l.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "{}", node)?;
-                }
-                write!(f, ")")
-            }
-        }?;
-        Ok(())
+impl<'src, I> Parser<'src, I>
+where
+    I: Iterator<Item = Result<Token<'src>, Diagnostic>>,
+{
+    pub fn new(tokens: impl IntoIterator<IntoIter = I>) -> Self {
+        let mut parser = Self {
+            tokens: tokens.into_iter().peekable(),
+            current: None,
+            previous_span: Span::default(),
+            diagnostics: Vec::new(),
+        };
+        parser.current = parser.next_token();
+        parser
     }
-}
 
-pub struct Parser<'a> {
-    tokens: &'a [Token],
-    position: usize,
-}
+    pub fn is_at_end(&self) -> bool {
+        self.peek()
+            .is_none_or(|token| token.token_type == TokenType::Eof)
+    }
 
-impl<'a> Parser<'a> {
-    pub fn new(tokens: &'a [Token]) -> Self {
-        Self {
-            tokens,
-            position: 0,
+    // Moves errors at the front of the stream into the diagnostics.
+    fn take_stream_errors(&mut self) {
+        while let Some(Err(diagnostic)) = self.tokens.next_if(Result::is_err) {
+            self.diagnostics.push(diagnostic);
         }
     }
 
-    fn is_at_end(&self) -> bool {
-        self.position >= self.tokens.len()
+    fn next_token(&mut self) -> Option<Token<'src>> {
+        self.take_stream_errors();
+        self.tokens.next().and_then(Result::ok)
     }
 
-    fn peek(&self) -> Option<&Token> {
-        if self.is_at_end() {
-            return None;
-        }
-        Some(&self.tokens[self.position])
+    fn peek(&self) -> Option<&Token<'src>> {
+        self.current.as_ref()
     }
 
-    fn advance(&mut self) -> Option<&Token> {
-        if self.is_at_end() {
-            return None;
+    // The token after the one under the cursor.
+    fn peek_second(&mut self) -> Option<&Token<'src>> {
+        self.take_stream_errors();
+        self.tokens.peek()?.as_ref().ok()
+    }
+
+    fn advance(&mut self) -> Option<Token<'src>> {
+        let token = self.current.take()?;
+        if token.token_type == TokenType::Eof {
+            self.current = Some(token.clone());
+        } else {
+            self.previous_span = token.span;
+            self.current = self.next_token();
         }
-        let token = &self.tokens[self.position];
-        self.position += 1;
         Some(token)
     }
 
-    fn parse_list(&mut self) -> Result<AstNode, String> {
-        let mut root_list: Vec<AstNode> = Vec::new();
-        let mut current_statement: Vec<AstNode> = Vec::new();
+    // Consumes the token under the cursor if it satisfies `predicate`.
+    fn advance_if(&mut self, predicate: impl FnOnce(&Token<'src>) -> bool) -> Option<Token<'src>> {
+        if self.peek().is_some_and(predicate) {
+            self.advance()
+        } else {
+            None
+        }
+    }
 
-        loop {
-            if let Some(token) = self.peek() {
-                match token.token_type {
-                    TokenType::Value => {
-                        let new_token = Token::new(TokenType::Value, token.lexeme.clone());
-                        current_statement.push(Atom(new_token));
-                        self.advance();
+    // Span of the token under the cursor, or of the last token when the input ran out.
+    fn current_span(&self) -> Span {
+        self.peek().map_or(self.previous_span, |token| token.span)
+    }
+
+    fn previous_span(&self) -> Span {
+        self.previous_span
+    }
+
+    fn check_punctuation(&self, punctuation: Punctuation) -> bool {
+        matches!(self.peek(), Some(t) if t.token_type == TokenType::Punctuation(punctuation))
+    }
+
+    fn check_operator(&self, operator: Operator) -> bool {
+        matches!(self.peek(), Some(t) if t.token_type == TokenType::Operator(operator))
+    }
+
+    fn check_keyword(&self, keyword: Keyword) -> bool {
+        matches!(self.peek(), Some(t) if t.token_type == TokenType::Keyword(keyword))
+    }
+
+    fn match_punctuation(&mut self, punctuation: Punctuation) -> bool {
+        let matched = self.check_punctuation(punctuation);
+        if matched {
+            self.advance();
+        }
+        matched
+    }
+
+    fn match_operator(&mut self, operator: Operator) -> bool {
+        let matched = self.check_operator(operator);
+        if matched {
+            self.advance();
+        }
+        matched
+    }
+
+    fn match_keyword(&mut self, keyword: Keyword) -> bool {
+        let matched = self.check_keyword(keyword);
+        if matched {
+            self.advance();
+        }
+        matched
+    }
+
+    fn unexpected(&self, expected: &str) -> Diagnostic {
+        let found = match self.peek() {
+            Some(token) if token.token_type != TokenType::Eof => format!("`{}`", token.lexeme),
+            _ => "end of file".to_string(),
+        };
+        Diagnostic::error(
+            codes::UNEXPECTED_TOKEN,
+            format!("expected {}, found {}", expected, found),
+            self.current_span(),
+        )
+    }
+
+    fn expect_punctuation(&mut self, punctuation: Punctuation) -> Result<Token<'src>, Diagnostic> {
+        self.take_punctuation(punctuation)
+            .ok_or_else(|| self.unexpected(&format!("`{}`", punctuation.lexeme())))
+    }
+
+    // Expects the delimiter that closes `open`, reporting where it was opened if it is missing.
+    fn expect_closing(&mut self, open: &Token<'src>) -> Result<Token<'src>, Diagnostic> {
+        let close = match open.token_type {
+            TokenType::Punctuation(punctuation) => punctuation.closing_delimiter(),
+            _ => None,
+        };
+        if let Some(token) = close.and_then(|close| self.take_punctuation(close)) {
+            return Ok(token);
+        }
+
+        let diagnostic = match self.peek() {
+            Some(token) if is_closing_delimiter(token) => Diagnostic::error(
+                codes::MISMATCHED_DELIMITER,
+                format!("mismatched closing delimiter `{}`", token.lexeme),
+                token.span,
+            )
+            .with_label(open.span, format!("`{}` opened here", open.lexeme)),
+            Some(token) if token.token_type != TokenType::Eof => self
+                .unexpected(&format!("`{}`", close.map_or("", Punctuation::lexeme)))
+                .with_label(open.span, format!("`{}` opened here", open.lexeme)),
+            _ => Diagnostic::error(
+                codes::UNCLOSED_DELIMITER,
+                format!(
+                    "unclosed `{}` opened at line {}",
+                    open.lexeme, open.span.start.line
+                ),
+                open.span,
+            )
+            .with_label(self.current_span(), "reached end of file"),
+        };
+        Err(diagnostic)
+    }
+
+    fn expect_identifier(&mut self) -> Result<Symbol, Diagnostic> {
+        match self.peek().map(|token| &token.token_type) {
+            Some(&TokenType::Identifier(name)) => {
+                self.advance();
+                Ok(name)
+            }
+            _ => Err(self.unexpected("identifier")),
+        }
+    }
+
+    // Skips tokens until just after a `;` or just before a `}` that closes the
+    // current block, so parsing can resume at the next statement.
+    fn synchronize(&mut self) {
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            match token.token_type {
+                TokenType::Eof => return,
+                TokenType::Punctuation(Punctuation::Semicolon) if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                TokenType::Punctuation(Punctuation::LeftBrace) => depth += 1,
+                TokenType::Punctuation(Punctuation::RightBrace) => {
+                    if depth == 0 {
+                        return;
                     }
-                    TokenType::Punctuation => match token.lexeme.as_str() {
-                        "(" | "{" => {
-                            self.advance();
-                            let nested_list = self.parse_list()?;
-                            current_statement.push(nested_list);
-                        }
-                        ")" | "}" => {
-                            root_list.append(&mut current_statement);
-                            self.advance();
-                            break;
-                        }
-                        ";" => {
-                            root_list.push(List(current_statement));
-                            current_statement = Vec::new();
-                            self.advance();
-                        }
-                        _ => {
-                            // todo: consider returning error
-                        }
-                    },
-                    TokenType::EOF => {
-                        root_list.push(List(current_statement));
-                        current_statement = Vec::new();
+                    depth -= 1;
+                    if depth == 0 {
                         self.advance();
+                        return;
                     }
                 }
+                _ => {}
             }
+            self.advance();
         }
-        Ok(List(root_list))
     }
 
-    pub fn parse(&mut self) -> Result<AstNode, String> {
-        let mut root_list: Vec<AstNode> = Vec::new();
-        let mut current_statement: Vec<AstNode> = Vec::new();
+    // Reports and skips a closing delimiter that has nothing to close.
+    fn skip_stray_closer(&mut self) -> bool {
+        match self.advance_if(is_closing_delimiter) {
+            Some(token) => {
+                self.diagnostics.push(Diagnostic::error(
+                    codes::UNEXPECTED_CLOSING_DELIMITER,
+                    format!("unexpected closing delimiter `{}`", token.lexeme),
+                    token.span,
+                ));
+                true
+            }
+            None => false,
+        }
+    }
 
+    /// Parses all tokens, recovering from errors where possible. Returns the
+    /// (possibly partial) program together with every diagnostic reported.
+    pub fn parse(&mut self) -> (Program<'src>, Vec<Diagnostic>) {
+        let mut items = Vec::new();
         while !self.is_at_end() {
-            if let Some(t) = self.peek() {
-                match &t.token_type {
-                    TokenType::Value => {
-                        let new_token = Token::new(TokenType::Value, t.lexeme.to_string());
-                        current_statement.push(Atom(new_token));
-                        self.advance();
-                    }
-                    TokenType::Punctuation => match t.lexeme.as_str() {
-                        "(" | "{" => {
-                            self.advance();
-                            let nested_list = self.parse_list()?;
-                            current_statement.push(nested_list);
-                        }
-                        ";" => {
-                            // saving statement as sublist after termination
-                            root_list.push(List(current_statement));
-                            current_statement = Vec::new();
-                            self.advance();
-                        }
-                        _ => {
-                            // todo: consider returning error
-                        }
-                    },
-                    TokenType::EOF => {
-                        root_list.append(&mut current_statement);
-                        current_statement = Vec::new();
-                        self.advance();
-                    }
+            if self.skip_stray_closer() {
+                continue;
+            }
+            match self.parse_item() {
+                Ok(item) => items.push(item),
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    self.synchronize();
                 }
             }
         }
+        (Program { items }, std::mem::take(&mut self.diagnostics))
+    }
 
-        Ok(List(root_list))
+    /// Parses all tokens as a sequence of statements, as typed into the REPL.
+    pub fn parse_statements(&mut self) -> (Vec<Stmt<'src>>, Vec<Diagnostic>) {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            if self.skip_stray_closer() {
+                continue;
+            }
+            match self.parse_statement() {
+                Ok(statement) => statements.push(statement),
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    self.synchronize();
+                }
+            }
+        }
+        (statements, std::mem::take(&mut self.diagnostics))
+    }
+
+    fn parse_item(&mut self) -> Result<Item<'src>, Diagnostic> {
+        let start = self.current_span();
+        let ty = self.parse_type()?;
+        let name = self.expect_identifier()?;
+
+        if self.check_punctuation(Punctuation::LeftParen) {
+            return self.parse_function(ty, name, start);
+        }
+        let global = self.finish_var_decl(ty, name, start)?;
+        Ok(Item::Global(global))
     }
-}
 
-// shunting yard algorithm
-fn convert_to_rpn(statement: Vec<AstNode>) -> Result<Vec<AstNode>, String> {
-    let mut precedence: HashMap<&str, u32> = HashMap::new();
-    precedence.insert("+", 1);
-    precedence.insert("-", 1);
-    precedence.insert("*", 2);
-    precedence.insert("/", 2);
-    precedence.insert("=", 0);
-
-    let mut output: Vec<AstNode> = Vec::new();
-    let mut operator_stack: Vec<AstNode> = Vec::new();
-
-    for ast_node in statement {
-        match ast_node {
-            Atom(token) => {
-                // if known operator
-                if let Some(current_op_precedence) = precedence.get(&token.lexeme.as_str()) {
-                    while let Some(Atom(op)) = operator_stack.last() {
-                        let op_precedence =
-                            precedence.get(op.lexeme.as_str()).unwrap_or_else(|| &0);
-                        if op_precedence < current_op_precedence {
+    pub fn is_type_start(&self) -> bool {
+        matches!(
+            self.peek().map(|token| &token.token_type),
+            Some(TokenType::Keyword(
+                Keyword::Int | Keyword::Char | Keyword::Bool | Keyword::Float | Keyword::Void
+            ))
+        )
+    }
+
+    fn parse_type(&mut self) -> Result<Type, Diagnostic> {
+        let mut ty = match self.peek().map(|token| &token.token_type) {
+            Some(TokenType::Keyword(Keyword::Int)) => Type::Int,
+            Some(TokenType::Keyword(Keyword::Char)) => Type::Char,
+            Some(TokenType::Keyword(Keyword::Bool)) => Type::Bool,
+            Some(TokenType::Keyword(Keyword::Float)) => Type::Float,
+            Some(TokenType::Keyword(Keyword::Void)) => Type::Void,
+            _ => return Err(self.unexpected("type")),
+        };
+        self.advance();
+        while self.match_operator(Operator::Star) {
+            ty = Type::Pointer(Box::new(ty));
+        }
+        Ok(ty)
+    }
+
+    // Parses a function definition, or a prototype if a `;` follows the parameters.
+    fn parse_function(
+        &mut self,
+        ret_ty: Type,
+        name: Symbol,
+        start: Span,
+    ) -> Result<Item<'src>, Diagnostic> {
+        let open = self.expect_punctuation(Punctuation::LeftParen)?;
+        let mut params = Vec::new();
+        // `f(void)` declares no parameters
+        let void_params = self.check_keyword(Keyword::Void)
+            && matches!(
+                self.peek_second(),
+                Some(t) if t.token_type == TokenType::Punctuation(Punctuation::RightParen)
+            );
+        if void_params {
+            self.advance();
+        } else if !self.check_punctuation(Punctuation::RightParen) {
+            loop {
+                params.push(self.parse_param()?);
+                if !self.match_punctuation(Punctuation::Comma) {
+                    break;
+                }
+            }
+        }
+        self.expect_closing(&open)?;
+
+        if self.match_punctuation(Punctuation::Semicolon) {
+            return Ok(Item::Prototype(Prototype {
+                ret_ty,
+                name,
+                params,
+                span: start.to(self.previous_span()),
+            }));
+        }
+        let body = self.parse_block()?;
+        Ok(Item::Function(FunctionDecl {
+            ret_ty,
+            name,
+            params,
+            body,
+            span: start.to(self.previous_span()),
+        }))
+    }
+
+    fn parse_param(&mut self) -> Result<Param, Diagnostic> {
+        let start = self.current_span();
+        let mut ty = self.parse_type()?;
+        let name = self.expect_identifier()?;
+        // `int a[]` decays to a pointer
+        if let Some(open) = self.take_punctuation(Punctuation::LeftBracket) {
+            self.expect_closing(&open)?;
+            ty = Type::Pointer(Box::new(ty));
+        }
+        Ok(Param {
+            ty,
+            name,
+            span: start.to(self.previous_span()),
+        })
+    }
+
+    fn take_punctuation(&mut self, punctuation: Punctuation) -> Option<Token<'src>> {
+        self.advance_if(|t| t.token_type == TokenType::Punctuation(punctuation))
+    }
+
+    // Parses `{ stmt* }` and returns the statements inside.
+    fn parse_block(&mut self) -> Result<Vec<Stmt<'src>>, Diagnostic> {
+        let open = self.expect_punctuation(Punctuation::LeftBrace)?;
+        let mut statements = Vec::new();
+        while !self.is_at_end() && !self.check_punctuation(Punctuation::RightBrace) {
+            if self.skip_stray_closer() {
+                continue;
+            }
+            match self.parse_statement() {
+                Ok(statement) => statements.push(statement),
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    self.synchronize();
+                }
+            }
+        }
+        self.expect_closing(&open)?;
+        Ok(statements)
+    }
+
+    fn parse_statement(&mut self) -> Result<Stmt<'src>, Diagnostic> {
+        let start = self.current_span();
+
+        if self.check_punctuation(Punctuation::LeftBrace) {
+            let statements = self.parse_block()?;
+            return Ok(Stmt::new(
+                StmtKind::Block(statements),
+                start.to(self.previous_span()),
+            ));
+        }
+        if self.match_keyword(Keyword::If) {
+            return self.parse_if(start);
+        }
+        if self.match_keyword(Keyword::While) {
+            return self.parse_while(start);
+        }
+        if self.match_keyword(Keyword::Do) {
+            return self.parse_do_while(start);
+        }
+        if self.match_keyword(Keyword::For) {
+            return self.parse_for(start);
+        }
+        if let Some(kind) = self.match_jump() {
+            self.expect_punctuation(Punctuation::Semicolon)?;
+            return Ok(Stmt::new(kind, start.to(self.previous_span())));
+        }
+        if self.match_keyword(Keyword::Return) {
+            let value = if self.check_punctuation(Punctuation::Semicolon) {
+                None
+            } else {
+                Some(self.parse_expression()?)
+            };
+            self.expect_punctuation(Punctuation::Semicolon)?;
+            return Ok(Stmt::new(
+                StmtKind::Return(value),
+                start.to(self.previous_span()),
+            ));
+        }
+        if self.is_type_start() {
+            let decl = self.parse_var_decl()?;
+            let span = decl.span;
+            return Ok(Stmt::new(StmtKind::VarDecl(decl), span));
+        }
+
+        let statement = self.parse_simple_statement()?;
+        self.expect_punctuation(Punctuation::Semicolon)?;
+        Ok(Stmt::new(statement.kind, start.to(self.previous_span())))
+    }
+
+    fn parse_var_decl(&mut self) -> Result<VarDecl<'src>, Diagnostic> {
+        let start = self.current_span();
+        let ty = self.parse_type()?;
+        let name = self.expect_identifier()?;
+        self.finish_var_decl(ty, name, start)
+    }
+
+    // Parses the rest of a declaration after its type and name: `[N]? (= expr)? ;`
+    fn finish_var_decl(
+        &mut self,
+        mut ty: Type,
+        name: Symbol,
+        start: Span,
+    ) -> Result<VarDecl<'src>, Diagnostic> {
+        if let Some(open) = self.take_punctuation(Punctuation::LeftBracket) {
+            let length = match self.peek().map(|token| &token.token_type) {
+                Some(TokenType::IntLiteral(length)) if *length >= 0 => *length as usize,
+                _ => return Err(self.unexpected("array length")),
+            };
+            self.advance();
+            self.expect_closing(&open)?;
+            ty = Type::Array(Box::new(ty), length);
+        }
+
+        let init = if self.match_operator(Operator::Assign) {
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+        self.expect_punctuation(Punctuation::Semicolon)?;
+        Ok(VarDecl {
+            ty,
+            name,
+            init,
+            span: start.to(self.previous_span()),
+        })
+    }
+
+    // An assignment, increment or bare expression, without the trailing `;`.
+    fn parse_simple_statement(&mut self) -> Result<Stmt<'src>, Diagnostic> {
+        let expr = self.parse_expression()?;
+
+        let op = match self.peek().map(|token| &token.token_type) {
+            Some(TokenType::Operator(Operator::Increment)) => BinaryOp::Add,
+            Some(TokenType::Operator(Operator::Decrement)) => BinaryOp::Sub,
+            _ => {
+                let span = expr.span;
+                let kind = match expr.kind {
+                    ExprKind::Assign { target, op, value } => StmtKind::Assign {
+                        target: *target,
+                        op,
+                        value: *value,
+                    },
+                    kind => StmtKind::Expr(Expr::new(kind, span)),
+                };
+                return Ok(Stmt::new(kind, span));
+            }
+        };
+        let token = self.advance().unwrap();
+        check_assignment_target(&expr)?;
+
+        let span = expr.span.to(token.span);
+        let one = Expr::new(ExprKind::Literal(Literal::Int(1)), token.span);
+        Ok(Stmt::new(
+            StmtKind::Assign {
+                target: expr,
+                op: Some(op),
+                value: one,
+            },
+            span,
+        ))
+    }
+
+    fn parse_if(&mut self, start: Span) -> Result<Stmt<'src>, Diagnostic> {
+        let condition = self.parse_condition()?;
+        let then_branch = Box::new(self.parse_body("if")?);
+        let else_branch = if self.match_keyword(Keyword::Else) {
+            Some(Box::new(self.parse_body("else")?))
+        } else {
+            None
+        };
+        Ok(Stmt::new(
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            },
+            start.to(self.previous_span()),
+        ))
+    }
+
+    fn parse_while(&mut self, start: Span) -> Result<Stmt<'src>, Diagnostic> {
+        let condition = self.parse_condition()?;
+        let body = Box::new(self.parse_body("while")?);
+        Ok(Stmt::new(
+            StmtKind::While { condition, body },
+            start.to(self.previous_span()),
+        ))
+    }
+
+    fn parse_do_while(&mut self, start: Span) -> Result<Stmt<'src>, Diagnostic> {
+        let body = Box::new(self.parse_body("do")?);
+        if !self.match_keyword(Keyword::While) {
+            return Err(self.unexpected("`while`"));
+        }
+        let condition = self.parse_condition()?;
+        self.expect_punctuation(Punctuation::Semicolon)?;
+        Ok(Stmt::new(
+            StmtKind::DoWhile { body, condition },
+            start.to(self.previous_span()),
+        ))
+    }
+
+    // Parses the body of `construct`. As in C it cannot be a bare declaration,
+    // which would have no scope of its own; one is parsed anyway to recover.
+    fn parse_body(&mut self, construct: &str) -> Result<Stmt<'src>, Diagnostic> {
+        if !self.is_type_start() {
+            return self.parse_statement();
+        }
+        let statement = self.parse_statement()?;
+        self.diagnostics.push(
+            Diagnostic::error(
+                codes::DECLARATION_AS_BODY,
+                format!("a declaration cannot be the body of `{}`", construct),
+                statement.span,
+            )
+            .with_note("wrap it in braces to give it a block of its own"),
+        );
+        Ok(statement)
+    }
+
+    // `break` or `continue`, without the trailing `;`.
+    fn match_jump(&mut self) -> Option<StmtKind<'src>> {
+        if self.match_keyword(Keyword::Break) {
+            Some(StmtKind::Break)
+        } else if self.match_keyword(Keyword::Continue) {
+            Some(StmtKind::Continue)
+        } else {
+            None
+        }
+    }
+
+    // Parses a parenthesised `( expr )` condition.
+    fn parse_condition(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        let open = self.expect_punctuation(Punctuation::LeftParen)?;
+        let condition = self.parse_expression()?;
+        self.expect_closing(&open)?;
+        Ok(condition)
+    }
+
+    fn parse_for(&mut self, start: Span) -> Result<Stmt<'src>, Diagnostic> {
+        let open = self.expect_punctuation(Punctuation::LeftParen)?;
+
+        let init = if self.match_punctuation(Punctuation::Semicolon) {
+            None
+        } else if self.is_type_start() {
+            let decl = self.parse_var_decl()?;
+            let span = decl.span;
+            Some(Box::new(Stmt::new(StmtKind::VarDecl(decl), span)))
+        } else {
+            let init = self.parse_simple_statement()?;
+            self.expect_punctuation(Punctuation::Semicolon)?;
+            Some(Box::new(init))
+        };
+
+        let condition = if self.check_punctuation(Punctuation::Semicolon) {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        self.expect_punctuation(Punctuation::Semicolon)?;
+
+        let step = if self.check_punctuation(Punctuation::RightParen) {
+            None
+        } else {
+            Some(Box::new(self.parse_simple_statement()?))
+        };
+        self.expect_closing(&open)?;
+
+        let body = Box::new(self.parse_body("for")?);
+        Ok(Stmt::new(
+            StmtKind::For {
+                init,
+                condition,
+                step,
+                body,
+            },
+            start.to(self.previous_span()),
+        ))
+    }
+
+    pub fn parse_expression(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        self.parse_binary(LOWEST_PRECEDENCE)
+    }
+
+    // Precedence climbing: parses operands and every infix operator that binds
+    // at least as tightly as `min_precedence`, see `precedence.rs`.
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expr<'src>, Diagnostic> {
+        let mut left = self.parse_unary()?;
+        while let Some(operator) = self.peek_infix_operator() {
+            if operator.precedence < min_precedence {
+                break;
+            }
+            self.advance();
+            let right = self.parse_binary(operator.right_precedence())?;
+            let span = left.span.to(right.span);
+            left = match operator.infix {
+                Infix::Binary(op) => binary(op, left, right),
+                Infix::Assign(op) => {
+                    check_assignment_target(&left)?;
+                    Expr::new(
+                        ExprKind::Assign {
+                            target: Box::new(left),
+                            op,
+                            value: Box::new(right),
+                        },
+                        span,
+                    )
+                }
+            };
+        }
+        Ok(left)
+    }
+
+    fn peek_infix_operator(&self) -> Option<InfixOperator> {
+        match self.peek()?.token_type {
+            TokenType::Operator(operator) => infix_operator(operator),
+            _ => None,
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        let start = self.current_span();
+        let op = if self.match_operator(Operator::Minus) {
+            UnaryOp::Neg
+        } else if self.match_operator(Operator::Not) {
+            UnaryOp::Not
+        } else if self.match_operator(Operator::Tilde) {
+            UnaryOp::BitNot
+        } else {
+            return self.parse_postfix();
+        };
+        let operand = self.parse_unary()?;
+        let span = start.to(operand.span);
+        Ok(Expr::new(
+            ExprKind::Unary {
+                op,
+                operand: Box::new(operand),
+            },
+            span,
+        ))
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            if let Some(open) = self.take_punctuation(Punctuation::LeftParen) {
+                let mut args = Vec::new();
+                if !self.check_punctuation(Punctuation::RightParen) {
+                    loop {
+                        args.push(self.parse_expression()?);
+                        if !self.match_punctuation(Punctuation::Comma) {
                             break;
                         }
-                        let op = operator_stack.pop().unwrap();
-                        output.push(op);
                     }
-                    operator_stack.push(Atom(token));
-                } else {
-                    output.push(Atom(token));
                 }
-            }
-            List(_) => {
-                output.push(ast_node);
+                let close = self.expect_closing(&open)?;
+                let span = expr.span.to(close.span);
+                expr = Expr::new(
+                    ExprKind::Call {
+                        callee: Box::new(expr),
+                        args,
+                    },
+                    span,
+                );
+            } else if let Some(open) = self.take_punctuation(Punctuation::LeftBracket) {
+                let index = self.parse_expression()?;
+                let close = self.expect_closing(&open)?;
+                let span = expr.span.to(close.span);
+                expr = Expr::new(
+                    ExprKind::Index {
+                        array: Box::new(expr),
+                        index: Box::new(index),
+                    },
+                    span,
+                );
+            } else {
+                return Ok(expr);
             }
         }
     }
 
-    while !operator_stack.is_empty() {
-        if let Some(op) = operator_stack.pop() {
-            output.push(op);
-        } else {
-            break;
+    fn parse_primary(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        if let Some(open) = self.take_punctuation(Punctuation::LeftParen) {
+            let inner = self.parse_expression()?;
+            let close = self.expect_closing(&open)?;
+            return Ok(Expr::new(inner.kind, open.span.to(close.span)));
         }
+        let Some(token) = self.peek() else {
+            return Err(self.expected_expression());
+        };
+        let kind = match &token.token_type {
+            TokenType::IntLiteral(value) => ExprKind::Literal(Literal::Int(*value)),
+            TokenType::FloatLiteral(value) => ExprKind::Literal(Literal::Float(*value)),
+            TokenType::CharLiteral(value) => ExprKind::Literal(Literal::Char(*value)),
+            TokenType::StringLiteral(value) => ExprKind::Literal(Literal::String(value.clone())),
+            TokenType::Keyword(Keyword::True) => ExprKind::Literal(Literal::Bool(true)),
+            TokenType::Keyword(Keyword::False) => ExprKind::Literal(Literal::Bool(false)),
+            TokenType::Identifier(name) => ExprKind::Var(*name),
+            _ => return Err(self.expected_expression()),
+        };
+        let span = token.span;
+        self.advance();
+        Ok(Expr::new(kind, span))
+    }
+
+    fn expected_expression(&self) -> Diagnostic {
+        let mut diagnostic = self.unexpected("expression");
+        diagnostic.code = codes::EXPECTED_EXPRESSION;
+        diagnostic
     }
-    Ok(output)
 }
 
-#[cfg(test)]
-mod test {
-    use crate::parser::parser::convert_to_rpn;
-    use crate::parser::AstNode::{Atom, List};
-    use crate::tokenizer::{Token, TokenType};
-
-    #[test]
-    fn convert_to_rpn_test_variable_declaration() {
-        let statement = vec![
-            Atom(Token::new(TokenType::Value, "int".to_string())),
-            Atom(Token::new(TokenType::Value, "a".to_string())),
-            Atom(Token::new(TokenType::Value, "=".to_string())),
-            Atom(Token::new(TokenType::Value, "1".to_string())),
-        ];
-        let converted_statement = convert_to_rpn(statement).unwrap();
-        println!("{}", List(converted_statement));
-    }
-
-    #[test]
-    fn convert_to_rpn_test_variable_expression() {
-        // int a = 10 + 33 * 7
-        let statement = vec![
-            Atom(Token::new(TokenType::Value, "int".to_string())),
-            Atom(Token::new(TokenType::Value, "a".to_string())),
-            Atom(Token::new(TokenType::Value, "=".to_string())),
-            Atom(Token::new(TokenType::Value, "10".to_string())),
-            Atom(Token::new(TokenType::Value, "+".to_string())),
-            Atom(Token::new(TokenType::Value, "33".to_string())),
-            Atom(Token::new(TokenType::Value, "*".to_string())),
-            Atom(Token::new(TokenType::Value, "7".to_string())),
-        ];
-        let converted_statement = convert_to_rpn(statement).unwrap();
-        println!("{}", List(converted_statement));
+fn binary<'src>(op: BinaryOp, left: Expr<'src>, right: Expr<'src>) -> Expr<'src> {
+    let span = left.span.to(right.span);
+    Expr::new(
+        ExprKind::Binary {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        },
+        span,
+    )
+}
+
+fn check_assignment_target(target: &Expr<'_>) -> Result<(), Diagnostic> {
+    match target.kind {
+        ExprKind::Var(_) | ExprKind::Index { .. } => Ok(()),
+        _ => Err(Diagnostic::error(
+            codes::INVALID_ASSIGNMENT_TARGET,
+            "invalid assignment target",
+            target.span,
+        )),
     }
 }
+
+fn is_closing_delimiter(token: &Token<'_>) -> bool {
+    matches!(
+        token.token_type,
+        TokenType::Punctuation(
+            Punctuation::RightParen | Punctuation::RightBrace | Punctuation::RightBracket
+        )
+    )
+}
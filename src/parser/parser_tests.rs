@@ -1,230 +1,872 @@
-use super::{AstNode, Parser};
-use crate::tokenizer::{Token, TokenType};
+use super::ast::{BinaryOp, Expr, ExprKind, Item, Literal, Stmt, StmtKind, Type, UnaryOp};
+use super::{Parser, Program};
+use crate::diagnostic::codes;
+use crate::span::{Position, Span};
+use crate::symbol::Symbol;
+use crate::tokenizer::{Keyword, Operator, Punctuation, Token, TokenType, Tokenizer};
+
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let (tokens, diagnostics) = Tokenizer::new(input).tokenize();
+    assert!(
+        diagnostics.is_empty(),
+        "unexpected diagnostics: {:?}",
+        diagnostics
+    );
+    tokens
+}
+
+fn parse(tokens: Vec<Token<'_>>) -> Program<'_> {
+    let (program, diagnostics) = Parser::new(tokens.into_iter().map(Ok)).parse();
+    assert!(
+        diagnostics.is_empty(),
+        "unexpected diagnostics: {:?}",
+        diagnostics
+    );
+    program
+}
+
+fn parse_source(input: &str) -> Program<'_> {
+    parse(tokenize(input))
+}
+
+// Parses `input` as a list of statements, as in the body of a function.
+fn parse_body(input: &str) -> Vec<Stmt<'_>> {
+    let (stmts, diagnostics) = Parser::new(tokenize(input).into_iter().map(Ok)).parse_statements();
+    assert!(
+        diagnostics.is_empty(),
+        "unexpected diagnostics: {:?}",
+        diagnostics
+    );
+    stmts
+}
+
+fn int(value: i64) -> ExprKind<'static> {
+    ExprKind::Literal(Literal::Int(value))
+}
+
+fn var(name: &str) -> ExprKind<'_> {
+    ExprKind::Var(Symbol::intern(name))
+}
+
+fn identifier(name: &str) -> Token<'_> {
+    Token::new(
+        TokenType::Identifier(Symbol::intern(name)),
+        name,
+        Span::default(),
+    )
+}
 
 #[test]
 fn test_parser_two_statements() {
     let tokens = vec![
-        Token::new(TokenType::Value, "int".to_string()),
-        Token::new(TokenType::Value, "main".to_string()),
-        Token::new(TokenType::Punctuation, "(".to_string()),
-        Token::new(TokenType::Punctuation, ")".to_string()),
-        Token::new(TokenType::Punctuation, "{".to_string()),
-        Token::new(TokenType::Value, "int".to_string()),
-        Token::new(TokenType::Value, "a".to_string()),
-        Token::new(TokenType::Value, "=".to_string()),
-        Token::new(TokenType::Value, "10".to_string()),
-        Token::new(TokenType::Punctuation, ";".to_string()),
-        Token::new(TokenType::Value, "return".to_string()),
-        Token::new(TokenType::Value, "0".to_string()),
-        Token::new(TokenType::Punctuation, ";".to_string()),
-        Token::new(TokenType::Punctuation, "}".to_string()),
-        Token::new(TokenType::EOF, String::new()),
+        Token::new(TokenType::Keyword(Keyword::Int), "int", Span::default()),
+        identifier("main"),
+        Token::new(
+            TokenType::Punctuation(Punctuation::LeftParen),
+            "(",
+            Span::default(),
+        ),
+        Token::new(
+            TokenType::Punctuation(Punctuation::RightParen),
+            ")",
+            Span::default(),
+        ),
+        Token::new(
+            TokenType::Punctuation(Punctuation::LeftBrace),
+            "{",
+            Span::default(),
+        ),
+        Token::new(TokenType::Keyword(Keyword::Int), "int", Span::default()),
+        identifier("a"),
+        Token::new(TokenType::Operator(Operator::Assign), "=", Span::default()),
+        Token::new(TokenType::IntLiteral(10), "10", Span::default()),
+        Token::new(
+            TokenType::Punctuation(Punctuation::Semicolon),
+            ";",
+            Span::default(),
+        ),
+        Token::new(
+            TokenType::Keyword(Keyword::Return),
+            "return",
+            Span::default(),
+        ),
+        Token::new(TokenType::IntLiteral(0), "0", Span::default()),
+        Token::new(
+            TokenType::Punctuation(Punctuation::Semicolon),
+            ";",
+            Span::default(),
+        ),
+        Token::new(
+            TokenType::Punctuation(Punctuation::RightBrace),
+            "}",
+            Span::default(),
+        ),
+        Token::new(TokenType::Eof, "", Span::default()),
     ];
 
-    let ast = Parser::new(&tokens).parse().unwrap();
-    println!("{}", ast);
-
-    // Expected structure: (int main () (int a = 10 return 0))
-    if let AstNode::List(root) = ast {
-        assert_eq!(root.len(), 4); // int, main, params, and body list
+    let program = parse(tokens);
 
-        // Check function signature
-        if let AstNode::Atom(token) = &root[0] {
-            assert_eq!(token.lexeme, "int");
-        }
-        if let AstNode::Atom(token) = &root[1] {
-            assert_eq!(token.lexeme, "main");
-        }
+    // int main() { int a = 10; return 0; }
+    assert_eq!(program.items.len(), 1);
+    let Item::Function(main) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    assert_eq!(main.name, "main");
+    assert_eq!(main.ret_ty, Type::Int);
+    assert!(main.params.is_empty());
+    assert_eq!(main.body.len(), 2);
 
-        // Check args
-        if let AstNode::List(list) = &root[2] {
-            assert_eq!(list.len(), 0);
-        }
+    let StmtKind::VarDecl(decl) = &main.body[0].kind else {
+        panic!("expected a declaration");
+    };
+    assert_eq!(decl.ty, Type::Int);
+    assert_eq!(decl.name, "a");
+    assert_eq!(decl.init.as_ref().map(|e| &e.kind), Some(&int(10)));
 
-        // Check body
-        if let AstNode::List(body) = &root[3] {
-            assert_eq!(body.len(), 2); // Two statements
-
-            // Check first statement: int a = 10
-            if let AstNode::List(statement) = &body[0] {
-                assert_eq!(statement.len(), 4);
-                if let AstNode::Atom(token) = &statement[0] {
-                    assert_eq!(token.lexeme, "int");
-                }
-                if let AstNode::Atom(token) = &statement[1] {
-                    assert_eq!(token.lexeme, "a");
-                }
-                if let AstNode::Atom(token) = &statement[2] {
-                    assert_eq!(token.lexeme, "=");
-                }
-                if let AstNode::Atom(token) = &statement[3] {
-                    assert_eq!(token.lexeme, "10");
-                }
-            }
-
-            // Check second statement: return 0
-            if let AstNode::List(statement) = &body[1] {
-                assert_eq!(statement.len(), 2);
-                if let AstNode::Atom(token) = &statement[0] {
-                    assert_eq!(token.lexeme, "return");
-                }
-                if let AstNode::Atom(token) = &statement[1] {
-                    assert_eq!(token.lexeme, "0");
-                }
-            }
-        }
-    } else {
-        panic!("Root node should be a List");
-    }
+    let StmtKind::Return(Some(value)) = &main.body[1].kind else {
+        panic!("expected a return");
+    };
+    assert_eq!(value.kind, int(0));
 }
 
 #[test]
 fn test_parser_simple() {
     let tokens = vec![
-        Token::new(TokenType::Value, "int".to_string()),
-        Token::new(TokenType::Value, "main".to_string()),
-        Token::new(TokenType::Punctuation, "(".to_string()),
-        Token::new(TokenType::Punctuation, ")".to_string()),
-        Token::new(TokenType::Punctuation, "{".to_string()),
-        Token::new(TokenType::Value, "return".to_string()),
-        Token::new(TokenType::Value, "0".to_string()),
-        Token::new(TokenType::Punctuation, ";".to_string()),
-        Token::new(TokenType::Punctuation, "}".to_string()),
-        Token::new(TokenType::EOF, String::new()),
+        Token::new(TokenType::Keyword(Keyword::Int), "int", Span::default()),
+        identifier("main"),
+        Token::new(
+            TokenType::Punctuation(Punctuation::LeftParen),
+            "(",
+            Span::default(),
+        ),
+        Token::new(
+            TokenType::Punctuation(Punctuation::RightParen),
+            ")",
+            Span::default(),
+        ),
+        Token::new(
+            TokenType::Punctuation(Punctuation::LeftBrace),
+            "{",
+            Span::default(),
+        ),
+        Token::new(
+            TokenType::Keyword(Keyword::Return),
+            "return",
+            Span::default(),
+        ),
+        Token::new(TokenType::IntLiteral(0), "0", Span::default()),
+        Token::new(
+            TokenType::Punctuation(Punctuation::Semicolon),
+            ";",
+            Span::default(),
+        ),
+        Token::new(
+            TokenType::Punctuation(Punctuation::RightBrace),
+            "}",
+            Span::default(),
+        ),
+        Token::new(TokenType::Eof, "", Span::default()),
     ];
 
-    let ast = Parser::new(&tokens).parse().unwrap();
-    println!("{}", ast);
+    let program = parse(tokens);
 
-    // Expected structure: (int main () (return 0))
-    if let AstNode::List(root) = ast {
-        assert_eq!(root.len(), 4); // int, main, and body list
+    // int main() { return 0; }
+    let Item::Function(main) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    assert_eq!(main.body.len(), 1);
+    assert!(matches!(
+        &main.body[0].kind,
+        StmtKind::Return(Some(Expr {
+            kind: ExprKind::Literal(Literal::Int(0)),
+            ..
+        }))
+    ));
+}
 
-        if let AstNode::Atom(token) = &root[0] {
-            assert_eq!(token.lexeme, "int");
-        }
-        if let AstNode::Atom(token) = &root[1] {
-            assert_eq!(token.lexeme, "main");
-        }
+#[test]
+fn test_parse_with_func_call() {
+    let tokens = vec![
+        Token::new(TokenType::Keyword(Keyword::Int), "int", Span::default()),
+        identifier("main"),
+        Token::new(
+            TokenType::Punctuation(Punctuation::LeftParen),
+            "(",
+            Span::default(),
+        ),
+        Token::new(
+            TokenType::Punctuation(Punctuation::RightParen),
+            ")",
+            Span::default(),
+        ),
+        Token::new(
+            TokenType::Punctuation(Punctuation::LeftBrace),
+            "{",
+            Span::default(),
+        ),
+        Token::new(TokenType::Keyword(Keyword::Int), "int", Span::default()),
+        identifier("a"),
+        Token::new(TokenType::Operator(Operator::Assign), "=", Span::default()),
+        Token::new(TokenType::IntLiteral(10), "10", Span::default()),
+        Token::new(
+            TokenType::Punctuation(Punctuation::Semicolon),
+            ";",
+            Span::default(),
+        ),
+        Token::new(TokenType::Keyword(Keyword::Int), "int", Span::default()),
+        identifier("b"),
+        Token::new(TokenType::Operator(Operator::Assign), "=", Span::default()),
+        identifier("test"),
+        Token::new(
+            TokenType::Punctuation(Punctuation::LeftParen),
+            "(",
+            Span::default(),
+        ),
+        Token::new(
+            TokenType::Punctuation(Punctuation::RightParen),
+            ")",
+            Span::default(),
+        ),
+        Token::new(
+            TokenType::Punctuation(Punctuation::Semicolon),
+            ";",
+            Span::default(),
+        ),
+        Token::new(
+            TokenType::Keyword(Keyword::Return),
+            "return",
+            Span::default(),
+        ),
+        Token::new(TokenType::IntLiteral(0), "0", Span::default()),
+        Token::new(
+            TokenType::Punctuation(Punctuation::Semicolon),
+            ";",
+            Span::default(),
+        ),
+        Token::new(
+            TokenType::Punctuation(Punctuation::RightBrace),
+            "}",
+            Span::default(),
+        ),
+        Token::new(TokenType::Eof, "", Span::default()),
+    ];
+
+    let program = parse(tokens);
+
+    // int main() { int a = 10; int b = test(); return 0; }
+    let Item::Function(main) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    assert_eq!(main.body.len(), 3);
+
+    let StmtKind::VarDecl(decl) = &main.body[1].kind else {
+        panic!("expected a declaration");
+    };
+    assert_eq!(decl.name, "b");
+    let Some(ExprKind::Call { callee, args }) = decl.init.as_ref().map(|e| &e.kind) else {
+        panic!("expected a call");
+    };
+    assert_eq!(callee.kind, var("test"));
+    assert!(args.is_empty());
+}
+
+#[test]
+fn test_parse_tokenized_source() {
+    let program = parse_source("int main() { int a = 10 + 33 * 7; return a; }");
+
+    let Item::Function(main) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    let StmtKind::VarDecl(decl) = &main.body[0].kind else {
+        panic!("expected a declaration");
+    };
+
+    // 10 + (33 * 7)
+    let Some(ExprKind::Binary { op, left, right }) = decl.init.as_ref().map(|e| &e.kind) else {
+        panic!("expected a binary expression");
+    };
+    assert_eq!(*op, BinaryOp::Add);
+    assert_eq!(left.kind, int(10));
+    let ExprKind::Binary { op, left, right } = &right.kind else {
+        panic!("expected a binary expression");
+    };
+    assert_eq!(*op, BinaryOp::Mul);
+    assert_eq!(left.kind, int(33));
+    assert_eq!(right.kind, int(7));
+}
+
+#[test]
+fn test_parse_spans() {
+    let input = "int main() {\n  return 0;\n}";
+    let program = parse_source(input);
+
+    let Item::Function(main) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    assert_eq!(main.span.start.offset, 0);
+    assert_eq!(main.span.end.offset, input.len());
 
-        if let AstNode::List(list) = &root[2] {
-            assert_eq!(list.len(), 0);
+    // the statement spans from `return` to `;`
+    let span = main.body[0].span;
+    assert_eq!(&input[span.start.offset..span.end.offset], "return 0;");
+    assert_eq!(span.start, Position::new(15, 2, 3));
+
+    let StmtKind::Return(Some(value)) = &main.body[0].kind else {
+        panic!("expected a return");
+    };
+    assert_eq!(value.span.start, Position::new(22, 2, 10));
+}
+
+#[test]
+fn test_parse_globals_and_params() {
+    let program = parse_source(
+        "int counter = 0;\nfloat values[4];\nint add(int a, int b[], char *c) { return a; }\nvoid f(void) {}",
+    );
+
+    assert_eq!(program.items.len(), 4);
+    let Item::Global(counter) = &program.items[0] else {
+        panic!("expected a global");
+    };
+    assert_eq!(counter.name, "counter");
+    assert_eq!(counter.init.as_ref().map(|e| &e.kind), Some(&int(0)));
+
+    let Item::Global(values) = &program.items[1] else {
+        panic!("expected a global");
+    };
+    assert_eq!(values.ty, Type::Array(Box::new(Type::Float), 4));
+
+    let Item::Function(add) = &program.items[2] else {
+        panic!("expected a function");
+    };
+    let params: Vec<(&str, &Type)> = add
+        .params
+        .iter()
+        .map(|p| (p.name.as_str(), &p.ty))
+        .collect();
+    assert_eq!(
+        params,
+        vec![
+            ("a", &Type::Int),
+            ("b", &Type::Pointer(Box::new(Type::Int))),
+            ("c", &Type::Pointer(Box::new(Type::Char))),
+        ]
+    );
+
+    let Item::Function(f) = &program.items[3] else {
+        panic!("expected a function");
+    };
+    assert_eq!(f.ret_ty, Type::Void);
+    assert!(f.params.is_empty());
+}
+
+#[test]
+fn test_parse_prototypes() {
+    let program = parse_source("int fib(int n);\nvoid log(void);\nint fib(int n) { return n; }");
+    assert_eq!(program.items.len(), 3);
+
+    let Item::Prototype(fib) = &program.items[0] else {
+        panic!("expected a prototype");
+    };
+    assert_eq!(fib.name, "fib");
+    assert_eq!(fib.ret_ty, Type::Int);
+    assert_eq!(fib.params.len(), 1);
+    assert_eq!(fib.span.end, Position::new(15, 1, 16));
+
+    let Item::Prototype(log) = &program.items[1] else {
+        panic!("expected a prototype");
+    };
+    assert!(log.params.is_empty());
+    assert!(matches!(&program.items[2], Item::Function(_)));
+}
+
+#[test]
+fn test_parse_assignments() {
+    let body = parse_body("a = 1; a += 2; a -= 3; a++; b[1] = a;");
+
+    let ops: Vec<Option<BinaryOp>> = body
+        .iter()
+        .map(|stmt| match &stmt.kind {
+            StmtKind::Assign { op, .. } => *op,
+            other => panic!("expected an assignment, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        ops,
+        vec![
+            None,
+            Some(BinaryOp::Add),
+            Some(BinaryOp::Sub),
+            Some(BinaryOp::Add),
+            None
+        ]
+    );
+
+    let StmtKind::Assign { value, .. } = &body[3].kind else {
+        unreachable!();
+    };
+    assert_eq!(value.kind, int(1));
+
+    let StmtKind::Assign { target, .. } = &body[4].kind else {
+        unreachable!();
+    };
+    assert!(matches!(target.kind, ExprKind::Index { .. }));
+}
+
+#[test]
+fn test_parse_invalid_assignment_target() {
+    let tokens = tokenize("int main() { 1 + a = 2; return 0; }");
+    let (program, diagnostics) = Parser::new(tokens.into_iter().map(Ok)).parse();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, codes::INVALID_ASSIGNMENT_TARGET);
+    let Item::Function(main) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    assert_eq!(main.body.len(), 1);
+}
+
+#[test]
+fn test_parse_control_flow() {
+    let body = parse_body(
+        "if (a < 1) { a = 1; } else a = 2; while (a) a -= 1; for (int i = 0; i < 10; i++) { }",
+    );
+    assert_eq!(body.len(), 3);
+
+    let StmtKind::If {
+        condition,
+        then_branch,
+        else_branch,
+    } = &body[0].kind
+    else {
+        panic!("expected an if");
+    };
+    assert!(matches!(
+        condition.kind,
+        ExprKind::Binary {
+            op: BinaryOp::Less,
+            ..
         }
+    ));
+    assert!(matches!(then_branch.kind, StmtKind::Block(_)));
+    assert!(matches!(
+        else_branch.as_deref().map(|s| &s.kind),
+        Some(StmtKind::Assign { .. })
+    ));
 
-        if let AstNode::List(body) = &root[3] {
-            assert_eq!(body.len(), 1); // One statement
-
-            if let AstNode::List(statement) = &body[0] {
-                assert_eq!(statement.len(), 2);
-                if let AstNode::Atom(token) = &statement[0] {
-                    assert_eq!(token.lexeme, "return");
-                }
-                if let AstNode::Atom(token) = &statement[1] {
-                    assert_eq!(token.lexeme, "0");
-                }
-            }
+    let StmtKind::While {
+        condition,
+        body: while_body,
+    } = &body[1].kind
+    else {
+        panic!("expected a while");
+    };
+    assert_eq!(condition.kind, var("a"));
+    assert!(matches!(while_body.kind, StmtKind::Assign { .. }));
+
+    let StmtKind::For {
+        init,
+        condition,
+        step,
+        body: for_body,
+    } = &body[2].kind
+    else {
+        panic!("expected a for");
+    };
+    assert!(matches!(
+        init.as_deref().map(|s| &s.kind),
+        Some(StmtKind::VarDecl(_))
+    ));
+    assert!(condition.is_some());
+    assert!(matches!(
+        step.as_deref().map(|s| &s.kind),
+        Some(StmtKind::Assign {
+            op: Some(BinaryOp::Add),
+            ..
+        })
+    ));
+    assert_eq!(for_body.kind, StmtKind::Block(vec![]));
+}
+
+#[test]
+fn test_parse_do_while_break_and_continue() {
+    let body = parse_body("do { if (a) break; continue; } while (a < 3);");
+
+    let StmtKind::DoWhile {
+        body: inner,
+        condition,
+    } = &body[0].kind
+    else {
+        panic!("expected a do-while");
+    };
+    assert!(matches!(
+        condition.kind,
+        ExprKind::Binary {
+            op: BinaryOp::Less,
+            ..
         }
-    } else {
-        panic!("Root node should be a List");
-    }
+    ));
+    let StmtKind::Block(stmts) = &inner.kind else {
+        panic!("expected a block");
+    };
+    assert!(matches!(
+        &stmts[0].kind,
+        StmtKind::If { then_branch, .. } if then_branch.kind == StmtKind::Break
+    ));
+    assert_eq!(stmts[1].kind, StmtKind::Continue);
+
+    let (_, diagnostics) = Parser::new(Tokenizer::new("do a++; (a);")).parse_statements();
+    assert_eq!(diagnostics[0].message, "expected `while`, found `(`");
 }
 
 #[test]
-fn test_parse_with_func_call() {
-    let tokens = vec![
-        Token::new(TokenType::Value, "int".to_string()),
-        Token::new(TokenType::Value, "main".to_string()),
-        Token::new(TokenType::Punctuation, "(".to_string()),
-        Token::new(TokenType::Punctuation, ")".to_string()),
-        Token::new(TokenType::Punctuation, "{".to_string()),
-        Token::new(TokenType::Value, "int".to_string()),
-        Token::new(TokenType::Value, "a".to_string()),
-        Token::new(TokenType::Value, "=".to_string()),
-        Token::new(TokenType::Value, "10".to_string()),
-        Token::new(TokenType::Punctuation, ";".to_string()),
-        Token::new(TokenType::Value, "int".to_string()),
-        Token::new(TokenType::Value, "b".to_string()),
-        Token::new(TokenType::Value, "=".to_string()),
-        Token::new(TokenType::Value, "test".to_string()),
-        Token::new(TokenType::Punctuation, "(".to_string()),
-        Token::new(TokenType::Punctuation, ")".to_string()),
-        Token::new(TokenType::Punctuation, ";".to_string()),
-        Token::new(TokenType::Value, "return".to_string()),
-        Token::new(TokenType::Value, "0".to_string()),
-        Token::new(TokenType::Punctuation, ";".to_string()),
-        Token::new(TokenType::Punctuation, "}".to_string()),
-        Token::new(TokenType::EOF, String::new()),
-    ];
+fn test_parse_declaration_as_a_body() {
+    let input = "\
+int main() {
+  int c = 0;
+  if (c) int x = 5;
+  int y = 7;
+  if (c) {} else int z;
+  while (c < 10) int w = c = c + 1;
+  do int v; while (c);
+  for (;;) int u;
+  return y;
+}";
+    let (program, diagnostics) = Parser::new(Tokenizer::new(input)).parse();
 
-    let ast = Parser::new(&tokens).parse().unwrap();
-    println!("{}", ast);
+    let messages: Vec<(&str, usize)> = diagnostics
+        .iter()
+        .map(|d| {
+            assert_eq!(d.code, codes::DECLARATION_AS_BODY);
+            (d.message.as_str(), d.span.start.line)
+        })
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            ("a declaration cannot be the body of `if`", 3),
+            ("a declaration cannot be the body of `else`", 5),
+            ("a declaration cannot be the body of `while`", 6),
+            ("a declaration cannot be the body of `do`", 7),
+            ("a declaration cannot be the body of `for`", 8),
+        ]
+    );
+    assert_eq!(diagnostics[0].span.start.column, 10);
+    // the declarations are parsed, so nothing after them is lost
+    let Item::Function(main) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    assert_eq!(main.body.len(), 8);
 
-    // Expected structure: (int main () (int a = 10 int b = (test) return 0))
-    if let AstNode::List(root) = ast {
-        assert_eq!(root.len(), 4); // int, main, and body list
+    // inside braces a declaration is fine
+    parse_source("int main() { if (1) { int x = 5; } while (0) { int z; } return 0; }");
+}
 
-        if let AstNode::Atom(token) = &root[0] {
-            assert_eq!(token.lexeme, "int");
+#[test]
+fn test_parse_dangling_else_binds_to_the_nearest_if() {
+    let body = parse_body("if (a) if (b) x = 1; else x = 2;");
+    let StmtKind::If {
+        then_branch,
+        else_branch: None,
+        ..
+    } = &body[0].kind
+    else {
+        panic!("expected an if without an else");
+    };
+    assert!(matches!(
+        &then_branch.kind,
+        StmtKind::If {
+            else_branch: Some(_),
+            ..
         }
-        if let AstNode::Atom(token) = &root[1] {
-            assert_eq!(token.lexeme, "main");
+    ));
+}
+
+#[test]
+fn test_parse_empty_for() {
+    let body = parse_body("for (;;) return 1;");
+    assert!(matches!(
+        &body[0].kind,
+        StmtKind::For {
+            init: None,
+            condition: None,
+            step: None,
+            ..
         }
+    ));
+}
 
-        if let AstNode::List(list) = &root[2] {
-            assert_eq!(list.len(), 0);
+#[test]
+fn test_parse_unary_index_and_call_arguments() {
+    let body = parse_body("f(-a, !b, c[i + 1], (d));");
+
+    let StmtKind::Expr(Expr {
+        kind: ExprKind::Call { args, .. },
+        ..
+    }) = &body[0].kind
+    else {
+        panic!("expected a call");
+    };
+    assert_eq!(args.len(), 4);
+    assert!(matches!(
+        args[0].kind,
+        ExprKind::Unary {
+            op: UnaryOp::Neg,
+            ..
         }
-        if let AstNode::List(body) = &root[3] {
-            assert_eq!(body.len(), 3); // Three statements
-
-            // First statement: int a = 10
-            if let AstNode::List(statement) = &body[0] {
-                assert_eq!(statement.len(), 4);
-                if let AstNode::Atom(token) = &statement[0] {
-                    assert_eq!(token.lexeme, "int");
-                }
-                if let AstNode::Atom(token) = &statement[1] {
-                    assert_eq!(token.lexeme, "a");
-                }
-                if let AstNode::Atom(token) = &statement[2] {
-                    assert_eq!(token.lexeme, "=");
-                }
-                if let AstNode::Atom(token) = &statement[3] {
-                    assert_eq!(token.lexeme, "10");
-                }
-            }
-
-            // Second statement: int b = test()
-            if let AstNode::List(statement) = &body[1] {
-                assert_eq!(statement.len(), 5);
-                if let AstNode::Atom(token) = &statement[0] {
-                    assert_eq!(token.lexeme, "int");
-                }
-                if let AstNode::Atom(token) = &statement[1] {
-                    assert_eq!(token.lexeme, "b");
-                }
-                if let AstNode::Atom(token) = &statement[2] {
-                    assert_eq!(token.lexeme, "=");
-                }
-                if let AstNode::Atom(func_name) = &statement[3] {
-                    assert_eq!(func_name.lexeme, "test");
-                }
-                if let AstNode::List(args) = &statement[4] {
-                    assert_eq!(args.len(), 0);
-                }
-            }
-
-            // Third statement: return 0
-            if let AstNode::List(statement) = &body[2] {
-                assert_eq!(statement.len(), 2);
-                if let AstNode::Atom(token) = &statement[0] {
-                    assert_eq!(token.lexeme, "return");
-                }
-                if let AstNode::Atom(token) = &statement[1] {
-                    assert_eq!(token.lexeme, "0");
-                }
-            }
+    ));
+    assert!(matches!(
+        args[1].kind,
+        ExprKind::Unary {
+            op: UnaryOp::Not,
+            ..
         }
-    } else {
-        panic!("Root node should be a List");
+    ));
+    assert!(matches!(args[2].kind, ExprKind::Index { .. }));
+    assert_eq!(args[3].kind, var("d"));
+}
+
+#[test]
+fn test_parse_recovers_at_statement_boundaries() {
+    let tokens = tokenize("int main() {\n  int a = ;\n  a = 1 1;\n  return a;\n}\nint g = 2;");
+    let (program, diagnostics) = Parser::new(tokens.into_iter().map(Ok)).parse();
+
+    let codes: Vec<&str> = diagnostics.iter().map(|d| d.code).collect();
+    assert_eq!(
+        codes,
+        vec![codes::EXPECTED_EXPRESSION, codes::UNEXPECTED_TOKEN]
+    );
+    assert_eq!(diagnostics[0].message, "expected expression, found `;`");
+    assert_eq!(diagnostics[0].span.start.line, 2);
+    assert_eq!(diagnostics[1].message, "expected `;`, found `1`");
+    assert_eq!(diagnostics[1].span.start.line, 3);
+
+    // the good statements and the global after the function survive
+    assert_eq!(program.items.len(), 2);
+    let Item::Function(main) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    assert_eq!(main.body.len(), 1);
+    assert!(matches!(main.body[0].kind, StmtKind::Return(_)));
+}
+
+#[test]
+fn test_parse_reports_stray_closers_and_continues() {
+    let tokens = tokenize("int a = 1; } int b = 2; ) int c = 3;");
+    let (program, diagnostics) = Parser::new(tokens.into_iter().map(Ok)).parse();
+
+    assert_eq!(diagnostics.len(), 2);
+    assert!(diagnostics
+        .iter()
+        .all(|d| d.code == codes::UNEXPECTED_CLOSING_DELIMITER));
+    assert_eq!(diagnostics[0].message, "unexpected closing delimiter `}`");
+    assert_eq!(diagnostics[0].span.start.column, 12);
+    assert_eq!(diagnostics[1].message, "unexpected closing delimiter `)`");
+
+    // all three declarations survive in the partial program
+    assert_eq!(program.items.len(), 3);
+}
+
+#[test]
+fn test_tokenizer_and_parser_errors_in_one_run() {
+    let input = "int a = 1 @ + 2; }\nint b = #3;";
+    let (_, diagnostics) = Parser::new(Tokenizer::new(input)).parse();
+
+    // tokens are scanned as the parser reaches them, so errors come in source order
+    let codes: Vec<&str> = diagnostics.iter().map(|d| d.code).collect();
+    assert_eq!(
+        codes,
+        vec![
+            codes::UNEXPECTED_CHARACTER,
+            codes::UNEXPECTED_CLOSING_DELIMITER,
+            codes::UNEXPECTED_CHARACTER
+        ]
+    );
+}
+
+#[test]
+fn test_parse_unclosed_brace_at_eof() {
+    let tokens = tokenize("int main() {\n  return 0;\n");
+    let (_, diagnostics) = Parser::new(tokens.into_iter().map(Ok)).parse();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, codes::UNCLOSED_DELIMITER);
+    assert_eq!(diagnostics[0].message, "unclosed `{` opened at line 1");
+    assert_eq!(diagnostics[0].span.start, Position::new(11, 1, 12));
+}
+
+#[test]
+fn test_parse_eof_inside_nested_lists() {
+    let tokens = tokenize("int main() {\n  foo(bar[1\n");
+    let (_, diagnostics) = Parser::new(tokens.into_iter().map(Ok)).parse();
+
+    // the innermost unclosed delimiter is reported, then the block it is in
+    let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "unclosed `[` opened at line 2",
+            "unclosed `{` opened at line 1",
+        ]
+    );
+}
+
+#[test]
+fn test_parse_eof_without_eof_token() {
+    // hand-built token streams may lack the trailing EOF token
+    let tokens = vec![
+        Token::new(TokenType::Keyword(Keyword::Int), "int", Span::default()),
+        identifier("f"),
+        Token::new(
+            TokenType::Punctuation(Punctuation::LeftParen),
+            "(",
+            Span::default(),
+        ),
+    ];
+    let (_, diagnostics) = Parser::new(tokens.into_iter().map(Ok)).parse();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, codes::UNEXPECTED_TOKEN);
+    assert_eq!(diagnostics[0].message, "expected type, found end of file");
+}
+
+#[test]
+fn test_parse_mismatched_delimiters() {
+    let tokens = tokenize("int main() {\n  foo(1, 2};\n  return 0;\n}");
+    let (program, diagnostics) = Parser::new(tokens.into_iter().map(Ok)).parse();
+
+    assert_eq!(diagnostics[0].code, codes::MISMATCHED_DELIMITER);
+    assert_eq!(diagnostics[0].message, "mismatched closing delimiter `}`");
+    assert_eq!(diagnostics[0].span.start, Position::new(23, 2, 11));
+    assert_eq!(diagnostics[0].labels[0].span.start, Position::new(18, 2, 6));
+
+    // the `}` still closes the function body
+    let Item::Function(main) = &program.items[0] else {
+        panic!("expected a function");
+    };
+    assert!(main.body.is_empty());
+}
+
+#[test]
+fn test_parse_missing_closer_inside_list() {
+    let tokens = tokenize("int main() { f(a b); }");
+    let (_, diagnostics) = Parser::new(tokens.into_iter().map(Ok)).parse();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, codes::UNEXPECTED_TOKEN);
+    assert_eq!(diagnostics[0].message, "expected `)`, found `b`");
+    assert_eq!(diagnostics[0].labels[0].message, "`(` opened here");
+}
+
+#[test]
+fn test_parser_pulls_tokens_lazily() {
+    let pulled = std::cell::Cell::new(0);
+    let tokens = Tokenizer::new("f(1) + 2; int x = 3; int y = 4;").inspect(|_| {
+        pulled.set(pulled.get() + 1);
+    });
+    let mut parser = Parser::new(tokens);
+    assert_eq!(pulled.get(), 1);
+    parser.parse_expression().unwrap();
+    // up to the `;` under the cursor, nothing after it
+    assert_eq!(pulled.get(), 7);
+}
+
+fn parse_expr(input: &str) -> String {
+    let tokens = tokenize(input);
+    let mut parser = Parser::new(tokens.into_iter().map(Ok));
+    let expr = parser.parse_expression().unwrap();
+    expr.to_string()
+}
+
+#[test]
+fn test_expression_precedence() {
+    let cases = [
+        ("1 + 2 * 3", "(1 + (2 * 3))"),
+        ("1 * 2 + 3", "((1 * 2) + 3)"),
+        ("1 - 2 - 3", "((1 - 2) - 3)"),
+        ("8 / 4 % 3", "((8 / 4) % 3)"),
+        ("a << 1 + 2", "(a << (1 + 2))"),
+        ("a < b == c > d", "((a < b) == (c > d))"),
+        ("a & b == c", "(a & (b == c))"),
+        ("a | b ^ c & d", "(a | (b ^ (c & d)))"),
+        ("a || b && c | d", "(a || (b && (c | d)))"),
+        ("a && b || c && d", "((a && b) || (c && d))"),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(parse_expr(input), expected, "input: {}", input);
     }
 }
+
+#[test]
+fn test_expression_assignment_is_right_associative() {
+    assert_eq!(parse_expr("a = b = c"), "(a = (b = c))");
+    assert_eq!(parse_expr("a += b -= 1 + 2"), "(a += (b -= (1 + 2)))");
+    assert_eq!(parse_expr("a = b || c"), "(a = (b || c))");
+}
+
+#[test]
+fn test_expression_parentheses() {
+    assert_eq!(parse_expr("(1 + 2) * 3"), "((1 + 2) * 3)");
+    assert_eq!(parse_expr("1 - (2 - 3)"), "(1 - (2 - 3))");
+    assert_eq!(parse_expr("((a))"), "a");
+}
+
+#[test]
+fn test_expression_unary_operators() {
+    assert_eq!(parse_expr("-a * b"), "((-a) * b)");
+    assert_eq!(parse_expr("!a && ~b"), "((!a) && (~b))");
+    assert_eq!(parse_expr("- -a"), "(-(-a))");
+    assert_eq!(parse_expr("-f(x)[1]"), "(-f(x)[1])");
+}
+
+#[test]
+fn test_expression_postfix_operators() {
+    assert_eq!(parse_expr("f(a, b + 1)"), "f(a, (b + 1))");
+    assert_eq!(parse_expr("a[i][j] * 2"), "(a[i][j] * 2)");
+    assert_eq!(parse_expr("f(g(x))(y)"), "f(g(x))(y)");
+}
+
+#[test]
+fn test_expression_tree_shape() {
+    let tokens = tokenize("a = b = 1 + 2");
+    let expr = Parser::new(tokens.into_iter().map(Ok))
+        .parse_expression()
+        .unwrap();
+
+    let ExprKind::Assign { target, op, value } = expr.kind else {
+        panic!("expected an assignment");
+    };
+    assert_eq!(target.kind, var("a"));
+    assert_eq!(op, None);
+    let ExprKind::Assign { target, value, .. } = value.kind else {
+        panic!("expected a nested assignment");
+    };
+    assert_eq!(target.kind, var("b"));
+    assert!(matches!(
+        value.kind,
+        ExprKind::Binary {
+            op: BinaryOp::Add,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_expression_invalid_assignment_target() {
+    let tokens = tokenize("a + b = c");
+    let err = Parser::new(tokens.into_iter().map(Ok))
+        .parse_expression()
+        .unwrap_err();
+    assert_eq!(err.code, codes::INVALID_ASSIGNMENT_TARGET);
+}
+
+#[test]
+fn test_parse_chained_assignment_statement() {
+    let body = parse_body("a = b = 3;");
+
+    let StmtKind::Assign { target, op, value } = &body[0].kind else {
+        panic!("expected an assignment");
+    };
+    assert_eq!(target.kind, var("a"));
+    assert_eq!(*op, None);
+    assert!(matches!(value.kind, ExprKind::Assign { .. }));
+}
@@ -0,0 +1,87 @@
+//! Binding power of infix operators, loosest first. Follows C:
+//!
+//! | precedence | operators            | associativity |
+//! |------------|----------------------|---------------|
+//! | 1          | `=` `+=` `-=`        | right         |
+//! | 2          | `\|\|`               | left          |
+//! | 3          | `&&`                 | left          |
+//! | 4          | `\|`                 | left          |
+//! | 5          | `^`                  | left          |
+//! | 6          | `&`                  | left          |
+//! | 7          | `==` `!=`            | left          |
+//! | 8          | `<` `<=` `>` `>=`    | left          |
+//! | 9          | `<<` `>>`            | left          |
+//! | 10         | `+` `-`              | left          |
+//! | 11         | `*` `/` `%`          | left          |
+//!
+//! Prefix `-` `!` `~` bind tighter than any infix operator, and postfix calls
+//! `f(...)` and indexing `a[...]` bind tighter still.
+
+use crate::parser::ast::BinaryOp;
+use crate::tokenizer::Operator;
+
+pub const LOWEST_PRECEDENCE: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Infix {
+    Binary(BinaryOp),
+    /// Assignment, compound when the operator is set.
+    Assign(Option<BinaryOp>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InfixOperator {
+    pub infix: Infix,
+    pub precedence: u8,
+    pub associativity: Associativity,
+}
+
+impl InfixOperator {
+    // The minimum precedence for the right-hand operand.
+    pub fn right_precedence(&self) -> u8 {
+        match self.associativity {
+            Associativity::Left => self.precedence + 1,
+            Associativity::Right => self.precedence,
+        }
+    }
+}
+
+pub fn infix_operator(operator: Operator) -> Option<InfixOperator> {
+    use Associativity::{Left, Right};
+
+    let (infix, precedence, associativity) = match operator {
+        Operator::Assign => (Infix::Assign(None), 1, Right),
+        Operator::PlusAssign => (Infix::Assign(Some(BinaryOp::Add)), 1, Right),
+        Operator::MinusAssign => (Infix::Assign(Some(BinaryOp::Sub)), 1, Right),
+        Operator::OrOr => (Infix::Binary(BinaryOp::Or), 2, Left),
+        Operator::AndAnd => (Infix::Binary(BinaryOp::And), 3, Left),
+        Operator::Pipe => (Infix::Binary(BinaryOp::BitOr), 4, Left),
+        Operator::Caret => (Infix::Binary(BinaryOp::BitXor), 5, Left),
+        Operator::Ampersand => (Infix::Binary(BinaryOp::BitAnd), 6, Left),
+        Operator::Equal => (Infix::Binary(BinaryOp::Equal), 7, Left),
+        Operator::NotEqual => (Infix::Binary(BinaryOp::NotEqual), 7, Left),
+        Operator::Less => (Infix::Binary(BinaryOp::Less), 8, Left),
+        Operator::LessEqual => (Infix::Binary(BinaryOp::LessEqual), 8, Left),
+        Operator::Greater => (Infix::Binary(BinaryOp::Greater), 8, Left),
+        Operator::GreaterEqual => (Infix::Binary(BinaryOp::GreaterEqual), 8, Left),
+        Operator::ShiftLeft => (Infix::Binary(BinaryOp::Shl), 9, Left),
+        Operator::ShiftRight => (Infix::Binary(BinaryOp::Shr), 9, Left),
+        Operator::Plus => (Infix::Binary(BinaryOp::Add), 10, Left),
+        Operator::Minus => (Infix::Binary(BinaryOp::Sub), 10, Left),
+        Operator::Star => (Infix::Binary(BinaryOp::Mul), 11, Left),
+        Operator::Slash => (Infix::Binary(BinaryOp::Div), 11, Left),
+        Operator::Percent => (Infix::Binary(BinaryOp::Mod), 11, Left),
+        _ => return None,
+    };
+    Some(InfixOperator {
+        infix,
+        precedence,
+        associativity,
+    })
+}
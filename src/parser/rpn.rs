@@ -0,0 +1,124 @@
+use crate::parser::ast::{BinaryOp, Expr, ExprKind, Literal, Type, UnaryOp};
+use crate::symbol::Symbol;
+use std::fmt::{Display, Formatter};
+
+/// One element of an expression in reverse Polish notation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rpn<'src> {
+    Literal(Literal<'src>),
+    Var(Symbol),
+    Binary(BinaryOp),
+    Unary(UnaryOp),
+    Assign(Option<BinaryOp>),
+    /// Call with the given number of arguments, the callee comes first.
+    Call(usize),
+    Index,
+    Convert(Type),
+}
+
+impl Display for Rpn<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rpn::Literal(literal) => write!(f, "{}", literal),
+            Rpn::Var(name) => write!(f, "{}", name),
+            Rpn::Binary(op) => write!(f, "{}", op.symbol()),
+            // spelled differently from binary minus so the output stays unambiguous
+            Rpn::Unary(UnaryOp::Neg) => write!(f, "neg"),
+            Rpn::Unary(op) => write!(f, "{}", op.symbol()),
+            Rpn::Assign(op) => write!(f, "{}=", op.map_or("", BinaryOp::symbol)),
+            Rpn::Call(argc) => write!(f, "call/{}", argc),
+            Rpn::Index => write!(f, "[]"),
+            Rpn::Convert(to) => write!(f, "({})", to),
+        }
+    }
+}
+
+/// Flattens a parsed expression into reverse Polish notation, operands before
+/// their operator. Since the tree already encodes precedence, associativity and
+/// parentheses, this is a post-order walk.
+pub fn convert_to_rpn<'src>(expr: &Expr<'src>) -> Vec<Rpn<'src>> {
+    let mut output = Vec::new();
+    push_rpn(expr, &mut output);
+    output
+}
+
+fn push_rpn<'src>(expr: &Expr<'src>, output: &mut Vec<Rpn<'src>>) {
+    match &expr.kind {
+        ExprKind::Binary { op, left, right } => {
+            push_rpn(left, output);
+            push_rpn(right, output);
+            output.push(Rpn::Binary(*op));
+        }
+        ExprKind::Unary { op, operand } => {
+            push_rpn(operand, output);
+            output.push(Rpn::Unary(*op));
+        }
+        ExprKind::Assign { target, op, value } => {
+            push_rpn(target, output);
+            push_rpn(value, output);
+            output.push(Rpn::Assign(*op));
+        }
+        ExprKind::Call { callee, args } => {
+            push_rpn(callee, output);
+            for arg in args {
+                push_rpn(arg, output);
+            }
+            output.push(Rpn::Call(args.len()));
+        }
+        ExprKind::Literal(literal) => output.push(Rpn::Literal(literal.clone())),
+        ExprKind::Var(name) => output.push(Rpn::Var(*name)),
+        ExprKind::Index { array, index } => {
+            push_rpn(array, output);
+            push_rpn(index, output);
+            output.push(Rpn::Index);
+        }
+        ExprKind::Convert { to, operand } => {
+            push_rpn(operand, output);
+            output.push(Rpn::Convert(to.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::rpn::convert_to_rpn;
+    use crate::parser::Parser;
+    use crate::tokenizer::Tokenizer;
+
+    fn rpn(input: &str) -> Vec<String> {
+        let expr = Parser::new(Tokenizer::new(input))
+            .parse_expression()
+            .unwrap();
+        convert_to_rpn(&expr)
+            .iter()
+            .map(|item| item.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn convert_to_rpn_test_variable_declaration() {
+        assert_eq!(rpn("a = 1"), vec!["a", "1", "="]);
+    }
+
+    #[test]
+    fn convert_to_rpn_test_variable_expression() {
+        // a = 10 + 33 * 7
+        assert_eq!(
+            rpn("a = 10 + 33 * 7"),
+            vec!["a", "10", "33", "7", "*", "+", "="]
+        );
+    }
+
+    #[test]
+    fn convert_to_rpn_test_right_associative_assignment() {
+        assert_eq!(rpn("a = b = c"), vec!["a", "b", "c", "=", "=",]);
+    }
+
+    #[test]
+    fn convert_to_rpn_test_parentheses_unary_and_calls() {
+        assert_eq!(
+            rpn("-(a - b) * f(x, y[1])"),
+            vec!["a", "b", "-", "neg", "f", "x", "y", "1", "[]", "call/2", "*"]
+        );
+    }
+}
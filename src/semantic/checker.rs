@@ -0,0 +1,632 @@
+use crate::diagnostic::{codes, Diagnostic};
+use crate::parser::ast::{
+    BinaryOp, Expr, ExprKind, FunctionDecl, Item, Literal, Program, Stmt, StmtKind, Type, UnaryOp,
+    VarDecl,
+};
+use crate::semantic::scope::{ScopeKind, SymbolTable};
+use crate::span::Span;
+use crate::symbol::Symbol;
+use crate::vm::NativeRegistry;
+use std::collections::HashSet;
+
+/// Assigns a type to every expression and checks that values are used at
+/// the types they are declared with.
+///
+/// Arithmetic types (`int`, `char`, `bool` and `float`) convert into each
+/// other implicitly, as in C. Every such conversion is made explicit in the
+/// tree as an `ExprKind::Convert`, so code generation never has to know the
+/// rules. Compound assignments are rewritten to plain ones on the way, since
+/// `c += 1` on a `char` converts the sum back to `char`.
+///
+/// Arrays and indexing are typed like C's but rejected once checked, as the
+/// compiler cannot lower them yet.
+///
+/// Runs after the resolver, so every name is assumed to be declared.
+pub struct TypeChecker {
+    symbols: SymbolTable<Type>,
+    /// Globals still bound to a native rather than a declaration of the program.
+    natives: HashSet<Symbol>,
+    /// Natives taking further arguments of any type after their parameters.
+    variadic: HashSet<Symbol>,
+    /// The return type of the function being checked, or `None` in a REPL
+    /// script, which may return a value of any type.
+    return_type: Option<Type>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        TypeChecker::new()
+    }
+}
+
+impl TypeChecker {
+    pub fn new() -> TypeChecker {
+        TypeChecker {
+            symbols: SymbolTable::new(),
+            natives: HashSet::new(),
+            variadic: HashSet::new(),
+            return_type: None,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn with_natives(mut self, natives: &NativeRegistry) -> TypeChecker {
+        for native in natives.iter() {
+            let name = Symbol::intern(&native.name);
+            let signature = &native.signature;
+            let ty = function_type(&signature.ret, &signature.params);
+            let _ = self.symbols.declare(name, Span::default(), ty);
+            self.natives.insert(name);
+            if signature.variadic {
+                self.variadic.insert(name);
+            }
+        }
+        self
+    }
+
+    pub fn check(&mut self, program: &mut Program) -> Vec<Diagnostic> {
+        for item in &program.items {
+            self.declare_item(item);
+        }
+        self.check_items(program);
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    /// Checks declarations typed into a REPL. A later input may redefine a
+    /// global from an earlier one, with a different type.
+    pub fn check_declarations(&mut self, program: &mut Program) -> Vec<Diagnostic> {
+        let mut seen = HashSet::new();
+        for item in &program.items {
+            let (name, ..) = item_declaration(item);
+            if seen.insert(name) {
+                self.symbols.forget(name);
+            }
+            self.declare_item(item);
+        }
+        self.check_items(program);
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    /// Checks statements as the body of a script, with the globals declared
+    /// so far in scope.
+    pub fn check_statements(&mut self, stmts: &mut [Stmt]) -> Vec<Diagnostic> {
+        self.symbols.push_scope(ScopeKind::Function);
+        self.return_type = None;
+        for stmt in stmts {
+            self.check_statement(stmt);
+        }
+        self.symbols.pop_scope();
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    /// Checks an expression typed into a REPL, whose value is printed.
+    pub fn check_expression(&mut self, expr: &mut Expr) -> Vec<Diagnostic> {
+        self.check_value(expr);
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    fn check_items(&mut self, program: &mut Program) {
+        for item in &mut program.items {
+            match item {
+                Item::Function(function) => self.check_function(function),
+                Item::Prototype(_) => {}
+                Item::Global(global) => self.check_var_decl(global),
+            }
+        }
+    }
+
+    // Prototypes and the definition of a function must agree on its type.
+    fn declare_item(&mut self, item: &Item) {
+        let (name, span, ty) = item_declaration(item);
+        // the program may replace a native with a declaration of its own
+        if self.natives.remove(&name) {
+            self.variadic.remove(&name);
+            self.symbols.forget(name);
+        }
+        let Err(previous) = self.symbols.declare(name, span, ty.clone()) else {
+            return;
+        };
+        let Some(declared) = self.symbols.lookup(name).map(|entry| &entry.value) else {
+            return;
+        };
+        if *declared != ty {
+            let diagnostic = Diagnostic::error(
+                codes::CONFLICTING_TYPES,
+                format!("conflicting types for `{}`", name),
+                span,
+            )
+            .with_label(previous, format!("previously declared as `{}`", declared))
+            .with_note(format!("now declared as `{}`", ty));
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn check_function(&mut self, function: &mut FunctionDecl) {
+        self.symbols.push_scope(ScopeKind::Function);
+        for param in &function.params {
+            if param.ty == Type::Void {
+                self.void_variable(param.name, param.span);
+            }
+            let _ = self
+                .symbols
+                .declare(param.name, param.span, param.ty.clone().decay());
+        }
+        self.return_type = Some(function.ret_ty.clone());
+        for stmt in &mut function.body {
+            self.check_statement(stmt);
+        }
+        self.return_type = None;
+        self.symbols.pop_scope();
+    }
+
+    fn check_statement(&mut self, stmt: &mut Stmt) {
+        let span = stmt.span;
+        match &mut stmt.kind {
+            StmtKind::VarDecl(decl) => self.check_var_decl(decl),
+            StmtKind::Assign { target, op, value } => {
+                self.check_assign(target, op, value, span);
+            }
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.check_condition(condition);
+                self.check_statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.check_statement(else_branch);
+                }
+            }
+            StmtKind::While { condition, body } => {
+                self.check_condition(condition);
+                self.check_statement(body);
+            }
+            StmtKind::DoWhile { body, condition } => {
+                self.check_statement(body);
+                self.check_condition(condition);
+            }
+            StmtKind::For {
+                init,
+                condition,
+                step,
+                body,
+            } => {
+                self.symbols.push_scope(ScopeKind::Block);
+                if let Some(init) = init {
+                    self.check_statement(init);
+                }
+                if let Some(condition) = condition {
+                    self.check_condition(condition);
+                }
+                if let Some(step) = step {
+                    self.check_statement(step);
+                }
+                self.check_statement(body);
+                self.symbols.pop_scope();
+            }
+            StmtKind::Return(value) => self.check_return(value.as_mut(), span),
+            StmtKind::Break | StmtKind::Continue => {}
+            StmtKind::Block(stmts) => {
+                self.symbols.push_scope(ScopeKind::Block);
+                for stmt in stmts {
+                    self.check_statement(stmt);
+                }
+                self.symbols.pop_scope();
+            }
+            StmtKind::Expr(expr) => {
+                self.check_value(expr);
+            }
+        }
+    }
+
+    fn check_var_decl(&mut self, decl: &mut VarDecl) {
+        if decl.ty == Type::Void {
+            self.void_variable(decl.name, decl.span);
+        } else if let Type::Array(..) = decl.ty {
+            self.arrays_unsupported("arrays are not supported yet", decl.span);
+        } else if let Some(init) = &mut decl.init {
+            self.coerce(init, &decl.ty);
+        }
+        let _ = self.symbols.declare(decl.name, decl.span, decl.ty.clone());
+    }
+
+    fn check_return(&mut self, value: Option<&mut Expr>, span: Span) {
+        let Some(expected) = self.return_type.clone() else {
+            if let Some(value) = value {
+                self.check_value(value);
+            }
+            return;
+        };
+        match value {
+            Some(value) if expected == Type::Void => {
+                // a value that failed to check has already been reported
+                let checked = self.check_value(value);
+                if checked.is_some() {
+                    self.diagnostics.push(Diagnostic::error(
+                        codes::INVALID_RETURN,
+                        "`return` with a value in a function returning `void`",
+                        value.span,
+                    ));
+                }
+            }
+            Some(value) => self.coerce(value, &expected),
+            None if expected != Type::Void => self.diagnostics.push(Diagnostic::error(
+                codes::INVALID_RETURN,
+                format!(
+                    "`return` without a value in a function returning `{}`",
+                    expected
+                ),
+                span,
+            )),
+            None => {}
+        }
+    }
+
+    // Conditions accept any scalar, tested against zero.
+    fn check_condition(&mut self, condition: &mut Expr) {
+        if let Some(ty) = self.check_value(condition) {
+            if !ty.is_scalar() {
+                self.diagnostics.push(Diagnostic::error(
+                    codes::MISMATCHED_TYPES,
+                    format!("mismatched types: expected a condition, found `{}`", ty),
+                    condition.span,
+                ));
+            }
+        }
+    }
+
+    // The type of `target`, and `value` converted to it.
+    fn check_assign<'src>(
+        &mut self,
+        target: &mut Expr<'src>,
+        op: &mut Option<BinaryOp>,
+        value: &mut Expr<'src>,
+        span: Span,
+    ) -> Option<Type> {
+        let target_ty = self.check_expression_type(target);
+        if let Some(ty @ (Type::Array(..) | Type::Function(..))) = &target_ty {
+            self.diagnostics.push(Diagnostic::error(
+                codes::NOT_ASSIGNABLE,
+                format!("cannot assign to `{}`, which has type `{}`", target, ty),
+                target.span,
+            ));
+            self.check_value(value);
+            return None;
+        }
+        // `x op= v` becomes `x = x op v`, which only evaluates the target once
+        // when it is a plain variable
+        let is_variable = matches!(target.kind, ExprKind::Var(_));
+        if let Some(compound) = op.filter(|_| !is_variable) {
+            if target_ty.is_some() {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        codes::NOT_ASSIGNABLE,
+                        format!("cannot use `{}=` on `{}`", compound.symbol(), target),
+                        target.span,
+                    )
+                    .with_note("compound assignment is only supported on variables"),
+                );
+            }
+            self.check_value(value);
+            return None;
+        }
+        if let Some(op) = op.take() {
+            let right = std::mem::replace(value, placeholder(value.span));
+            *value = Expr::new(
+                ExprKind::Binary {
+                    op,
+                    left: Box::new(target.clone()),
+                    right: Box::new(right),
+                },
+                span,
+            );
+        }
+        let target_ty = target_ty?;
+        self.coerce(value, &target_ty);
+        Some(target_ty)
+    }
+
+    // Checks `expr` where its value is used, so arrays decay to pointers.
+    fn check_value(&mut self, expr: &mut Expr) -> Option<Type> {
+        self.check_expression_type(expr).map(Type::decay)
+    }
+
+    // Records the type of `expr` in the tree. `None` means an error was
+    // already reported, and suppresses errors that would only repeat it.
+    fn check_expression_type(&mut self, expr: &mut Expr) -> Option<Type> {
+        let ty = self.infer(expr)?;
+        expr.ty = Some(ty.clone());
+        Some(ty)
+    }
+
+    fn infer(&mut self, expr: &mut Expr) -> Option<Type> {
+        let span = expr.span;
+        match &mut expr.kind {
+            ExprKind::Binary { op, left, right } => self.check_binary(*op, left, right, span),
+            ExprKind::Unary { op, operand } => {
+                let ty = self.check_value(operand)?;
+                let result = match op {
+                    UnaryOp::Neg if ty.is_arithmetic() => promote(&ty),
+                    UnaryOp::Not if ty.is_scalar() => return Some(Type::Bool),
+                    UnaryOp::BitNot if ty.is_integer() => Type::Int,
+                    _ => {
+                        self.diagnostics.push(Diagnostic::error(
+                            codes::INVALID_OPERANDS,
+                            format!("cannot apply unary `{}` to `{}`", op.symbol(), ty),
+                            span,
+                        ));
+                        return None;
+                    }
+                };
+                convert(operand, &ty, &result);
+                Some(result)
+            }
+            ExprKind::Assign { target, op, value } => self.check_assign(target, op, value, span),
+            ExprKind::Call { callee, args } => {
+                let callee_ty = self.check_value(callee);
+                let Some(Type::Function(ret, params)) = &callee_ty else {
+                    for arg in args.iter_mut() {
+                        self.check_value(arg);
+                    }
+                    if let Some(ty) = callee_ty {
+                        self.diagnostics.push(Diagnostic::error(
+                            codes::NOT_A_FUNCTION,
+                            format!("`{}` is not a function, it has type `{}`", callee, ty),
+                            callee.span,
+                        ));
+                    }
+                    return None;
+                };
+                let variadic =
+                    matches!(&callee.kind, ExprKind::Var(name) if self.variadic.contains(name));
+                let accepted = if variadic {
+                    args.len() >= params.len()
+                } else {
+                    args.len() == params.len()
+                };
+                if !accepted {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            codes::WRONG_ARGUMENT_COUNT,
+                            format!(
+                                "`{}` takes {}{} argument(s) but {} were supplied",
+                                callee,
+                                if variadic { "at least " } else { "" },
+                                params.len(),
+                                args.len()
+                            ),
+                            span,
+                        )
+                        .with_note(format!(
+                            "`{}` has type `{}`{}",
+                            callee,
+                            function_type(ret, params),
+                            if variadic {
+                                ", followed by any arguments"
+                            } else {
+                                ""
+                            }
+                        )),
+                    );
+                }
+                for (i, arg) in args.iter_mut().enumerate() {
+                    match params.get(i) {
+                        Some(param) => self.coerce(arg, param),
+                        None => {
+                            self.check_value(arg);
+                        }
+                    }
+                }
+                Some(Type::clone(ret))
+            }
+            ExprKind::Literal(literal) => Some(match literal {
+                Literal::Int(_) => Type::Int,
+                Literal::Float(_) => Type::Float,
+                Literal::Char(_) => Type::Char,
+                Literal::Bool(_) => Type::Bool,
+                Literal::String(_) => Type::Pointer(Box::new(Type::Char)),
+            }),
+            ExprKind::Var(name) => self.symbols.lookup(*name).map(|entry| entry.value.clone()),
+            ExprKind::Index { array, index } => {
+                let array_ty = self.check_value(array);
+                let index_ty = self.check_value(index);
+                let array_ty = array_ty?;
+                if !matches!(array_ty, Type::Pointer(_)) {
+                    self.diagnostics.push(Diagnostic::error(
+                        codes::NOT_INDEXABLE,
+                        format!("cannot index into a value of type `{}`", array_ty),
+                        array.span,
+                    ));
+                    return None;
+                }
+                let index_ty = index_ty?;
+                if !index_ty.is_integer() {
+                    self.diagnostics.push(Diagnostic::error(
+                        codes::MISMATCHED_TYPES,
+                        format!(
+                            "mismatched types: expected an integer index, found `{}`",
+                            index_ty
+                        ),
+                        index.span,
+                    ));
+                    return None;
+                }
+                self.arrays_unsupported("indexing is not supported yet", span);
+                None
+            }
+            ExprKind::Convert { to, .. } => Some(to.clone()),
+        }
+    }
+
+    fn check_binary(
+        &mut self,
+        op: BinaryOp,
+        left: &mut Expr,
+        right: &mut Expr,
+        span: Span,
+    ) -> Option<Type> {
+        let left_ty = self.check_value(left);
+        let right_ty = self.check_value(right);
+        let (left_ty, right_ty) = (left_ty?, right_ty?);
+
+        let (operands, result) = match op {
+            BinaryOp::And | BinaryOp::Or if left_ty.is_scalar() && right_ty.is_scalar() => {
+                return Some(Type::Bool);
+            }
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div
+                if left_ty.is_arithmetic() && right_ty.is_arithmetic() =>
+            {
+                let common = common_type(&left_ty, &right_ty);
+                (common.clone(), common)
+            }
+            BinaryOp::Mod
+            | BinaryOp::BitAnd
+            | BinaryOp::BitOr
+            | BinaryOp::BitXor
+            | BinaryOp::Shl
+            | BinaryOp::Shr
+                if left_ty.is_integer() && right_ty.is_integer() =>
+            {
+                (Type::Int, Type::Int)
+            }
+            BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::Less
+            | BinaryOp::LessEqual
+            | BinaryOp::Greater
+            | BinaryOp::GreaterEqual
+                if left_ty == right_ty && left_ty.is_arithmetic() =>
+            {
+                return Some(Type::Bool);
+            }
+            BinaryOp::Equal | BinaryOp::NotEqual
+                if left_ty == right_ty && matches!(left_ty, Type::Pointer(_)) =>
+            {
+                return Some(Type::Bool);
+            }
+            BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::Less
+            | BinaryOp::LessEqual
+            | BinaryOp::Greater
+            | BinaryOp::GreaterEqual
+                if left_ty.is_arithmetic() && right_ty.is_arithmetic() =>
+            {
+                (common_type(&left_ty, &right_ty), Type::Bool)
+            }
+            _ => {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        codes::INVALID_OPERANDS,
+                        format!(
+                            "cannot apply `{}` to `{}` and `{}`",
+                            op.symbol(),
+                            left_ty,
+                            right_ty
+                        ),
+                        span,
+                    )
+                    .with_label(left.span, format!("this has type `{}`", left_ty))
+                    .with_label(right.span, format!("this has type `{}`", right_ty)),
+                );
+                return None;
+            }
+        };
+        convert(left, &left_ty, &operands);
+        convert(right, &right_ty, &operands);
+        Some(result)
+    }
+
+    // Checks `expr` and converts it to `expected` if the types allow it.
+    fn coerce(&mut self, expr: &mut Expr, expected: &Type) {
+        let Some(found) = self.check_value(expr) else {
+            return;
+        };
+        if found == *expected {
+            return;
+        }
+        if found.is_arithmetic() && expected.is_arithmetic() {
+            convert(expr, &found, expected);
+            return;
+        }
+        self.diagnostics.push(Diagnostic::error(
+            codes::MISMATCHED_TYPES,
+            format!(
+                "mismatched types: expected `{}`, found `{}`",
+                expected, found
+            ),
+            expr.span,
+        ));
+    }
+
+    fn arrays_unsupported(&mut self, message: &str, span: Span) {
+        self.diagnostics
+            .push(Diagnostic::error(codes::ARRAYS_UNSUPPORTED, message, span));
+    }
+
+    fn void_variable(&mut self, name: Symbol, span: Span) {
+        self.diagnostics.push(Diagnostic::error(
+            codes::VOID_VARIABLE,
+            format!("`{}` is declared `void`, which has no values", name),
+            span,
+        ));
+    }
+}
+
+fn item_declaration(item: &Item) -> (Symbol, Span, Type) {
+    match item {
+        Item::Function(function) => {
+            let params = function.params.iter().map(|param| param.ty.clone());
+            let ty = function_type(&function.ret_ty, &params.collect::<Vec<_>>());
+            (function.name, function.span, ty)
+        }
+        Item::Prototype(prototype) => {
+            let params = prototype.params.iter().map(|param| param.ty.clone());
+            let ty = function_type(&prototype.ret_ty, &params.collect::<Vec<_>>());
+            (prototype.name, prototype.span, ty)
+        }
+        Item::Global(global) => (global.name, global.span, global.ty.clone()),
+    }
+}
+
+// Array parameters are pointers, as in C.
+fn function_type(ret: &Type, params: &[Type]) -> Type {
+    let params = params.iter().map(|param| param.clone().decay()).collect();
+    Type::Function(Box::new(ret.clone()), params)
+}
+
+// The usual arithmetic conversions: `float` if either side is, `int` otherwise.
+fn common_type(left: &Type, right: &Type) -> Type {
+    if *left == Type::Float || *right == Type::Float {
+        Type::Float
+    } else {
+        Type::Int
+    }
+}
+
+// `char` and `bool` take part in arithmetic as `int`.
+fn promote(ty: &Type) -> Type {
+    common_type(ty, ty)
+}
+
+// Wraps `expr`, of type `from`, in a conversion to `to` unless they are equal.
+fn convert(expr: &mut Expr, from: &Type, to: &Type) {
+    if from == to {
+        return;
+    }
+    let span = expr.span;
+    let operand = std::mem::replace(expr, placeholder(span));
+    *expr = Expr::new(
+        ExprKind::Convert {
+            to: to.clone(),
+            operand: Box::new(operand),
+        },
+        span,
+    );
+    expr.ty = Some(to.clone());
+}
+
+fn placeholder<'src>(span: Span) -> Expr<'src> {
+    Expr::new(ExprKind::Literal(Literal::Int(0)), span)
+}
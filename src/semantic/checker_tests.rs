@@ -0,0 +1,331 @@
+use super::*;
+use crate::diagnostic::{codes, Diagnostic};
+use crate::parser::ast::{Expr, ExprKind, FunctionDecl, Item, Program, StmtKind, Type};
+use crate::parser::Parser;
+use crate::tokenizer::Tokenizer;
+use crate::vm::NativeRegistry;
+
+fn check(input: &str) -> (Program<'_>, Vec<Diagnostic>) {
+    check_with_natives(input, &NativeRegistry::new())
+}
+
+fn check_with_natives<'src>(
+    input: &'src str,
+    natives: &NativeRegistry,
+) -> (Program<'src>, Vec<Diagnostic>) {
+    let (mut program, diagnostics) = Parser::new(Tokenizer::new(input)).parse();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    assert!(Resolver::new()
+        .with_natives(natives)
+        .resolve(&program)
+        .is_empty());
+    let diagnostics = TypeChecker::new().with_natives(natives).check(&mut program);
+    (program, diagnostics)
+}
+
+fn errors(input: &str) -> Vec<(&'static str, String)> {
+    check(input)
+        .1
+        .into_iter()
+        .map(|d| (d.code, d.message))
+        .collect()
+}
+
+fn function<'a, 'src>(program: &'a Program<'src>, index: usize) -> &'a FunctionDecl<'src> {
+    let Item::Function(function) = &program.items[index] else {
+        panic!("expected a function");
+    };
+    function
+}
+
+// The expression in the `n`th statement of `function`.
+fn body_expr<'a, 'src>(function: &'a FunctionDecl<'src>, n: usize) -> &'a Expr<'src> {
+    match &function.body[n].kind {
+        StmtKind::VarDecl(decl) => decl.init.as_ref().unwrap(),
+        StmtKind::Return(Some(expr)) | StmtKind::Expr(expr) => expr,
+        StmtKind::Assign { value, .. } => value,
+        other => panic!("no expression in {:?}", other),
+    }
+}
+
+#[test]
+fn test_every_expression_gets_a_type() {
+    let input = "\
+bool test() {
+  float f = 2.5;
+  return f < 3 && 'a' == 'b';
+}";
+    let (program, diagnostics) = check(input);
+    assert_eq!(diagnostics, vec![]);
+    let test = function(&program, 0);
+    let ret = body_expr(test, 1);
+    assert_eq!(ret.ty, Some(Type::Bool));
+    let ExprKind::Binary { left, right, .. } = &ret.kind else {
+        panic!("expected `&&`");
+    };
+    assert_eq!(left.to_string(), "(f < ((float) 3))");
+    // operands of the same type are compared as they are
+    assert_eq!(right.to_string(), "('a' == 'b')");
+    assert_eq!(body_expr(test, 0).ty, Some(Type::Float));
+}
+
+#[test]
+fn test_implicit_conversions_are_explicit_in_the_tree() {
+    let input = "\
+int half(float x) { return x / 2; }
+int main() {
+  char c = 66;
+  c += 1;
+  bool b = half(c);
+  return 'a' + true;
+}";
+    let (program, diagnostics) = check(input);
+    assert_eq!(diagnostics, vec![]);
+    let half = function(&program, 0);
+    assert_eq!(body_expr(half, 0).to_string(), "((int) (x / ((float) 2)))");
+
+    let main = function(&program, 1);
+    assert_eq!(body_expr(main, 0).to_string(), "((char) 66)");
+    // `c += 1` becomes `c = (char)(c + 1)`
+    let StmtKind::Assign { op, value, .. } = &main.body[1].kind else {
+        panic!("expected an assignment");
+    };
+    assert_eq!(*op, None);
+    assert_eq!(value.to_string(), "((char) (((int) c) + 1))");
+    assert_eq!(body_expr(main, 2).to_string(), "((bool) half(((float) c)))");
+    assert_eq!(
+        body_expr(main, 3).to_string(),
+        "(((int) 'a') + ((int) true))"
+    );
+}
+
+#[test]
+fn test_mismatched_types() {
+    let (_, diagnostics) = check("int a = \"x\";");
+    assert_eq!(diagnostics.len(), 1);
+    let diagnostic = &diagnostics[0];
+    assert_eq!(diagnostic.code, codes::MISMATCHED_TYPES);
+    assert_eq!(
+        diagnostic.message,
+        "mismatched types: expected `int`, found `char*`"
+    );
+    assert_eq!(diagnostic.span.start.column, 9);
+    assert_eq!(diagnostic.span.len(), 3);
+
+    assert_eq!(
+        errors("int f(int n) { return n; } int main() { return f(\"no\"); }"),
+        vec![(
+            codes::MISMATCHED_TYPES,
+            "mismatched types: expected `int`, found `char*`".to_string()
+        )]
+    );
+}
+
+#[test]
+fn test_return_types() {
+    let (_, diagnostics) = check("void f() {\n  return 1;\n}");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, codes::INVALID_RETURN);
+    assert_eq!(
+        diagnostics[0].message,
+        "`return` with a value in a function returning `void`"
+    );
+    assert_eq!(diagnostics[0].span.start.line, 2);
+    assert_eq!(diagnostics[0].span.start.column, 10);
+
+    assert_eq!(
+        errors("int f() { return; } void g() { return; } float h() { return 1; }"),
+        vec![(
+            codes::INVALID_RETURN,
+            "`return` without a value in a function returning `int`".to_string()
+        )]
+    );
+}
+
+#[test]
+fn test_calls() {
+    let input = "\
+int add(int a, int b) { return a + b; }
+void log(int n) {}
+int main() {
+  int x = 1;
+  add(1);
+  x(2);
+  return add(1, 2) + log(3);
+}";
+    assert_eq!(
+        errors(input),
+        vec![
+            (
+                codes::WRONG_ARGUMENT_COUNT,
+                "`add` takes 2 argument(s) but 1 were supplied".to_string()
+            ),
+            (
+                codes::NOT_A_FUNCTION,
+                "`x` is not a function, it has type `int`".to_string()
+            ),
+            (
+                codes::INVALID_OPERANDS,
+                "cannot apply `+` to `int` and `void`".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_invalid_operands_and_targets() {
+    let input = "\
+int f() { return 0; }
+int main() {
+  float x = 1.5 % 2;
+  int y = -\"s\";
+  f = 1;
+  void v;
+  if (f()) {}
+  return \"a\" < \"b\";
+}";
+    assert_eq!(
+        errors(input),
+        vec![
+            (
+                codes::INVALID_OPERANDS,
+                "cannot apply `%` to `float` and `int`".to_string()
+            ),
+            (
+                codes::INVALID_OPERANDS,
+                "cannot apply unary `-` to `char*`".to_string()
+            ),
+            (
+                codes::NOT_ASSIGNABLE,
+                "cannot assign to `f`, which has type `int()`".to_string()
+            ),
+            (
+                codes::VOID_VARIABLE,
+                "`v` is declared `void`, which has no values".to_string()
+            ),
+            (
+                codes::INVALID_OPERANDS,
+                "cannot apply `<` to `char*` and `char*`".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_arrays_and_indexing_are_rejected() {
+    let input = "\
+int table[4];
+int main(int argv[]) {
+  char *s = \"ab\";
+  int n = argv[0];
+  s[0] += 1;
+  return n[0] + s[true + 1.5];
+}";
+    let diagnostics = check(input).1;
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|d| (d.code, d.message.as_str(), d.span.start.line))
+            .collect::<Vec<_>>(),
+        vec![
+            (codes::ARRAYS_UNSUPPORTED, "arrays are not supported yet", 1),
+            (
+                codes::ARRAYS_UNSUPPORTED,
+                "indexing is not supported yet",
+                4
+            ),
+            (
+                codes::ARRAYS_UNSUPPORTED,
+                "indexing is not supported yet",
+                5
+            ),
+            (
+                codes::NOT_INDEXABLE,
+                "cannot index into a value of type `int`",
+                6
+            ),
+            (
+                codes::MISMATCHED_TYPES,
+                "mismatched types: expected an integer index, found `float`",
+                6
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_prototypes_must_match_the_definition() {
+    let (_, diagnostics) = check("int f(int n);\nint f(float x) { return 0; }");
+    assert_eq!(diagnostics.len(), 1);
+    let diagnostic = &diagnostics[0];
+    assert_eq!(diagnostic.code, codes::CONFLICTING_TYPES);
+    assert_eq!(diagnostic.message, "conflicting types for `f`");
+    assert_eq!(diagnostic.span.start.line, 2);
+    assert_eq!(
+        diagnostic.labels[0].message,
+        "previously declared as `int(int)`"
+    );
+    assert_eq!(diagnostic.notes, vec!["now declared as `int(float)`"]);
+
+    // arguments are converted to the prototype's parameter types
+    let (program, diagnostics) = check(
+        "float half(float x);\nfloat half(float x) { return x / 2; }\nint main() { half(3); }",
+    );
+    assert_eq!(diagnostics, vec![]);
+    assert_eq!(
+        body_expr(function(&program, 2), 0).to_string(),
+        "half(((float) 3))"
+    );
+}
+
+#[test]
+fn test_native_calls() {
+    let natives = NativeRegistry::with_stdlib();
+
+    let input = "\
+int main() {
+  print_char(65);
+  printf(\"%d %s\\n\", 1.5, \"x\");
+  return getchar();
+}";
+    let (program, diagnostics) = check_with_natives(input, &natives);
+    assert_eq!(diagnostics, vec![]);
+    let main = function(&program, 0);
+    assert_eq!(body_expr(main, 0).to_string(), "print_char(((char) 65))");
+    // variadic arguments are passed as they are
+    assert_eq!(
+        body_expr(main, 1).to_string(),
+        "printf(\"%d %s\\n\", 1.5, \"x\")"
+    );
+
+    let input = "int main() { printf(); puts(1); exit(); return 0; }";
+    let messages: Vec<String> = check_with_natives(input, &natives)
+        .1
+        .into_iter()
+        .map(|d| d.message)
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "`printf` takes at least 1 argument(s) but 0 were supplied",
+            "mismatched types: expected `char*`, found `int`",
+            "`exit` takes 1 argument(s) but 0 were supplied",
+        ]
+    );
+
+    // a program's own definition replaces the native, type and all
+    let input = "float puts(float x) { return x; }\nint main() { puts(2); return 0; }";
+    assert_eq!(check_with_natives(input, &natives).1, vec![]);
+}
+
+#[test]
+fn test_errors_do_not_cascade() {
+    // the mismatch inside is reported once, not again for each enclosing operator
+    assert_eq!(
+        errors("int main() { return (1 + \"a\" * 2) - 3 == 4; }"),
+        vec![(
+            codes::INVALID_OPERANDS,
+            "cannot apply `*` to `char*` and `int`".to_string()
+        )]
+    );
+}
@@ -0,0 +1,11 @@
+mod checker;
+#[cfg(test)]
+mod checker_tests;
+mod resolver;
+#[cfg(test)]
+mod resolver_tests;
+mod scope;
+
+pub use checker::TypeChecker;
+pub use resolver::{Resolver, SymbolKind};
+pub use scope::{Entry, ScopeKind, SymbolTable};
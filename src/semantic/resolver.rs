@@ -0,0 +1,367 @@
+use crate::diagnostic::{codes, Diagnostic};
+use crate::parser::ast::{Expr, ExprKind, FunctionDecl, Item, Program, Stmt, StmtKind, VarDecl};
+use crate::semantic::scope::{ScopeKind, SymbolTable};
+use crate::span::Span;
+use crate::symbol::Symbol;
+use crate::vm::NativeRegistry;
+use std::collections::HashSet;
+
+/// What a name was declared as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    /// A function declared by a prototype and not defined so far.
+    Prototype,
+    Global,
+    Parameter,
+    Local,
+    /// A function provided by the VM, which the program may redefine.
+    Native,
+}
+
+impl SymbolKind {
+    fn describe(self) -> &'static str {
+        match self {
+            SymbolKind::Function | SymbolKind::Prototype => "function",
+            SymbolKind::Global => "global",
+            SymbolKind::Parameter => "parameter",
+            SymbolKind::Local => "local",
+            SymbolKind::Native => "native function",
+        }
+    }
+}
+
+/// Checks that every name is declared before it is used and declared only
+/// once per scope, and that `break` and `continue` are inside a loop.
+///
+/// Functions are visible from the whole program, so they may be used before
+/// their definition. So are globals, except from global initializers: globals
+/// are initialized in source order, and an initializer may only read those
+/// defined before it. A function may be declared by any number of
+/// prototypes but defined only once. Locals are visible from the end of their
+/// declaration to the end of their block, and may shadow outer names.
+/// Natives are declared before the program and replaced by any global or
+/// function of the same name.
+pub struct Resolver {
+    symbols: SymbolTable<SymbolKind>,
+    /// How many loops enclose the statement being resolved.
+    loop_depth: usize,
+    /// Uses of functions that only have a prototype, with their spans.
+    prototype_uses: Vec<(Symbol, Span)>,
+    /// The program's globals that are not initialized yet, checked while
+    /// `in_initializer` is set.
+    uninitialized_globals: HashSet<Symbol>,
+    in_initializer: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Resolver::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Resolver {
+        Resolver {
+            symbols: SymbolTable::new(),
+            loop_depth: 0,
+            prototype_uses: Vec::new(),
+            uninitialized_globals: HashSet::new(),
+            in_initializer: false,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn with_natives(mut self, natives: &NativeRegistry) -> Resolver {
+        for native in natives.iter() {
+            let name = Symbol::intern(&native.name);
+            // registry names are unique, so this never collides
+            let _ = self
+                .symbols
+                .declare(name, Span::default(), SymbolKind::Native);
+        }
+        self
+    }
+
+    pub fn resolve(&mut self, program: &Program) -> Vec<Diagnostic> {
+        for item in &program.items {
+            let (name, span, kind) = item_declaration(item);
+            self.declare(name, span, kind);
+            if kind == SymbolKind::Global {
+                self.uninitialized_globals.insert(name);
+            }
+        }
+        self.resolve_items(program);
+        self.report_undefined_functions();
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    /// Resolves declarations typed into a REPL. Globals stay known to later
+    /// calls, and a later input may redefine a global from an earlier one or
+    /// define a function declared by a prototype.
+    pub fn resolve_declarations(&mut self, program: &Program) -> Vec<Diagnostic> {
+        let mut seen = HashSet::new();
+        for item in &program.items {
+            let (name, span, kind) = item_declaration(item);
+            // a global from an earlier input keeps its value until redefined
+            let earlier = self.symbols.lookup(name).map(|entry| entry.value);
+            if kind == SymbolKind::Global && earlier != Some(SymbolKind::Global) {
+                self.uninitialized_globals.insert(name);
+            }
+            if seen.insert(name) {
+                self.symbols.forget(name);
+            }
+            self.declare(name, span, kind);
+        }
+        self.resolve_items(program);
+        self.prototype_uses.clear();
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    /// Resolves statements as the body of a script, with the globals declared
+    /// so far in scope.
+    pub fn resolve_statements(&mut self, stmts: &[Stmt]) -> Vec<Diagnostic> {
+        self.symbols.push_scope(ScopeKind::Function);
+        for stmt in stmts {
+            self.resolve_statement(stmt);
+        }
+        self.symbols.pop_scope();
+        self.prototype_uses.clear();
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    fn resolve_items(&mut self, program: &Program) {
+        for item in &program.items {
+            match item {
+                Item::Function(function) => self.resolve_function(function),
+                Item::Prototype(_) => {}
+                Item::Global(global) => {
+                    if let Some(init) = &global.init {
+                        self.in_initializer = true;
+                        self.resolve_expression(init);
+                        self.in_initializer = false;
+                    }
+                    self.uninitialized_globals.remove(&global.name);
+                }
+            }
+        }
+    }
+
+    fn resolve_function(&mut self, function: &FunctionDecl) {
+        self.symbols.push_scope(ScopeKind::Function);
+        for param in &function.params {
+            self.declare(param.name, param.span, SymbolKind::Parameter);
+        }
+        for stmt in &function.body {
+            self.resolve_statement(stmt);
+        }
+        self.symbols.pop_scope();
+    }
+
+    fn resolve_statement(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::VarDecl(decl) => self.resolve_var_decl(decl),
+            StmtKind::Assign { target, value, .. } => {
+                self.resolve_expression(target);
+                self.resolve_expression(value);
+            }
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.resolve_expression(condition);
+                self.resolve_statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_statement(else_branch);
+                }
+            }
+            StmtKind::While { condition, body } => {
+                self.resolve_expression(condition);
+                self.resolve_loop_body(body);
+            }
+            StmtKind::DoWhile { body, condition } => {
+                self.resolve_loop_body(body);
+                self.resolve_expression(condition);
+            }
+            StmtKind::For {
+                init,
+                condition,
+                step,
+                body,
+            } => {
+                self.symbols.push_scope(ScopeKind::Block);
+                if let Some(init) = init {
+                    self.resolve_statement(init);
+                }
+                if let Some(condition) = condition {
+                    self.resolve_expression(condition);
+                }
+                if let Some(step) = step {
+                    self.resolve_statement(step);
+                }
+                self.resolve_loop_body(body);
+                self.symbols.pop_scope();
+            }
+            StmtKind::Return(value) => {
+                if let Some(value) = value {
+                    self.resolve_expression(value);
+                }
+            }
+            StmtKind::Break | StmtKind::Continue if self.loop_depth == 0 => {
+                let keyword = if stmt.kind == StmtKind::Break {
+                    "break"
+                } else {
+                    "continue"
+                };
+                self.diagnostics.push(Diagnostic::error(
+                    codes::BREAK_OUTSIDE_LOOP,
+                    format!("`{}` outside of a loop", keyword),
+                    stmt.span,
+                ));
+            }
+            StmtKind::Break | StmtKind::Continue => {}
+            StmtKind::Block(stmts) => {
+                self.symbols.push_scope(ScopeKind::Block);
+                for stmt in stmts {
+                    self.resolve_statement(stmt);
+                }
+                self.symbols.pop_scope();
+            }
+            StmtKind::Expr(expr) => self.resolve_expression(expr),
+        }
+    }
+
+    fn resolve_loop_body(&mut self, body: &Stmt) {
+        self.loop_depth += 1;
+        self.resolve_statement(body);
+        self.loop_depth -= 1;
+    }
+
+    // The initializer is resolved first: `int x = x;` refers to an outer `x`.
+    fn resolve_var_decl(&mut self, decl: &VarDecl) {
+        if let Some(init) = &decl.init {
+            self.resolve_expression(init);
+        }
+        self.declare(decl.name, decl.span, SymbolKind::Local);
+    }
+
+    fn resolve_expression(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Binary { left, right, .. } => {
+                self.resolve_expression(left);
+                self.resolve_expression(right);
+            }
+            ExprKind::Unary { operand, .. } => self.resolve_expression(operand),
+            ExprKind::Assign { target, value, .. } => {
+                self.resolve_expression(target);
+                self.resolve_expression(value);
+            }
+            ExprKind::Call { callee, args } => {
+                self.resolve_expression(callee);
+                for arg in args {
+                    self.resolve_expression(arg);
+                }
+            }
+            ExprKind::Literal(_) => {}
+            ExprKind::Var(name) => match self.symbols.lookup(*name) {
+                None => self.diagnostics.push(Diagnostic::error(
+                    codes::UNDECLARED_IDENTIFIER,
+                    format!("cannot find `{}` in this scope", name),
+                    expr.span,
+                )),
+                Some(entry) if entry.value == SymbolKind::Prototype => {
+                    self.prototype_uses.push((*name, expr.span));
+                }
+                Some(entry)
+                    if entry.value == SymbolKind::Global
+                        && self.in_initializer
+                        && self.uninitialized_globals.contains(name) =>
+                {
+                    let diagnostic = Diagnostic::error(
+                        codes::USE_BEFORE_DEFINITION,
+                        format!("`{}` is used before its definition", name),
+                        expr.span,
+                    )
+                    .with_label(entry.span, format!("`{}` is defined here", name))
+                    .with_note("globals are initialized in the order they are defined");
+                    self.diagnostics.push(diagnostic);
+                }
+                Some(_) => {}
+            },
+            ExprKind::Index { array, index } => {
+                self.resolve_expression(array);
+                self.resolve_expression(index);
+            }
+            ExprKind::Convert { operand, .. } => self.resolve_expression(operand),
+        }
+    }
+
+    // Every function is declared before any body is resolved, so a function
+    // still known only by its prototype then is never defined.
+    fn report_undefined_functions(&mut self) {
+        let mut reported = HashSet::new();
+        for (name, span) in std::mem::take(&mut self.prototype_uses) {
+            if !reported.insert(name) {
+                continue;
+            }
+            let mut diagnostic = Diagnostic::error(
+                codes::UNDEFINED_FUNCTION,
+                format!("function `{}` is declared but never defined", name),
+                span,
+            );
+            if let Some(entry) = self.symbols.lookup(name) {
+                diagnostic = diagnostic.with_label(entry.span, "declared here");
+            }
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn declare(&mut self, name: Symbol, span: Span, kind: SymbolKind) {
+        let Err(previous) = self.symbols.declare(name, span, kind) else {
+            return;
+        };
+        let entry = self.symbols.lookup_mut(name).expect("declared before");
+        match (entry.value, kind) {
+            (SymbolKind::Native, _) => {
+                entry.value = kind;
+                entry.span = span;
+                return;
+            }
+            (SymbolKind::Prototype | SymbolKind::Function, SymbolKind::Prototype) => return,
+            (SymbolKind::Prototype, SymbolKind::Function) => {
+                entry.value = SymbolKind::Function;
+                return;
+            }
+            _ => {}
+        }
+        let previous_kind = self.symbols.lookup(name).map(|entry| entry.value);
+        let mut diagnostic = Diagnostic::error(
+            codes::REDECLARATION,
+            format!("`{}` is already declared in this scope", name),
+            span,
+        )
+        .with_label(previous, format!("`{}` first declared here", name));
+        if kind == SymbolKind::Local && previous_kind == Some(SymbolKind::Parameter) {
+            diagnostic = diagnostic.with_note(
+                "parameters share a scope with the outermost block of the function body",
+            );
+        } else if let Some(previous_kind) = previous_kind.filter(|previous| *previous != kind) {
+            diagnostic = diagnostic.with_note(format!(
+                "it was declared as a {} and again as a {}",
+                previous_kind.describe(),
+                kind.describe()
+            ));
+        }
+        self.diagnostics.push(diagnostic);
+    }
+}
+
+fn item_declaration(item: &Item) -> (Symbol, Span, SymbolKind) {
+    match item {
+        Item::Function(function) => (function.name, function.span, SymbolKind::Function),
+        Item::Prototype(prototype) => (prototype.name, prototype.span, SymbolKind::Prototype),
+        Item::Global(global) => (global.name, global.span, SymbolKind::Global),
+    }
+}
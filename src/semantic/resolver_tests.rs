@@ -0,0 +1,231 @@
+use super::*;
+use crate::diagnostic::{codes, Diagnostic};
+use crate::parser::Parser;
+use crate::span::Position;
+use crate::tokenizer::Tokenizer;
+use crate::vm::NativeRegistry;
+
+fn resolve(input: &str) -> Vec<Diagnostic> {
+    let (program, diagnostics) = Parser::new(Tokenizer::new(input)).parse();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    Resolver::new().resolve(&program)
+}
+
+fn messages(diagnostics: &[Diagnostic]) -> Vec<&str> {
+    diagnostics.iter().map(|d| d.message.as_str()).collect()
+}
+
+#[test]
+fn test_resolves_locals_params_and_globals() {
+    let input = "\
+int g = 1;
+int main() { int a = g; { int b = a + helper(a); } return a; }
+int helper(int n) { for (int i = 0; i < n; i++) n += i; return n; }";
+    assert_eq!(resolve(input), vec![]);
+}
+
+#[test]
+fn test_undeclared_identifiers() {
+    let input = "int main() {\n  { int inner = 1; }\n  return inner + missing(2);\n}";
+    let diagnostics = resolve(input);
+    assert_eq!(
+        messages(&diagnostics),
+        vec![
+            "cannot find `inner` in this scope",
+            "cannot find `missing` in this scope"
+        ]
+    );
+    assert!(diagnostics
+        .iter()
+        .all(|d| d.code == codes::UNDECLARED_IDENTIFIER));
+    assert_eq!(diagnostics[0].span.start.line, 3);
+    assert_eq!(diagnostics[0].span.start.column, 10);
+    assert_eq!(diagnostics[0].span.len(), 5);
+}
+
+#[test]
+fn test_for_variable_is_scoped_to_the_loop() {
+    let diagnostics = resolve("int main() { for (int i = 0; i < 3; i++) {} return i; }");
+    assert_eq!(
+        messages(&diagnostics),
+        vec!["cannot find `i` in this scope"]
+    );
+}
+
+#[test]
+fn test_a_local_is_not_visible_in_its_own_initializer() {
+    assert_eq!(
+        messages(&resolve("int main() { int x = x; return 0; }")),
+        vec!["cannot find `x` in this scope"]
+    );
+    // an outer `x` is, and is shadowed from then on
+    assert_eq!(
+        resolve("int x = 1;\nint main() { int x = x + 1; { int x = 3; } return x; }"),
+        vec![]
+    );
+}
+
+#[test]
+fn test_redeclarations() {
+    let input = "\
+int twice;
+int twice(int n, int n) { return n; }
+int main() {
+  int a = 1;
+  { int a = 2; }
+  int a = 3;
+  return a;
+}";
+    let diagnostics = resolve(input);
+    assert_eq!(
+        messages(&diagnostics),
+        vec![
+            "`twice` is already declared in this scope",
+            "`n` is already declared in this scope",
+            "`a` is already declared in this scope",
+        ]
+    );
+    assert!(diagnostics.iter().all(|d| d.code == codes::REDECLARATION));
+    assert_eq!(diagnostics[0].labels[0].span.start.line, 1);
+    assert_eq!(
+        diagnostics[0].notes,
+        vec!["it was declared as a global and again as a function"]
+    );
+    assert_eq!(diagnostics[2].span.start.line, 6);
+    assert_eq!(diagnostics[2].labels[0].span.start.line, 4);
+}
+
+#[test]
+fn test_break_and_continue_outside_a_loop() {
+    let input = "\
+int main() {
+  while (1) { if (1) break; { continue; } }
+  do { for (;;) break; } while (0);
+  break;
+  if (1) continue;
+  return 0;
+}";
+    let diagnostics = resolve(input);
+    assert_eq!(
+        messages(&diagnostics),
+        vec!["`break` outside of a loop", "`continue` outside of a loop"]
+    );
+    assert!(diagnostics
+        .iter()
+        .all(|d| d.code == codes::BREAK_OUTSIDE_LOOP));
+    assert_eq!(diagnostics[0].span.start.line, 4);
+    assert_eq!(diagnostics[0].span.len(), 6);
+}
+
+#[test]
+fn test_prototypes() {
+    let input = "\
+int is_odd(int n);
+int is_even(int n) { if (n == 0) return 1; return is_odd(n - 1); }
+int is_odd(int n);
+int is_odd(int n) { if (n == 0) return 0; return is_even(n - 1); }";
+    assert_eq!(resolve(input), vec![]);
+
+    let diagnostics = resolve("int f();\nint f() { return 1; }\nint f() { return 2; }");
+    assert_eq!(
+        messages(&diagnostics),
+        vec!["`f` is already declared in this scope"]
+    );
+    assert_eq!(diagnostics[0].span.start.line, 3);
+    assert_eq!(diagnostics[0].labels[0].span.start.line, 1);
+}
+
+#[test]
+fn test_calling_a_function_that_is_never_defined() {
+    let input = "int missing(int n);\nint main() { return missing(1) + missing(2); }";
+    let diagnostics = resolve(input);
+    assert_eq!(
+        messages(&diagnostics),
+        vec!["function `missing` is declared but never defined"]
+    );
+    assert_eq!(diagnostics[0].code, codes::UNDEFINED_FUNCTION);
+    assert_eq!(diagnostics[0].span.start, Position::new(40, 2, 21));
+    assert_eq!(diagnostics[0].labels[0].span.start.line, 1);
+
+    // declaring it without calling it is fine
+    assert_eq!(
+        resolve("int unused(int n);\nint main() { return 0; }"),
+        vec![]
+    );
+}
+
+#[test]
+fn test_natives_are_declared_and_may_be_redefined() {
+    let natives = NativeRegistry::with_stdlib();
+    let resolve_with_natives = |input: &str| {
+        let (program, _) = Parser::new(Tokenizer::new(input)).parse();
+        Resolver::new().with_natives(&natives).resolve(&program)
+    };
+    assert_eq!(
+        resolve_with_natives("int main() { puts(\"hi\"); return read_int(); }"),
+        vec![]
+    );
+    assert_eq!(
+        resolve_with_natives("int puts(char *s) { return 0; }\nint exit = 3;"),
+        vec![]
+    );
+    assert_eq!(
+        messages(&resolve("int main() { puts(\"hi\"); return 0; }")),
+        vec!["cannot find `puts` in this scope"]
+    );
+}
+
+#[test]
+fn test_a_local_cannot_redeclare_a_parameter() {
+    let diagnostics =
+        resolve("int f(int n) { int n = 2; return n; }\nint g(int n) { { int n = 2; } return n; }");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].notes,
+        vec!["parameters share a scope with the outermost block of the function body"]
+    );
+}
+
+#[test]
+fn test_global_initializers_only_read_earlier_globals() {
+    let input = "\
+int a = b + 1;
+int b = 2;
+int c = b + twice(b) + c;
+int twice(int n) { return n * 2 + d; }
+int d;";
+    let diagnostics = resolve(input);
+    assert_eq!(
+        messages(&diagnostics),
+        vec![
+            "`b` is used before its definition",
+            "`c` is used before its definition"
+        ]
+    );
+    assert!(diagnostics
+        .iter()
+        .all(|d| d.code == codes::USE_BEFORE_DEFINITION));
+    assert_eq!(diagnostics[0].span.start, Position::new(8, 1, 9));
+    assert_eq!(diagnostics[0].labels[0].span.start.line, 2);
+}
+
+#[test]
+fn test_later_declarations_may_redefine_earlier_ones() {
+    let mut resolver = Resolver::new();
+    let declare = |resolver: &mut Resolver, input: &str| {
+        let (program, _) = Parser::new(Tokenizer::new(input)).parse();
+        resolver.resolve_declarations(&program)
+    };
+    assert_eq!(declare(&mut resolver, "int x = 1;"), vec![]);
+    assert_eq!(declare(&mut resolver, "int x = x + 1;"), vec![]);
+    assert_eq!(
+        messages(&declare(&mut resolver, "int y; int y;")),
+        vec!["`y` is already declared in this scope"]
+    );
+
+    let (stmts, _) = Parser::new(Tokenizer::new("x = y; { int z; } z;")).parse_statements();
+    assert_eq!(
+        messages(&resolver.resolve_statements(&stmts)),
+        vec!["cannot find `z` in this scope"]
+    );
+}
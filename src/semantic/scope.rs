@@ -0,0 +1,169 @@
+use crate::span::Span;
+use crate::symbol::Symbol;
+use std::collections::HashMap;
+
+/// The region of the program a scope covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    /// File scope, holding globals and functions.
+    Global,
+    /// A function's parameters together with the outermost block of its
+    /// body, so a local there cannot redeclare a parameter.
+    Function,
+    /// A nested block, or the header of a `for` loop.
+    Block,
+}
+
+/// A declared name and whatever a stage records about it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry<T> {
+    pub name: Symbol,
+    pub span: Span,
+    pub value: T,
+}
+
+struct Scope<T> {
+    kind: ScopeKind,
+    entries: HashMap<Symbol, Entry<T>>,
+}
+
+/// The names visible at a point in the program. Scopes nest: a name declared
+/// in an inner scope shadows the same name further out, and is forgotten when
+/// its scope is popped. Declaring a name twice in one scope is an error.
+///
+/// Each stage decides what to store per name, e.g. its kind or its type.
+pub struct SymbolTable<T> {
+    scopes: Vec<Scope<T>>,
+}
+
+impl<T> Default for SymbolTable<T> {
+    fn default() -> Self {
+        SymbolTable::new()
+    }
+}
+
+impl<T> SymbolTable<T> {
+    /// Creates a table holding only the global scope.
+    pub fn new() -> SymbolTable<T> {
+        SymbolTable {
+            scopes: vec![Scope {
+                kind: ScopeKind::Global,
+                entries: HashMap::new(),
+            }],
+        }
+    }
+
+    pub fn push_scope(&mut self, kind: ScopeKind) {
+        self.scopes.push(Scope {
+            kind,
+            entries: HashMap::new(),
+        });
+    }
+
+    /// Pops the innermost scope. The global scope is never popped.
+    pub fn pop_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    pub fn scope_kind(&self) -> ScopeKind {
+        self.current().kind
+    }
+
+    /// Declares `name` in the innermost scope. If that scope already has it,
+    /// nothing changes and the span of the earlier declaration is returned.
+    pub fn declare(&mut self, name: Symbol, span: Span, value: T) -> Result<(), Span> {
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("the global scope is never popped");
+        if let Some(previous) = scope.entries.get(&name) {
+            return Err(previous.span);
+        }
+        scope.entries.insert(name, Entry { name, span, value });
+        Ok(())
+    }
+
+    /// Removes `name` from the innermost scope, so it can be declared anew.
+    pub fn forget(&mut self, name: Symbol) -> Option<Entry<T>> {
+        self.scopes.last_mut()?.entries.remove(&name)
+    }
+
+    /// Finds the innermost declaration of `name`.
+    pub fn lookup(&self, name: Symbol) -> Option<&Entry<T>> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.entries.get(&name))
+    }
+
+    pub fn lookup_mut(&mut self, name: Symbol) -> Option<&mut Entry<T>> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.entries.get_mut(&name))
+    }
+
+    /// Whether `name` resolves to a global rather than a local.
+    pub fn is_global(&self, name: Symbol) -> bool {
+        let innermost = self
+            .scopes
+            .iter()
+            .rposition(|scope| scope.entries.contains_key(&name));
+        innermost == Some(0)
+    }
+
+    fn current(&self) -> &Scope<T> {
+        self.scopes
+            .last()
+            .expect("the global scope is never popped")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ScopeKind, SymbolTable};
+    use crate::span::{Position, Span};
+    use crate::symbol::Symbol;
+
+    fn span(offset: usize) -> Span {
+        let position = Position::new(offset, 1, offset + 1);
+        Span::new(position, position)
+    }
+
+    #[test]
+    fn inner_scopes_shadow_and_are_forgotten() {
+        let x = Symbol::intern("x");
+        let mut table = SymbolTable::new();
+        table.declare(x, span(0), "global").unwrap();
+        table.push_scope(ScopeKind::Function);
+        table.push_scope(ScopeKind::Block);
+        table.declare(x, span(5), "local").unwrap();
+        assert_eq!(table.lookup(x).unwrap().value, "local");
+        assert!(!table.is_global(x));
+        assert_eq!(table.scope_kind(), ScopeKind::Block);
+
+        table.pop_scope();
+        assert_eq!(table.lookup(x).unwrap().value, "global");
+        assert!(table.is_global(x));
+        table.pop_scope();
+        table.pop_scope();
+        assert_eq!(table.scope_kind(), ScopeKind::Global);
+        assert!(table.lookup(Symbol::intern("y")).is_none());
+    }
+
+    #[test]
+    fn redeclaring_in_one_scope_returns_the_first() {
+        let x = Symbol::intern("x");
+        let mut table = SymbolTable::new();
+        table.declare(x, span(0), 1).unwrap();
+        assert_eq!(table.declare(x, span(9), 2), Err(span(0)));
+        assert_eq!(table.lookup(x).unwrap().value, 1);
+
+        table.forget(x);
+        table.declare(x, span(9), 2).unwrap();
+        table.lookup_mut(x).unwrap().value += 1;
+        assert_eq!(table.lookup(x).unwrap().value, 3);
+    }
+}
@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+mod span;
+
+pub use span::{Position, Span};
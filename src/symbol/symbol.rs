@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::{LazyLock, Mutex, PoisonError};
+
+/// An interned identifier. Equal names intern to the same symbol, so symbols
+/// compare and hash as integers.
+///
+/// The interner is global to the process and never frees its strings, which
+/// lets `as_str` hand out `&'static str`. This is deliberate, and the leak is
+/// unbounded: every distinct identifier ever interned stays in memory, so a
+/// host compiling many unrelated scripts grows by the size of their names.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+#[derive(Default)]
+struct Interner {
+    symbols: HashMap<&'static str, Symbol>,
+    names: Vec<&'static str>,
+}
+
+static INTERNER: LazyLock<Mutex<Interner>> = LazyLock::new(Mutex::default);
+
+impl Symbol {
+    pub fn intern(name: &str) -> Symbol {
+        let mut interner = INTERNER.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(&symbol) = interner.symbols.get(name) {
+            return symbol;
+        }
+        let name: &'static str = Box::leak(name.into());
+        let symbol = Symbol(interner.names.len() as u32);
+        interner.names.push(name);
+        interner.symbols.insert(name, symbol);
+        symbol
+    }
+
+    pub fn as_str(self) -> &'static str {
+        let interner = INTERNER.lock().unwrap_or_else(PoisonError::into_inner);
+        interner.names[self.0 as usize]
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Symbol;
+
+    #[test]
+    fn equal_names_intern_to_one_symbol() {
+        let a = Symbol::intern("counter");
+        let b = Symbol::intern(&String::from("counter"));
+        assert_eq!(a, b);
+        assert_ne!(a, Symbol::intern("Counter"));
+        assert_eq!(a.as_str(), "counter");
+        assert_eq!(a, "counter");
+        assert_eq!(a.to_string(), "counter");
+        assert_eq!(format!("{:?}", a), "\"counter\"");
+    }
+
+    #[test]
+    fn symbols_are_shared_across_threads() {
+        let here = Symbol::intern("shared_name");
+        let there = std::thread::spawn(|| Symbol::intern("shared_name"))
+            .join()
+            .unwrap();
+        assert_eq!(here, there);
+    }
+}
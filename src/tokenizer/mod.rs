@@ -1,5 +1,9 @@
+mod number;
+mod token;
+#[allow(clippy::module_inception)]
 mod tokenizer;
 #[cfg(test)]
 mod tokenizer_tests;
 
-pub use tokenizer::{Token, TokenType, Tokenizer};
+pub use token::{Keyword, Operator, Punctuation, Token, TokenType};
+pub use tokenizer::Tokenizer;
@@ -0,0 +1,173 @@
+//! Validation and evaluation of numeric literals.
+//!
+//! The tokenizer first takes the longest run of characters that could belong
+//! to a number (digits, letters, `_`, `.` and a sign after an exponent), so
+//! that `10abc` is reported as one bad literal rather than `10` followed by
+//! `abc`. `parse_number` then checks that run against the grammar:
+//!
+//! ```text
+//! integer = (decimal | "0" octal | "0x" hex | "0b" binary) int-suffix?
+//! float   = digits "." digits? exponent? "f"? | digits exponent "f"? | "." digits exponent? "f"?
+//! exponent = ("e" | "E") ("+" | "-")? digits
+//! int-suffix = "u" | "l" | "ul" | "lu" | "ll" | "ull" | "llu"   (any case)
+//! ```
+//!
+//! `_` may separate digits but cannot start or end a digit group. Integers are
+//! 64 bits wide: unsuffixed literals must fit in `i64`, while `u` literals may
+//! use the full `u64` range and wrap into the same bits.
+
+use crate::diagnostic::{codes, Diagnostic};
+use crate::span::Span;
+
+use super::token::TokenType;
+
+pub fn parse_number(lexeme: &str, span: Span) -> Result<TokenType<'static>, Diagnostic> {
+    let invalid = |message: String| Diagnostic::error(codes::INVALID_NUMBER, message, span);
+
+    let lower = lexeme.to_ascii_lowercase();
+    let (radix, prefix) = if lower.starts_with("0x") {
+        (16, &lexeme[..2])
+    } else if lower.starts_with("0b") {
+        (2, &lexeme[..2])
+    } else {
+        (10, "")
+    };
+
+    if radix != 10 {
+        let rest = &lexeme[2..];
+        let digits_len = rest
+            .find(|c: char| !c.is_digit(radix.max(10)) && c != '_')
+            .unwrap_or(rest.len());
+        let (digits, suffix) = rest.split_at(digits_len);
+        if !digits.chars().any(|c| c != '_') {
+            return Err(invalid(format!(
+                "missing digits after the integer base prefix `{}`",
+                prefix
+            )));
+        }
+        // only reachable for binary, every decimal digit is also a hex digit
+        if let Some(bad) = digits.chars().find(|c| !c.is_digit(radix) && *c != '_') {
+            return Err(invalid(format!(
+                "invalid digit `{}` in binary literal",
+                bad
+            )));
+        }
+        check_separators(digits, &invalid)?;
+        return parse_integer(lexeme, digits, radix, suffix, span);
+    }
+
+    let (int_part, rest) = split_digits(lexeme);
+    let mut is_float = false;
+    let mut rest = rest;
+    let mut frac_part = "";
+    if let Some(after_dot) = rest.strip_prefix('.') {
+        is_float = true;
+        (frac_part, rest) = split_digits(after_dot);
+    }
+    let mut exponent = None;
+    if let Some(after_e) = rest.strip_prefix(['e', 'E']) {
+        is_float = true;
+        let after_sign = after_e.strip_prefix(['+', '-']).unwrap_or(after_e);
+        let (digits, after_digits) = split_digits(after_sign);
+        if !digits.chars().any(|c| c != '_') {
+            return Err(
+                invalid("expected at least one digit in the exponent".to_string())
+                    .with_note("an exponent looks like `1e10` or `2.5e-3`"),
+            );
+        }
+        exponent = Some(digits);
+        rest = after_digits;
+    }
+    let suffix = rest;
+
+    for part in [int_part, frac_part].into_iter().chain(exponent) {
+        check_separators(part, &invalid)?;
+    }
+
+    if is_float {
+        if !suffix.is_empty() && !suffix.eq_ignore_ascii_case("f") {
+            return Err(invalid(format!(
+                "invalid suffix `{}` on float literal",
+                suffix
+            )));
+        }
+        let text: String = lexeme[..lexeme.len() - suffix.len()]
+            .chars()
+            .filter(|c| *c != '_')
+            .collect();
+        let value: f64 = text.parse().expect("validated float literal");
+        if value.is_infinite() {
+            return Err(invalid(format!(
+                "float literal `{}` is out of range",
+                lexeme
+            )));
+        }
+        return Ok(TokenType::FloatLiteral(value));
+    }
+
+    // a leading zero makes the literal octal, as in C
+    if int_part.len() > 1 && int_part.starts_with('0') {
+        if let Some(bad) = int_part.chars().find(|c| matches!(c, '8' | '9')) {
+            return Err(invalid(format!("invalid digit `{}` in octal literal", bad)));
+        }
+        return parse_integer(lexeme, &int_part[1..], 8, suffix, span);
+    }
+    parse_integer(lexeme, int_part, 10, suffix, span)
+}
+
+// Splits off the leading run of decimal digits and separators.
+fn split_digits(text: &str) -> (&str, &str) {
+    let len = text
+        .find(|c: char| !c.is_ascii_digit() && c != '_')
+        .unwrap_or(text.len());
+    text.split_at(len)
+}
+
+fn check_separators(
+    digits: &str,
+    invalid: &impl Fn(String) -> Diagnostic,
+) -> Result<(), Diagnostic> {
+    if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        return Err(invalid(
+            "digit separator `_` must be between digits".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn parse_integer(
+    lexeme: &str,
+    digits: &str,
+    radix: u32,
+    suffix: &str,
+    span: Span,
+) -> Result<TokenType<'static>, Diagnostic> {
+    let unsigned = match suffix.to_ascii_lowercase().as_str() {
+        "" | "l" | "ll" => false,
+        "u" | "ul" | "lu" | "ull" | "llu" => true,
+        _ => {
+            return Err(Diagnostic::error(
+                codes::INVALID_NUMBER,
+                format!("invalid suffix `{}` on integer literal", suffix),
+                span,
+            ))
+        }
+    };
+
+    let digits: String = digits.chars().filter(|c| *c != '_').collect();
+    let too_large = || {
+        Diagnostic::error(
+            codes::INTEGER_OVERFLOW,
+            format!("integer literal `{}` is too large", lexeme),
+            span,
+        )
+    };
+    let value = u64::from_str_radix(&digits, radix).map_err(|_| too_large())?;
+    if unsigned {
+        return Ok(TokenType::IntLiteral(value as i64));
+    }
+    match i64::try_from(value) {
+        Ok(value) => Ok(TokenType::IntLiteral(value)),
+        Err(_) => Err(too_large().with_note("use a `u` suffix for values up to 2^64 - 1")),
+    }
+}
@@ -0,0 +1,164 @@
+use crate::span::Span;
+use crate::symbol::Symbol;
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keyword {
+    Int,
+    Char,
+    Bool,
+    Float,
+    Void,
+    Return,
+    If,
+    Else,
+    While,
+    Do,
+    For,
+    Break,
+    Continue,
+    True,
+    False,
+}
+
+impl Keyword {
+    pub fn from_lexeme(lexeme: &str) -> Option<Keyword> {
+        let keyword = match lexeme {
+            "int" => Keyword::Int,
+            "char" => Keyword::Char,
+            "bool" => Keyword::Bool,
+            "float" => Keyword::Float,
+            "void" => Keyword::Void,
+            "return" => Keyword::Return,
+            "if" => Keyword::If,
+            "else" => Keyword::Else,
+            "while" => Keyword::While,
+            "do" => Keyword::Do,
+            "for" => Keyword::For,
+            "break" => Keyword::Break,
+            "continue" => Keyword::Continue,
+            "true" => Keyword::True,
+            "false" => Keyword::False,
+            _ => return None,
+        };
+        Some(keyword)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Assign,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Not,
+    AndAnd,
+    OrOr,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    ShiftLeft,
+    ShiftRight,
+    PlusAssign,
+    MinusAssign,
+    Increment,
+    Decrement,
+    Dot,
+    Arrow,
+    Question,
+    Colon,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Punctuation {
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Semicolon,
+    Comma,
+}
+
+impl Punctuation {
+    pub fn from_char(c: char) -> Option<Punctuation> {
+        let punctuation = match c {
+            '(' => Punctuation::LeftParen,
+            ')' => Punctuation::RightParen,
+            '{' => Punctuation::LeftBrace,
+            '}' => Punctuation::RightBrace,
+            '[' => Punctuation::LeftBracket,
+            ']' => Punctuation::RightBracket,
+            ';' => Punctuation::Semicolon,
+            ',' => Punctuation::Comma,
+            _ => return None,
+        };
+        Some(punctuation)
+    }
+
+    pub fn lexeme(self) -> &'static str {
+        match self {
+            Punctuation::LeftParen => "(",
+            Punctuation::RightParen => ")",
+            Punctuation::LeftBrace => "{",
+            Punctuation::RightBrace => "}",
+            Punctuation::LeftBracket => "[",
+            Punctuation::RightBracket => "]",
+            Punctuation::Semicolon => ";",
+            Punctuation::Comma => ",",
+        }
+    }
+
+    pub fn closing_delimiter(self) -> Option<Punctuation> {
+        match self {
+            Punctuation::LeftParen => Some(Punctuation::RightParen),
+            Punctuation::LeftBrace => Some(Punctuation::RightBrace),
+            Punctuation::LeftBracket => Some(Punctuation::RightBracket),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenType<'src> {
+    Identifier(Symbol),
+    Keyword(Keyword),
+    IntLiteral(i64),
+    FloatLiteral(f64),
+    CharLiteral(char),
+    /// The decoded value, borrowed from the source unless it contains escapes.
+    StringLiteral(Cow<'src, str>),
+    Operator(Operator),
+    Punctuation(Punctuation),
+    /// A `//` or `/* */` comment, only produced when the tokenizer keeps trivia.
+    Comment,
+    Eof,
+}
+
+/// A token borrowing its lexeme from the source text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token<'src> {
+    pub token_type: TokenType<'src>,
+    pub lexeme: &'src str,
+    pub span: Span,
+}
+
+impl<'src> Token<'src> {
+    pub fn new(token_type: TokenType<'src>, lexeme: &'src str, span: Span) -> Token<'src> {
+        Token {
+            token_type,
+            lexeme,
+            span,
+        }
+    }
+}
@@ -1,28 +1,59 @@
-// TODO: consider replacing Value with Identifier, Number and Keyword.
-#[derive(Debug, PartialEq)]
-pub enum TokenType {
-    Value,
-    Punctuation,
-    EOF,
-}
+use crate::diagnostic::{codes, Diagnostic};
+use crate::span::{Position, Span};
+use crate::symbol::Symbol;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::iter::FusedIterator;
 
-#[derive(Debug, PartialEq)]
-pub struct Token {
-    pub token_type: TokenType,
-    pub lexeme: String,
-}
+use super::number::parse_number;
+use super::token::{Keyword, Operator, Punctuation, Token, TokenType};
 
-impl Token {
-    pub fn new(token_type: TokenType, lexeme: String) -> Token {
-        Token { token_type, lexeme }
-    }
-}
+// Ordered longest first so that matching is maximal munch.
+const OPERATORS: &[(&str, Operator)] = &[
+    ("==", Operator::Equal),
+    ("!=", Operator::NotEqual),
+    ("<=", Operator::LessEqual),
+    (">=", Operator::GreaterEqual),
+    ("&&", Operator::AndAnd),
+    ("||", Operator::OrOr),
+    ("<<", Operator::ShiftLeft),
+    (">>", Operator::ShiftRight),
+    ("+=", Operator::PlusAssign),
+    ("-=", Operator::MinusAssign),
+    ("++", Operator::Increment),
+    ("--", Operator::Decrement),
+    ("->", Operator::Arrow),
+    ("=", Operator::Assign),
+    ("<", Operator::Less),
+    (">", Operator::Greater),
+    ("+", Operator::Plus),
+    ("-", Operator::Minus),
+    ("*", Operator::Star),
+    ("/", Operator::Slash),
+    ("%", Operator::Percent),
+    ("!", Operator::Not),
+    ("&", Operator::Ampersand),
+    ("|", Operator::Pipe),
+    ("^", Operator::Caret),
+    ("~", Operator::Tilde),
+    (".", Operator::Dot),
+    ("?", Operator::Question),
+    (":", Operator::Colon),
+];
 
+/// Splits source text into tokens. It is an iterator that scans one token per
+/// call to `next`, yielding errors in source order alongside the tokens and
+/// ending with a single `Eof` token; `tokenize` collects the whole input.
 pub struct Tokenizer<'a> {
     input: &'a str,
     position: usize,
     column: usize,
     line: usize,
+    keep_comments: bool,
+    diagnostics: VecDeque<Diagnostic>,
+    // a scanned token held back until the errors found before it are yielded
+    pending: Option<Token<'a>>,
+    finished: bool,
 }
 
 impl<'a> Tokenizer<'a> {
@@ -31,10 +62,30 @@ impl<'a> Tokenizer<'a> {
             input,
             position: 0,
             column: 1,
-            line: 0,
+            line: 1,
+            keep_comments: false,
+            diagnostics: VecDeque::new(),
+            pending: None,
+            finished: false,
         }
     }
 
+    /// Emits comments as `TokenType::Comment` tokens instead of skipping them.
+    pub fn keep_comments(mut self) -> Tokenizer<'a> {
+        self.keep_comments = true;
+        self
+    }
+
+    /// Starts scanning at `start` instead of the beginning of the input, for
+    /// example at the first line an editor shows. `start` must not be inside a
+    /// token or comment. Spans stay relative to the whole input.
+    pub fn starting_at(mut self, start: Position) -> Tokenizer<'a> {
+        self.position = start.offset;
+        self.line = start.line;
+        self.column = start.column;
+        self
+    }
+
     fn is_at_end(&self) -> bool {
         self.position >= self.input.len()
     }
@@ -43,6 +94,12 @@ impl<'a> Tokenizer<'a> {
         self.input[self.position..].chars().next()
     }
 
+    fn peek_next(&self) -> Option<char> {
+        let mut chars = self.input[self.position..].chars();
+        chars.next();
+        chars.next()
+    }
+
     fn advance(&mut self) -> Option<char> {
         if self.is_at_end() {
             return None;
@@ -58,8 +115,18 @@ impl<'a> Tokenizer<'a> {
         Some(c)
     }
 
-    fn tokenize_value(&mut self) -> Token {
-        let start = self.position;
+    fn current_position(&self) -> Position {
+        Position::new(self.position, self.line, self.column)
+    }
+
+    fn make_token(&self, token_type: TokenType<'a>, start: Position) -> Token<'a> {
+        let lexeme = &self.input[start.offset..self.position];
+        let span = Span::new(start, self.current_position());
+        Token::new(token_type, lexeme, span)
+    }
+
+    fn tokenize_word(&mut self) -> Token<'a> {
+        let start = self.current_position();
         while let Some(c) = self.peek() {
             if !c.is_alphanumeric() && c != '_' {
                 break;
@@ -67,36 +134,355 @@ impl<'a> Tokenizer<'a> {
             self.advance();
         }
 
-        let lexeme = &self.input[start..self.position];
-        Token::new(TokenType::Value, lexeme.to_string())
+        let lexeme = &self.input[start.offset..self.position];
+        let token_type = match Keyword::from_lexeme(lexeme) {
+            Some(keyword) => TokenType::Keyword(keyword),
+            None => TokenType::Identifier(Symbol::intern(lexeme)),
+        };
+        self.make_token(token_type, start)
     }
 
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, String> {
-        let mut tokens: Vec<Token> = Vec::new();
-        while !self.is_at_end() {
-            if let Some(c) = self.peek() {
-                match c {
-                    ' ' | '\t' | '\n' | '\r' => {
-                        self.advance();
+    fn tokenize_number(&mut self) -> Token<'a> {
+        let start = self.current_position();
+        let is_hex = matches!(self.peek_next(), Some('x' | 'X')) && self.peek() == Some('0');
+        // take everything that could be part of a number so that `10abc` is
+        // reported whole; `parse_number` sorts out what is valid
+        let mut previous = None;
+        while let Some(c) = self.peek() {
+            let exponent_sign =
+                matches!(c, '+' | '-') && matches!(previous, Some('e' | 'E')) && !is_hex;
+            if !c.is_alphanumeric() && c != '_' && c != '.' && !exponent_sign {
+                break;
+            }
+            previous = self.advance();
+        }
+
+        let lexeme = &self.input[start.offset..self.position];
+        let span = Span::new(start, self.current_position());
+        // a malformed literal is still a number token, so parsing can continue
+        let token_type = parse_number(lexeme, span).unwrap_or_else(|diagnostic| {
+            self.diagnostics.push_back(diagnostic);
+            TokenType::IntLiteral(0)
+        });
+        self.make_token(token_type, start)
+    }
+
+    // Reads a string or char literal up to the closing `quote`, decoding
+    // escapes. Stops before a newline when the literal is unterminated.
+    fn read_quoted(&mut self, quote: char, what: &str) -> Cow<'a, str> {
+        let start = self.current_position();
+        self.advance();
+        let opening = Span::new(start, self.current_position());
+
+        // only allocated once an escape means the value differs from the source
+        let content_start = self.position;
+        let mut decoded: Option<String> = None;
+        let content_end = loop {
+            match self.peek() {
+                Some(c) if c == quote => {
+                    let end = self.position;
+                    self.advance();
+                    break end;
+                }
+                Some('\\') => {
+                    let mut value = decoded
+                        .take()
+                        .unwrap_or_else(|| self.input[content_start..self.position].to_string());
+                    if let Some(c) = self.read_escape() {
+                        value.push(c);
                     }
-                    '{' | '}' | ';' | '(' | ')' => {
-                        let t = Token::new(TokenType::Punctuation, c.to_string());
-                        tokens.push(t);
-                        self.advance();
+                    decoded = Some(value);
+                }
+                Some('\n') | None => {
+                    let end = self.current_position();
+                    self.diagnostics.push_back(
+                        Diagnostic::error(
+                            codes::UNTERMINATED_LITERAL,
+                            format!("unterminated {} literal", what),
+                            opening,
+                        )
+                        .with_label(Span::new(end, end), format!("expected `{}`", quote)),
+                    );
+                    break self.position;
+                }
+                Some(c) => {
+                    self.advance();
+                    if let Some(value) = &mut decoded {
+                        value.push(c);
                     }
-                    'a'..='z' | 'A'..='Z' | '_' | '0'..='9' => {
-                        tokens.push(self.tokenize_value());
+                }
+            }
+        };
+        match decoded {
+            Some(value) => Cow::Owned(value),
+            None => Cow::Borrowed(&self.input[content_start..content_end]),
+        }
+    }
+
+    // Decodes the escape sequence under the cursor. Invalid escapes are
+    // reported and skipped.
+    fn read_escape(&mut self) -> Option<char> {
+        let start = self.current_position();
+        self.advance();
+        let c = self.peek()?;
+        if c == '\n' {
+            // leave the newline for the unterminated literal check
+            self.push_invalid_escape(start, "unknown escape sequence `\\`");
+            return None;
+        }
+        self.advance();
+        let decoded = match c {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            '\'' => Some('\''),
+            'x' => {
+                let digits = self.take_hex_digits(2);
+                if digits.len() == 2 {
+                    u8::from_str_radix(&digits, 16).ok().map(char::from)
+                } else {
+                    self.push_invalid_escape(start, "`\\x` must be followed by two hex digits");
+                    return None;
+                }
+            }
+            'u' => return self.read_unicode_escape(start),
+            other => {
+                let message = format!("unknown escape sequence `\\{}`", other);
+                self.push_invalid_escape(start, &message);
+                return None;
+            }
+        };
+        decoded
+    }
+
+    // `\u{...}` with one to six hex digits naming a Unicode scalar value.
+    fn read_unicode_escape(&mut self, start: Position) -> Option<char> {
+        if self.peek() != Some('{') {
+            self.push_invalid_escape(start, "expected `{` after `\\u`");
+            return None;
+        }
+        self.advance();
+        let digits = self.take_hex_digits(6);
+        if self.peek() != Some('}') || digits.is_empty() {
+            self.push_invalid_escape(start, "`\\u{...}` must contain one to six hex digits");
+            return None;
+        }
+        self.advance();
+        let value = u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32);
+        if value.is_none() {
+            let message = format!("`{}` is not a valid Unicode scalar value", digits);
+            self.push_invalid_escape(start, &message);
+        }
+        value
+    }
+
+    fn take_hex_digits(&mut self, max: usize) -> String {
+        let mut digits = String::new();
+        while digits.len() < max {
+            match self.peek() {
+                Some(c) if c.is_ascii_hexdigit() => {
+                    self.advance();
+                    digits.push(c);
+                }
+                _ => break,
+            }
+        }
+        digits
+    }
+
+    fn push_invalid_escape(&mut self, start: Position, message: &str) {
+        self.diagnostics.push_back(Diagnostic::error(
+            codes::INVALID_ESCAPE,
+            message,
+            Span::new(start, self.current_position()),
+        ));
+    }
+
+    fn tokenize_string(&mut self) -> Token<'a> {
+        let start = self.current_position();
+        let value = self.read_quoted('"', "string");
+        self.make_token(TokenType::StringLiteral(value), start)
+    }
+
+    fn tokenize_char(&mut self) -> Token<'a> {
+        let start = self.current_position();
+        let errors = self.diagnostics.len();
+        let value = self.read_quoted('\'', "character");
+        let mut chars = value.chars();
+        let c = match (chars.next(), chars.next()) {
+            (Some(c), None) => c,
+            // an escape or termination problem was already reported
+            _ if self.diagnostics.len() > errors => '\0',
+            (None, _) => {
+                let span = Span::new(start, self.current_position());
+                self.diagnostics.push_back(Diagnostic::error(
+                    codes::INVALID_CHAR_LITERAL,
+                    "empty character literal",
+                    span,
+                ));
+                '\0'
+            }
+            (Some(c), Some(_)) => {
+                let span = Span::new(start, self.current_position());
+                self.diagnostics.push_back(
+                    Diagnostic::error(
+                        codes::INVALID_CHAR_LITERAL,
+                        "character literal may only contain one character",
+                        span,
+                    )
+                    .with_note("use double quotes for strings"),
+                );
+                c
+            }
+        };
+        self.make_token(TokenType::CharLiteral(c), start)
+    }
+
+    fn tokenize_line_comment(&mut self) -> Token<'a> {
+        let start = self.current_position();
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                break;
+            }
+            self.advance();
+        }
+        self.make_token(TokenType::Comment, start)
+    }
+
+    fn tokenize_block_comment(&mut self) -> Token<'a> {
+        let start = self.current_position();
+        self.advance();
+        self.advance();
+        let opening = Span::new(start, self.current_position());
+        loop {
+            match self.peek() {
+                Some('*') if self.peek_next() == Some('/') => {
+                    self.advance();
+                    self.advance();
+                    break;
+                }
+                Some(_) => {
+                    self.advance();
+                }
+                None => {
+                    let end = self.current_position();
+                    self.diagnostics.push_back(
+                        Diagnostic::error(
+                            codes::UNTERMINATED_COMMENT,
+                            "unterminated block comment",
+                            opening,
+                        )
+                        .with_label(Span::new(end, end), "reached end of file")
+                        .with_note("block comments end with `*/` and do not nest"),
+                    );
+                    break;
+                }
+            }
+        }
+        self.make_token(TokenType::Comment, start)
+    }
+
+    fn tokenize_operator(&mut self) -> Option<Token<'a>> {
+        let start = self.current_position();
+        let rest = &self.input[self.position..];
+        let (lexeme, operator) = OPERATORS.iter().find(|(op, _)| rest.starts_with(op))?;
+        for _ in 0..lexeme.len() {
+            self.advance();
+        }
+        Some(self.make_token(TokenType::Operator(*operator), start))
+    }
+
+    /// Scans the rest of the input, returning every token (ending with `Eof`)
+    /// and every error.
+    pub fn tokenize(&mut self) -> (Vec<Token<'a>>, Vec<Diagnostic>) {
+        let mut tokens = Vec::new();
+        let mut diagnostics = Vec::new();
+        for item in self {
+            match item {
+                Ok(token) => tokens.push(token),
+                Err(diagnostic) => diagnostics.push(diagnostic),
+            }
+        }
+        (tokens, diagnostics)
+    }
+
+    // Scans the next token, skipping whitespace and, unless they are kept,
+    // comments. Characters that cannot start a token are reported and skipped.
+    fn scan_token(&mut self) -> Token<'a> {
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\n' | '\r' => {
+                    self.advance();
+                }
+                'a'..='z' | 'A'..='Z' | '_' => return self.tokenize_word(),
+                '0'..='9' => return self.tokenize_number(),
+                '"' => return self.tokenize_string(),
+                '\'' => return self.tokenize_char(),
+                '.' if self.peek_next().is_some_and(|c| c.is_ascii_digit()) => {
+                    return self.tokenize_number()
+                }
+                '/' if matches!(self.peek_next(), Some('/' | '*')) => {
+                    let comment = if self.peek_next() == Some('/') {
+                        self.tokenize_line_comment()
+                    } else {
+                        self.tokenize_block_comment()
+                    };
+                    if self.keep_comments {
+                        return comment;
+                    }
+                }
+                _ => {
+                    if let Some(punctuation) = Punctuation::from_char(c) {
+                        let start = self.current_position();
+                        self.advance();
+                        return self.make_token(TokenType::Punctuation(punctuation), start);
                     }
-                    _ => {
-                        return Err(format!(
-                            "Unexpected character {}, at line {} column {}",
-                            c, self.line, self.column
-                        ));
+                    if let Some(token) = self.tokenize_operator() {
+                        return token;
                     }
+                    let start = self.current_position();
+                    self.advance();
+                    self.diagnostics.push_back(Diagnostic::error(
+                        codes::UNEXPECTED_CHARACTER,
+                        format!("unexpected character `{}`", c),
+                        Span::new(start, self.current_position()),
+                    ));
                 }
             }
         }
-        tokens.push(Token::new(TokenType::EOF, String::new()));
-        Ok(tokens)
+        self.make_token(TokenType::Eof, self.current_position())
     }
 }
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<Token<'a>, Diagnostic>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(diagnostic) = self.diagnostics.pop_front() {
+            return Some(Err(diagnostic));
+        }
+        if let Some(token) = self.pending.take() {
+            return Some(Ok(token));
+        }
+        if self.finished {
+            return None;
+        }
+
+        let token = self.scan_token();
+        self.finished = token.token_type == TokenType::Eof;
+        match self.diagnostics.pop_front() {
+            Some(diagnostic) => {
+                self.pending = Some(token);
+                Some(Err(diagnostic))
+            }
+            None => Some(Ok(token)),
+        }
+    }
+}
+
+impl FusedIterator for Tokenizer<'_> {}
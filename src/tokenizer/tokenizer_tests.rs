@@ -1,48 +1,655 @@
 use super::*;
+use crate::diagnostic::{codes, Diagnostic};
+use crate::span::Position;
+use crate::symbol::Symbol;
+use std::borrow::Cow;
+
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let (tokens, diagnostics) = Tokenizer::new(input).tokenize();
+    assert!(
+        diagnostics.is_empty(),
+        "unexpected diagnostics: {:?}",
+        diagnostics
+    );
+    tokens
+}
+
+fn tokenize_err(input: &str) -> Diagnostic {
+    let (_, mut diagnostics) = Tokenizer::new(input).tokenize();
+    assert_eq!(
+        diagnostics.len(),
+        1,
+        "expected one diagnostic: {:?}",
+        diagnostics
+    );
+    diagnostics.remove(0)
+}
 
 #[test]
 fn test_tokenize() {
     let input = "value";
-    let tokens = Tokenizer::new(input).tokenize().unwrap();
+    let tokens = tokenize(input);
 
     assert_eq!(tokens.len(), 2);
-    assert_eq!(tokens[0].token_type, TokenType::Value);
-    assert_eq!(tokens[1].token_type, TokenType::EOF);
+    assert_eq!(
+        tokens[0].token_type,
+        TokenType::Identifier(Symbol::intern("value"))
+    );
+    assert_eq!(tokens[1].token_type, TokenType::Eof);
     assert_eq!(tokens[0].lexeme, "value");
 }
 
 #[test]
 fn test_tokenize_simple_program() {
     let input = "int main() { return 0; }";
-    let tokens = Tokenizer::new(input).tokenize().unwrap();
+    let tokens = tokenize(input);
 
     assert_eq!(tokens.len(), 10);
-    assert_eq!(tokens[0].token_type, TokenType::Value);
+    assert_eq!(tokens[0].token_type, TokenType::Keyword(Keyword::Int));
     assert_eq!(tokens[0].lexeme, "int");
 
-    assert_eq!(tokens[1].token_type, TokenType::Value);
+    assert_eq!(
+        tokens[1].token_type,
+        TokenType::Identifier(Symbol::intern("main"))
+    );
     assert_eq!(tokens[1].lexeme, "main");
 
-    assert_eq!(tokens[2].token_type, TokenType::Punctuation);
+    assert_eq!(
+        tokens[2].token_type,
+        TokenType::Punctuation(Punctuation::LeftParen)
+    );
     assert_eq!(tokens[2].lexeme, "(");
 
-    assert_eq!(tokens[3].token_type, TokenType::Punctuation);
+    assert_eq!(
+        tokens[3].token_type,
+        TokenType::Punctuation(Punctuation::RightParen)
+    );
     assert_eq!(tokens[3].lexeme, ")");
 
-    assert_eq!(tokens[4].token_type, TokenType::Punctuation);
+    assert_eq!(
+        tokens[4].token_type,
+        TokenType::Punctuation(Punctuation::LeftBrace)
+    );
     assert_eq!(tokens[4].lexeme, "{");
 
-    assert_eq!(tokens[5].token_type, TokenType::Value);
+    assert_eq!(tokens[5].token_type, TokenType::Keyword(Keyword::Return));
     assert_eq!(tokens[5].lexeme, "return");
 
-    assert_eq!(tokens[6].token_type, TokenType::Value);
+    assert_eq!(tokens[6].token_type, TokenType::IntLiteral(0));
     assert_eq!(tokens[6].lexeme, "0");
 
-    assert_eq!(tokens[7].token_type, TokenType::Punctuation);
+    assert_eq!(
+        tokens[7].token_type,
+        TokenType::Punctuation(Punctuation::Semicolon)
+    );
     assert_eq!(tokens[7].lexeme, ";");
 
-    assert_eq!(tokens[8].token_type, TokenType::Punctuation);
+    assert_eq!(
+        tokens[8].token_type,
+        TokenType::Punctuation(Punctuation::RightBrace)
+    );
     assert_eq!(tokens[8].lexeme, "}");
 
-    assert_eq!(tokens[9].token_type, TokenType::EOF);
+    assert_eq!(tokens[9].token_type, TokenType::Eof);
+}
+
+#[test]
+fn test_tokenize_operators() {
+    let input = "a = 10 + 33 * 7;";
+    let tokens = tokenize(input);
+
+    let expected = [
+        (TokenType::Identifier(Symbol::intern("a")), "a"),
+        (TokenType::Operator(Operator::Assign), "="),
+        (TokenType::IntLiteral(10), "10"),
+        (TokenType::Operator(Operator::Plus), "+"),
+        (TokenType::IntLiteral(33), "33"),
+        (TokenType::Operator(Operator::Star), "*"),
+        (TokenType::IntLiteral(7), "7"),
+        (TokenType::Punctuation(Punctuation::Semicolon), ";"),
+        (TokenType::Eof, ""),
+    ];
+    assert_eq!(tokens.len(), expected.len());
+    for (token, (token_type, lexeme)) in tokens.iter().zip(expected) {
+        assert_eq!(token.token_type, token_type);
+        assert_eq!(token.lexeme, lexeme);
+    }
+}
+
+#[test]
+fn test_tokenize_multi_character_operators() {
+    let input = "== != <= >= && || << >> += -= ++ -- ->";
+    let tokens = tokenize(input);
+
+    let operators: Vec<&TokenType> = tokens.iter().map(|t| &t.token_type).collect();
+    assert_eq!(
+        operators,
+        vec![
+            &TokenType::Operator(Operator::Equal),
+            &TokenType::Operator(Operator::NotEqual),
+            &TokenType::Operator(Operator::LessEqual),
+            &TokenType::Operator(Operator::GreaterEqual),
+            &TokenType::Operator(Operator::AndAnd),
+            &TokenType::Operator(Operator::OrOr),
+            &TokenType::Operator(Operator::ShiftLeft),
+            &TokenType::Operator(Operator::ShiftRight),
+            &TokenType::Operator(Operator::PlusAssign),
+            &TokenType::Operator(Operator::MinusAssign),
+            &TokenType::Operator(Operator::Increment),
+            &TokenType::Operator(Operator::Decrement),
+            &TokenType::Operator(Operator::Arrow),
+            &TokenType::Eof,
+        ]
+    );
+}
+
+#[test]
+fn test_tokenize_maximal_munch() {
+    // `a+++b` is `a ++ + b`, `x<<=y` is `x << = y` and `p->q` is a single arrow
+    let cases = [
+        ("a+++b", vec!["a", "++", "+", "b"]),
+        ("x<<=y", vec!["x", "<<", "=", "y"]),
+        ("a===b", vec!["a", "==", "=", "b"]),
+        ("p->q", vec!["p", "->", "q"]),
+        ("a--->b", vec!["a", "--", "->", "b"]),
+        ("!!a", vec!["!", "!", "a"]),
+    ];
+
+    for (input, expected) in cases {
+        let tokens = tokenize(input);
+        let lexemes: Vec<&str> = tokens
+            .iter()
+            .filter(|t| t.token_type != TokenType::Eof)
+            .map(|t| t.lexeme)
+            .collect();
+        assert_eq!(lexemes, expected, "input: {}", input);
+    }
+}
+
+#[test]
+fn test_tokenize_symbols() {
+    let input = "a[1], b.c ? d : e";
+    let tokens = tokenize(input);
+
+    let expected = [
+        (TokenType::Identifier(Symbol::intern("a")), "a"),
+        (TokenType::Punctuation(Punctuation::LeftBracket), "["),
+        (TokenType::IntLiteral(1), "1"),
+        (TokenType::Punctuation(Punctuation::RightBracket), "]"),
+        (TokenType::Punctuation(Punctuation::Comma), ","),
+        (TokenType::Identifier(Symbol::intern("b")), "b"),
+        (TokenType::Operator(Operator::Dot), "."),
+        (TokenType::Identifier(Symbol::intern("c")), "c"),
+        (TokenType::Operator(Operator::Question), "?"),
+        (TokenType::Identifier(Symbol::intern("d")), "d"),
+        (TokenType::Operator(Operator::Colon), ":"),
+        (TokenType::Identifier(Symbol::intern("e")), "e"),
+        (TokenType::Eof, ""),
+    ];
+    assert_eq!(tokens.len(), expected.len());
+    for (token, (token_type, lexeme)) in tokens.iter().zip(expected) {
+        assert_eq!(token.token_type, token_type);
+        assert_eq!(token.lexeme, lexeme);
+    }
+}
+
+#[test]
+fn test_tokenize_keywords() {
+    let input = "int char bool float void return if else while do for break continue true false";
+    let tokens = tokenize(input);
+
+    let keywords: Vec<Keyword> = tokens
+        .iter()
+        .filter_map(|t| match t.token_type {
+            TokenType::Keyword(keyword) => Some(keyword),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        keywords,
+        vec![
+            Keyword::Int,
+            Keyword::Char,
+            Keyword::Bool,
+            Keyword::Float,
+            Keyword::Void,
+            Keyword::Return,
+            Keyword::If,
+            Keyword::Else,
+            Keyword::While,
+            Keyword::Do,
+            Keyword::For,
+            Keyword::Break,
+            Keyword::Continue,
+            Keyword::True,
+            Keyword::False,
+        ]
+    );
+}
+
+#[test]
+fn test_tokenize_keyword_prefix_is_identifier() {
+    let input = "integer returns _if";
+    let tokens = tokenize(input);
+
+    assert!(tokens[..3]
+        .iter()
+        .all(|t| t.token_type == TokenType::Identifier(Symbol::intern(t.lexeme))));
+}
+
+#[test]
+fn test_tokenize_integer_literal() {
+    let tokens = tokenize("9223372036854775807");
+    assert_eq!(tokens[0].token_type, TokenType::IntLiteral(i64::MAX));
+
+    let err = tokenize_err("9223372036854775808");
+    assert_eq!(err.code, codes::INTEGER_OVERFLOW);
+    assert!(err.message.contains("too large"));
+    assert_eq!(err.span.len(), 19);
+}
+
+#[test]
+fn test_tokenize_unexpected_character() {
+    let input = "int a = 1 @ 2;";
+    let err = tokenize_err(input);
+    assert_eq!(err.code, codes::UNEXPECTED_CHARACTER);
+    assert_eq!(err.message, "unexpected character `@`");
+    assert_eq!(err.span.start, Position::new(10, 1, 11));
+    assert_eq!(err.span.end, Position::new(11, 1, 12));
+}
+
+#[test]
+fn test_tokenize_spans() {
+    let input = "int a;\n  return a;";
+    let tokens = tokenize(input);
+
+    let int = &tokens[0];
+    assert_eq!(int.span.start, Position::new(0, 1, 1));
+    assert_eq!(int.span.end, Position::new(3, 1, 4));
+
+    let ret = &tokens[3];
+    assert_eq!(ret.lexeme, "return");
+    assert_eq!(ret.span.start, Position::new(9, 2, 3));
+    assert_eq!(ret.span.end, Position::new(15, 2, 9));
+    assert_eq!(&input[ret.span.start.offset..ret.span.end.offset], "return");
+
+    let eof = tokens.last().unwrap();
+    assert_eq!(eof.token_type, TokenType::Eof);
+    assert_eq!(eof.span.start, Position::new(18, 2, 12));
+    assert!(eof.span.is_empty());
+}
+
+#[test]
+fn test_tokenize_multi_character_operator_span() {
+    let tokens = tokenize("a <<= b");
+
+    assert_eq!(tokens[1].span.start.column, 3);
+    assert_eq!(tokens[1].span.end.column, 5);
+    assert_eq!(tokens[2].span.start.column, 5);
+    assert_eq!(tokens[2].span.end.column, 6);
+}
+
+#[test]
+fn test_tokenize_error_location_is_one_based() {
+    let err = tokenize_err("int a;\n@");
+    assert_eq!(err.span.start.line, 2);
+    assert_eq!(err.span.start.column, 1);
+}
+
+#[test]
+fn test_tokenize_reports_all_errors() {
+    let input = "int @a = 1;\nint b = 99999999999999999999 $ 2;";
+    let (tokens, diagnostics) = Tokenizer::new(input).tokenize();
+
+    let codes: Vec<&str> = diagnostics.iter().map(|d| d.code).collect();
+    assert_eq!(
+        codes,
+        vec![
+            codes::UNEXPECTED_CHARACTER,
+            codes::INTEGER_OVERFLOW,
+            codes::UNEXPECTED_CHARACTER
+        ]
+    );
+    assert_eq!(diagnostics[0].span.start, Position::new(4, 1, 5));
+    assert_eq!(diagnostics[2].span.start.line, 2);
+
+    // the bad characters are skipped and everything else is still tokenized
+    let lexemes: Vec<&str> = tokens.iter().map(|t| t.lexeme).collect();
+    assert_eq!(
+        lexemes,
+        vec![
+            "int",
+            "a",
+            "=",
+            "1",
+            ";",
+            "int",
+            "b",
+            "=",
+            "99999999999999999999",
+            "2",
+            ";",
+            ""
+        ]
+    );
+}
+
+#[test]
+fn test_tokenize_comments_are_skipped() {
+    let input = "int a; // the answer\n/* block\n   comment */ a = 1 / 2; /**/";
+    let lexemes: Vec<&str> = tokenize(input).into_iter().map(|t| t.lexeme).collect();
+    assert_eq!(
+        lexemes,
+        vec!["int", "a", ";", "a", "=", "1", "/", "2", ";", ""]
+    );
+
+    // the line count keeps advancing through block comments
+    let tokens = tokenize("/*\n\n*/ x");
+    assert_eq!(tokens[0].span.start, Position::new(7, 3, 4));
+}
+
+#[test]
+fn test_tokenize_comment_markers_inside_comments() {
+    let tokens = tokenize("/* // not a line comment */ a // /* not a block\nb");
+    let lexemes: Vec<&str> = tokens.iter().map(|t| t.lexeme).collect();
+    assert_eq!(lexemes, vec!["a", "b", ""]);
+}
+
+#[test]
+fn test_tokenize_keeps_comments_as_trivia() {
+    let input = "a // one\n/* two */ b";
+    let (tokens, diagnostics) = Tokenizer::new(input).keep_comments().tokenize();
+    assert!(diagnostics.is_empty());
+
+    let tokens: Vec<(&TokenType, &str)> =
+        tokens.iter().map(|t| (&t.token_type, t.lexeme)).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            (&TokenType::Identifier(Symbol::intern("a")), "a"),
+            (&TokenType::Comment, "// one"),
+            (&TokenType::Comment, "/* two */"),
+            (&TokenType::Identifier(Symbol::intern("b")), "b"),
+            (&TokenType::Eof, ""),
+        ]
+    );
+}
+
+#[test]
+fn test_tokenize_unterminated_block_comment() {
+    let err = tokenize_err("int a;\n  /* never\nclosed");
+    assert_eq!(err.code, codes::UNTERMINATED_COMMENT);
+    assert_eq!(err.message, "unterminated block comment");
+    assert_eq!(err.span.start, Position::new(9, 2, 3));
+    assert_eq!(err.span.len(), 2);
+    assert_eq!(err.labels[0].message, "reached end of file");
+    assert_eq!(err.labels[0].span.start.line, 3);
+}
+
+fn token_types(input: &str) -> Vec<TokenType<'_>> {
+    tokenize(input)
+        .into_iter()
+        .map(|t| t.token_type)
+        .filter(|t| *t != TokenType::Eof)
+        .collect()
+}
+
+#[test]
+fn test_tokenize_integer_bases() {
+    let types = token_types("0x1F 0XfF 017 0b1010 0B1 0 00 1_000_000 0xFF_FF 0b1_0");
+    let values = [31, 255, 15, 10, 1, 0, 0, 1_000_000, 0xFFFF, 2];
+    assert_eq!(
+        types,
+        values.map(TokenType::IntLiteral).to_vec(),
+        "{:?}",
+        types
+    );
+}
+
+#[test]
+fn test_tokenize_integer_suffixes() {
+    let types = token_types("1u 2L 3ul 4LU 5ll 6ULL 0x10u 18446744073709551615u");
+    let values = [1, 2, 3, 4, 5, 6, 16, -1];
+    assert_eq!(types, values.map(TokenType::IntLiteral).to_vec());
+}
+
+#[test]
+fn test_tokenize_float_literals() {
+    let types = token_types("2.75 1e10 2.5E-3 .5 1. 1_0.2_5 6.02e+23f 7e0F");
+    let values = [2.75, 1e10, 2.5e-3, 0.5, 1.0, 10.25, 6.02e23, 7.0];
+    assert_eq!(types, values.map(TokenType::FloatLiteral).to_vec());
+}
+
+#[test]
+fn test_tokenize_number_followed_by_operator() {
+    let lexemes: Vec<&str> = tokenize("1+2 3.5*x 1e-2-1 0x1e+1 a[0].b")
+        .into_iter()
+        .map(|t| t.lexeme)
+        .collect();
+    assert_eq!(
+        lexemes,
+        vec![
+            "1", "+", "2", "3.5", "*", "x", "1e-2", "-", "1", "0x1e", "+", "1", "a", "[", "0", "]",
+            ".", "b", ""
+        ]
+    );
+}
+
+#[test]
+fn test_tokenize_malformed_numbers() {
+    let cases = [
+        ("0x", "missing digits after the integer base prefix `0x`"),
+        ("0b", "missing digits after the integer base prefix `0b`"),
+        ("0xg", "missing digits after the integer base prefix `0x`"),
+        ("1e", "expected at least one digit in the exponent"),
+        ("2.5e+", "expected at least one digit in the exponent"),
+        ("10abc", "invalid suffix `abc` on integer literal"),
+        ("1.5u", "invalid suffix `u` on float literal"),
+        ("1.2.3", "invalid suffix `.3` on float literal"),
+        ("0b102", "invalid digit `2` in binary literal"),
+        ("089", "invalid digit `8` in octal literal"),
+        ("1_", "digit separator `_` must be between digits"),
+        ("0x_1", "digit separator `_` must be between digits"),
+        ("1._5", "digit separator `_` must be between digits"),
+        ("1__0", "digit separator `_` must be between digits"),
+        ("0xF__F", "digit separator `_` must be between digits"),
+        ("2.5e1__0", "digit separator `_` must be between digits"),
+    ];
+    for (input, message) in cases {
+        let err = tokenize_err(input);
+        assert_eq!(err.code, codes::INVALID_NUMBER, "input: {}", input);
+        assert_eq!(err.message, message, "input: {}", input);
+        assert_eq!(err.span.len(), input.len(), "input: {}", input);
+    }
+}
+
+#[test]
+fn test_tokenize_malformed_number_is_one_token() {
+    let (tokens, diagnostics) = Tokenizer::new("x = 10abc;").tokenize();
+    assert_eq!(diagnostics.len(), 1);
+    let lexemes: Vec<&str> = tokens.iter().map(|t| t.lexeme).collect();
+    assert_eq!(lexemes, vec!["x", "=", "10abc", ";", ""]);
+    assert_eq!(tokens[2].token_type, TokenType::IntLiteral(0));
+}
+
+#[test]
+fn test_tokenize_number_range() {
+    let err = tokenize_err("18446744073709551616u");
+    assert_eq!(err.code, codes::INTEGER_OVERFLOW);
+
+    let err = tokenize_err("0x8000000000000000");
+    assert_eq!(err.code, codes::INTEGER_OVERFLOW);
+    assert_eq!(
+        err.notes,
+        vec!["use a `u` suffix for values up to 2^64 - 1"]
+    );
+
+    let err = tokenize_err("1e999");
+    assert_eq!(err.message, "float literal `1e999` is out of range");
+}
+
+#[test]
+fn test_tokenize_string_literals() {
+    let tokens = tokenize(r#"puts("hello\n") "" "tab\there" "q\"\\\'""#);
+    assert_eq!(
+        tokens[2].token_type,
+        TokenType::StringLiteral("hello\n".into())
+    );
+    assert_eq!(tokens[2].lexeme, r#""hello\n""#);
+    assert_eq!(tokens[2].span.len(), 9);
+    assert_eq!(tokens[4].token_type, TokenType::StringLiteral("".into()));
+    assert_eq!(
+        tokens[5].token_type,
+        TokenType::StringLiteral("tab\there".into())
+    );
+    assert_eq!(
+        tokens[6].token_type,
+        TokenType::StringLiteral("q\"\\'".into())
+    );
+}
+
+#[test]
+fn test_tokenize_escape_sequences() {
+    let types =
+        token_types(r"'a' '\n' '\t' '\r' '\0' '\\' '\'' '\x41' '\u{e9}' '\u{1F600}' 'é' '\x7f'");
+    let values = [
+        'a', '\n', '\t', '\r', '\0', '\\', '\'', 'A', 'é', '😀', 'é', '\x7f',
+    ];
+    assert_eq!(types, values.map(TokenType::CharLiteral).to_vec());
+
+    let tokens = tokenize(r#""\x48\x49 \u{263A}""#);
+    assert_eq!(
+        tokens[0].token_type,
+        TokenType::StringLiteral("HI \u{263A}".into())
+    );
+}
+
+#[test]
+fn test_tokenize_unterminated_literals() {
+    let err = tokenize_err("x = \"abc\ny;");
+    assert_eq!(err.code, codes::UNTERMINATED_LITERAL);
+    assert_eq!(err.message, "unterminated string literal");
+    // points at the opening quote
+    assert_eq!(err.span.start, Position::new(4, 1, 5));
+    assert_eq!(err.span.len(), 1);
+    assert_eq!(err.labels[0].message, "expected `\"`");
+
+    let err = tokenize_err("'a");
+    assert_eq!(err.message, "unterminated character literal");
+
+    // the rest of the input is still tokenized
+    let (tokens, _) = Tokenizer::new("\"abc\nint").tokenize();
+    assert_eq!(tokens[0].token_type, TokenType::StringLiteral("abc".into()));
+    assert_eq!(tokens[1].token_type, TokenType::Keyword(Keyword::Int));
+}
+
+#[test]
+fn test_tokenize_invalid_escapes() {
+    let cases = [
+        (r#""\q""#, "unknown escape sequence `\\q`", 1, 3),
+        (r#""\x4""#, "`\\x` must be followed by two hex digits", 1, 4),
+        (r#""\u41""#, "expected `{` after `\\u`", 1, 3),
+        (
+            r#""\u{}""#,
+            "`\\u{...}` must contain one to six hex digits",
+            1,
+            4,
+        ),
+        (
+            r#""\u{1234567}""#,
+            "`\\u{...}` must contain one to six hex digits",
+            1,
+            10,
+        ),
+        (
+            r#""\u{D800}""#,
+            "`D800` is not a valid Unicode scalar value",
+            1,
+            9,
+        ),
+    ];
+    for (input, message, start, end) in cases {
+        let err = tokenize_err(input);
+        assert_eq!(err.code, codes::INVALID_ESCAPE, "input: {}", input);
+        assert_eq!(err.message, message, "input: {}", input);
+        assert_eq!(
+            (err.span.start.offset, err.span.end.offset),
+            (start, end),
+            "input: {}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_tokenize_invalid_char_literals() {
+    let err = tokenize_err("''");
+    assert_eq!(err.code, codes::INVALID_CHAR_LITERAL);
+    assert_eq!(err.message, "empty character literal");
+
+    let err = tokenize_err("'ab'");
+    assert_eq!(
+        err.message,
+        "character literal may only contain one character"
+    );
+    assert_eq!(err.span.len(), 4);
+}
+
+#[test]
+fn test_tokens_borrow_from_source() {
+    let input = r#"name "plain" "esc\n""#;
+    let tokens = tokenize(input);
+    assert!(std::ptr::eq(tokens[0].lexeme, &input[..4]));
+    assert!(matches!(
+        &tokens[1].token_type,
+        TokenType::StringLiteral(Cow::Borrowed("plain"))
+    ));
+    // an escape means the value no longer matches the source text
+    assert!(matches!(
+        &tokens[2].token_type,
+        TokenType::StringLiteral(Cow::Owned(value)) if value == "esc\n"
+    ));
+}
+
+#[test]
+fn test_tokenizer_is_an_iterator() {
+    let mut tokenizer = Tokenizer::new("a @ 1x");
+    assert_eq!(tokenizer.next().unwrap().unwrap().lexeme, "a");
+    // an error comes before the token scanned after it
+    let error = tokenizer.next().unwrap().unwrap_err();
+    assert_eq!(error.code, codes::UNEXPECTED_CHARACTER);
+    // a malformed literal yields its error, then a placeholder token
+    let error = tokenizer.next().unwrap().unwrap_err();
+    assert_eq!(error.code, codes::INVALID_NUMBER);
+    assert_eq!(
+        tokenizer.next().unwrap().unwrap().token_type,
+        TokenType::IntLiteral(0)
+    );
+    assert_eq!(
+        tokenizer.next().unwrap().unwrap().token_type,
+        TokenType::Eof
+    );
+    assert!(tokenizer.next().is_none());
+    assert!(tokenizer.next().is_none());
+}
+
+#[test]
+fn test_tokenize_visible_range() {
+    let input = "int a;\nint b = 2;\nint c = \"unterminated\n";
+    // from the start of line 2 up to the end of it, without scanning line 3
+    let start = Position::new(7, 2, 1);
+    let lexemes: Vec<&str> = Tokenizer::new(input)
+        .starting_at(start)
+        .map_while(Result::ok)
+        .take_while(|t| t.span.start.line == 2)
+        .map(|t| t.lexeme)
+        .collect();
+    assert_eq!(lexemes, vec!["int", "b", "=", "2", ";"]);
+
+    let token = Tokenizer::new(input)
+        .starting_at(start)
+        .nth(1)
+        .unwrap()
+        .unwrap();
+    assert_eq!(token.span.start, Position::new(11, 2, 5));
 }
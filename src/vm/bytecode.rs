@@ -0,0 +1,281 @@
+//! The `.uvmb` file format: a compiled script function, serialized. The
+//! script defines the program's globals and functions, and whoever runs it
+//! calls the entry point, `main` for `uvm run`.
+//!
+//! ```text
+//! file     = "UVMB" version:u8 function
+//! function = name:str arity:u8 code:bytes constants:u32 constant* spans:u32 span*
+//! constant = tag:u8 payload
+//! span     = offset:u32 start:position end:position
+//! position = offset:u32 line:u32 column:u32
+//! str      = len:u32 utf8-bytes
+//! bytes    = len:u32 bytes
+//! ```
+//!
+//! Integers are little-endian.
+
+use super::{Chunk, Function, Value};
+use crate::span::{Position, Span};
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+const MAGIC: &[u8; 4] = b"UVMB";
+// Version 1 scripts called `main` themselves.
+const VERSION: u8 = 2;
+
+const TAG_INT: u8 = 0;
+const TAG_FLOAT: u8 = 1;
+const TAG_BOOL: u8 = 2;
+const TAG_CHAR: u8 = 3;
+const TAG_STR: u8 = 4;
+const TAG_FUNCTION: u8 = 5;
+const TAG_VOID: u8 = 6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+    pub message: String,
+    /// Byte offset in the input where decoding failed.
+    pub offset: usize,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+pub fn is_bytecode(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+pub fn encode(function: &Function) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    out.push(VERSION);
+    encode_function(&mut out, function);
+    out
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Rc<Function>, DecodeError> {
+    let mut reader = Reader { bytes, offset: 0 };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(reader.error("not a uvm bytecode file"));
+    }
+    let version = reader.u8()?;
+    if version != VERSION {
+        return Err(reader.error(format!("unsupported bytecode version {}", version)));
+    }
+    let function = reader.function()?;
+    if reader.offset != bytes.len() {
+        return Err(reader.error("trailing bytes after the script"));
+    }
+    Ok(function)
+}
+
+fn encode_function(out: &mut Vec<u8>, function: &Function) {
+    encode_str(out, &function.name);
+    out.push(function.arity);
+    encode_u32(out, function.chunk.code.len());
+    out.extend_from_slice(&function.chunk.code);
+
+    encode_u32(out, function.chunk.constants.len());
+    for constant in &function.chunk.constants {
+        match constant {
+            Value::Int(value) => {
+                out.push(TAG_INT);
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            Value::Float(value) => {
+                out.push(TAG_FLOAT);
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            Value::Bool(value) => out.extend_from_slice(&[TAG_BOOL, *value as u8]),
+            Value::Char(value) => {
+                out.push(TAG_CHAR);
+                encode_u32(out, *value as usize);
+            }
+            Value::Str(value) => {
+                out.push(TAG_STR);
+                encode_str(out, value);
+            }
+            Value::Function(function) => {
+                out.push(TAG_FUNCTION);
+                encode_function(out, function);
+            }
+            // natives are bound to globals when the VM starts, never stored as constants
+            Value::Native(native) => unreachable!("native `{}` in a constant pool", native.name),
+            Value::Void => out.push(TAG_VOID),
+        }
+    }
+
+    encode_u32(out, function.chunk.spans.len());
+    for (offset, span) in &function.chunk.spans {
+        encode_u32(out, *offset);
+        for position in [span.start, span.end] {
+            encode_u32(out, position.offset);
+            encode_u32(out, position.line);
+            encode_u32(out, position.column);
+        }
+    }
+}
+
+fn encode_u32(out: &mut Vec<u8>, value: usize) {
+    let value = u32::try_from(value).expect("bytecode value exceeds u32");
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn encode_str(out: &mut Vec<u8>, value: &str) {
+    encode_u32(out, value.len());
+    out.extend_from_slice(value.as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn error(&self, message: impl Into<String>) -> DecodeError {
+        DecodeError {
+            message: message.into(),
+            offset: self.offset,
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let bytes = self
+            .bytes
+            .get(self.offset..self.offset + len)
+            .ok_or_else(|| self.error("unexpected end of file"))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<usize, DecodeError> {
+        Ok(u32::from_le_bytes(self.array()?) as usize)
+    }
+
+    fn str(&mut self) -> Result<String, DecodeError> {
+        let len = self.u32()?;
+        let start = self.offset;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError {
+            message: "invalid UTF-8 in string".to_string(),
+            offset: start,
+        })
+    }
+
+    fn position(&mut self) -> Result<Position, DecodeError> {
+        Ok(Position::new(self.u32()?, self.u32()?, self.u32()?))
+    }
+
+    fn function(&mut self) -> Result<Rc<Function>, DecodeError> {
+        let name = self.str()?;
+        let arity = self.u8()?;
+        let len = self.u32()?;
+        let mut chunk = Chunk::new();
+        chunk.code = self.take(len)?.to_vec();
+
+        for _ in 0..self.u32()? {
+            let constant = match self.u8()? {
+                TAG_INT => Value::Int(i64::from_le_bytes(self.array()?)),
+                TAG_FLOAT => Value::Float(f64::from_le_bytes(self.array()?)),
+                TAG_BOOL => Value::Bool(self.u8()? != 0),
+                TAG_CHAR => {
+                    let value = self.u32()? as u32;
+                    Value::Char(char::from_u32(value).ok_or_else(|| self.error("invalid char"))?)
+                }
+                TAG_STR => Value::Str(self.str()?.into()),
+                TAG_FUNCTION => Value::Function(self.function()?),
+                TAG_VOID => Value::Void,
+                tag => return Err(self.error(format!("unknown constant tag {}", tag))),
+            };
+            chunk.constants.push(constant);
+        }
+
+        for _ in 0..self.u32()? {
+            let offset = self.u32()?;
+            let span = Span::new(self.position()?, self.position()?);
+            chunk.spans.push((offset, span));
+        }
+
+        Ok(Rc::new(Function { name, arity, chunk }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vm::OpCode;
+
+    fn script() -> Function {
+        let span = Span::new(Position::new(4, 2, 3), Position::new(9, 2, 8));
+        let mut inner = Chunk::new();
+        inner.write_op(OpCode::Void, span);
+        inner.write_op(OpCode::Return, span);
+
+        let mut chunk = Chunk::new();
+        for value in [
+            Value::Int(-7),
+            Value::Float(2.5),
+            Value::Bool(true),
+            Value::Char('é'),
+            Value::Str("main".into()),
+            Value::Void,
+            Value::Function(Rc::new(Function {
+                name: "f".to_string(),
+                arity: 2,
+                chunk: inner,
+            })),
+        ] {
+            let index = chunk.add_constant(value).unwrap();
+            chunk.write_op(OpCode::Constant, span);
+            chunk.write_u16(index, Span::default());
+        }
+        chunk.write_op(OpCode::Halt, span);
+        Function {
+            name: "<script>".to_string(),
+            arity: 0,
+            chunk,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let script = script();
+        let bytes = encode(&script);
+        assert!(is_bytecode(&bytes));
+        assert_eq!(*decode(&bytes).unwrap(), script);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(
+            decode(b"int main() {}").unwrap_err().message,
+            "not a uvm bytecode file"
+        );
+
+        let mut bytes = encode(&script());
+        bytes[4] = 99;
+        assert_eq!(
+            decode(&bytes).unwrap_err().message,
+            "unsupported bytecode version 99"
+        );
+
+        let bytes = encode(&script());
+        let err = decode(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("unexpected end of file at byte {}", bytes.len() - 4)
+        );
+    }
+}
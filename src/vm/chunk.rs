@@ -0,0 +1,76 @@
+use super::{OpCode, Value};
+use crate::span::Span;
+
+/// A unit of compiled bytecode: the instructions, the constants they refer to
+/// and a line table mapping instruction offsets back to source spans.
+#[derive(Debug, Default, PartialEq)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Value>,
+    // (offset, span) pairs, one per run of bytes emitted for the same span
+    pub(super) spans: Vec<(usize, Span)>,
+}
+
+impl Chunk {
+    pub fn new() -> Chunk {
+        Chunk::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.code.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.code.is_empty()
+    }
+
+    pub fn write(&mut self, byte: u8, span: Span) {
+        if self.spans.last().is_none_or(|(_, last)| *last != span) {
+            self.spans.push((self.code.len(), span));
+        }
+        self.code.push(byte);
+    }
+
+    pub fn write_op(&mut self, op: OpCode, span: Span) {
+        self.write(op as u8, span);
+    }
+
+    pub fn write_u16(&mut self, value: u16, span: Span) {
+        for byte in value.to_be_bytes() {
+            self.write(byte, span);
+        }
+    }
+
+    pub fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_be_bytes([self.code[offset], self.code[offset + 1]])
+    }
+
+    /// Overwrites the `u16` operand at `offset`, used to backpatch jumps.
+    pub fn patch_u16(&mut self, offset: usize, value: u16) {
+        self.code[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+    }
+
+    /// Adds `value` to the constant pool, reusing an equal existing entry.
+    /// Returns `None` once the pool is full.
+    pub fn add_constant(&mut self, value: Value) -> Option<u16> {
+        let index = match self.constants.iter().position(|c| *c == value) {
+            Some(index) => index,
+            None => {
+                self.constants.push(value);
+                self.constants.len() - 1
+            }
+        };
+        u16::try_from(index).ok()
+    }
+
+    /// The source span of the instruction byte at `offset`.
+    pub fn span_at(&self, offset: usize) -> Option<Span> {
+        let run = self.spans.partition_point(|(start, _)| *start <= offset);
+        run.checked_sub(1).map(|run| self.spans[run].1)
+    }
+
+    /// The 1-based source line of the instruction byte at `offset`.
+    pub fn line_at(&self, offset: usize) -> Option<usize> {
+        self.span_at(offset).map(|span| span.start.line)
+    }
+}
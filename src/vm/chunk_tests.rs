@@ -0,0 +1,90 @@
+use super::*;
+use crate::span::{Position, Span};
+
+fn span(line: usize) -> Span {
+    let start = Position::new(0, line, 1);
+    Span::new(start, Position::new(1, line, 2))
+}
+
+#[test]
+fn test_opcode_round_trip() {
+    for byte in 0..=u8::MAX {
+        if let Some(op) = OpCode::from_byte(byte) {
+            assert_eq!(op as u8, byte);
+        }
+    }
+    assert_eq!(
+        OpCode::from_byte(OpCode::Convert as u8),
+        Some(OpCode::Convert)
+    );
+    assert_eq!(OpCode::from_byte(OpCode::Convert as u8 + 1), None);
+}
+
+#[test]
+fn test_write_instructions() {
+    let mut chunk = Chunk::new();
+    let index = chunk.add_constant(Value::Int(10)).unwrap();
+    chunk.write_op(OpCode::Constant, span(1));
+    chunk.write_u16(index, span(1));
+    chunk.write_op(OpCode::Negate, span(1));
+    chunk.write_op(OpCode::Return, span(2));
+
+    assert_eq!(
+        chunk.code,
+        vec![
+            OpCode::Constant as u8,
+            0,
+            0,
+            OpCode::Negate as u8,
+            OpCode::Return as u8
+        ]
+    );
+    assert_eq!(chunk.constants, vec![Value::Int(10)]);
+}
+
+#[test]
+fn test_u16_operands_are_big_endian() {
+    let mut chunk = Chunk::new();
+    chunk.write_op(OpCode::Jump, span(1));
+    chunk.write_u16(0xFFFF, span(1));
+    chunk.patch_u16(1, 0x1234);
+
+    assert_eq!(chunk.code[1..], [0x12, 0x34]);
+    assert_eq!(chunk.read_u16(1), 0x1234);
+}
+
+#[test]
+fn test_constants_are_deduplicated() {
+    let mut chunk = Chunk::new();
+    assert_eq!(chunk.add_constant(Value::Int(1)), Some(0));
+    assert_eq!(chunk.add_constant(Value::Str("a".into())), Some(1));
+    assert_eq!(chunk.add_constant(Value::Int(1)), Some(0));
+    assert_eq!(chunk.add_constant(Value::Float(1.0)), Some(2));
+    assert_eq!(chunk.constants.len(), 3);
+}
+
+#[test]
+fn test_line_table() {
+    let mut chunk = Chunk::new();
+    chunk.write_op(OpCode::Constant, span(1));
+    chunk.write_u16(0, span(1));
+    chunk.write_op(OpCode::Pop, span(3));
+    chunk.write_op(OpCode::Void, span(4));
+    chunk.write_op(OpCode::Return, span(4));
+
+    let lines: Vec<Option<usize>> = (0..=chunk.len()).map(|i| chunk.line_at(i)).collect();
+    assert_eq!(
+        lines,
+        vec![
+            Some(1),
+            Some(1),
+            Some(1),
+            Some(3),
+            Some(4),
+            Some(4),
+            Some(4)
+        ]
+    );
+    assert_eq!(chunk.span_at(3), Some(span(3)));
+    assert_eq!(Chunk::new().span_at(0), None);
+}
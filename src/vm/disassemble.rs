@@ -0,0 +1,143 @@
+use super::{Chunk, Function, OpCode, ScalarType, Value};
+use std::fmt::Write;
+
+/// Disassembles `function` and, after it, every function in its constant pool.
+///
+/// ```text
+/// == main/0 ==
+/// 0000    1 Constant         0 10
+/// 0003    | SetLocal         0
+/// 0005    2 JumpIfFalse      7 -> 0015
+/// ```
+///
+/// The second column is the source line, `|` when unchanged.
+pub fn disassemble(function: &Function) -> String {
+    let mut out = String::new();
+    disassemble_function(function, &mut out);
+    out
+}
+
+fn disassemble_function(function: &Function, out: &mut String) {
+    let _ = writeln!(out, "== {}/{} ==", function.name, function.arity);
+    let chunk = &function.chunk;
+    let mut offset = 0;
+    while offset < chunk.len() {
+        offset = disassemble_instruction(chunk, offset, out);
+    }
+
+    for constant in &chunk.constants {
+        if let Value::Function(function) = constant {
+            out.push('\n');
+            disassemble_function(function, out);
+        }
+    }
+}
+
+/// Appends the instruction at `offset` to `out` and returns the next offset.
+pub fn disassemble_instruction(chunk: &Chunk, offset: usize, out: &mut String) -> usize {
+    let _ = write!(out, "{:04} ", offset);
+    let line = chunk.line_at(offset);
+    if offset > 0 && line == chunk.line_at(offset - 1) {
+        let _ = write!(out, "   | ");
+    } else {
+        let _ = write!(out, "{:4} ", line.unwrap_or(0));
+    }
+
+    let byte = chunk.code[offset];
+    let op = match OpCode::from_byte(byte) {
+        Some(op) => op,
+        None => {
+            let _ = writeln!(out, "<invalid {:#04x}>", byte);
+            return offset + 1;
+        }
+    };
+    let next = offset + 1 + op.operand_len();
+    if next > chunk.len() {
+        let _ = writeln!(out, "{:?} <truncated>", op);
+        return chunk.len();
+    }
+
+    let name = format!("{:?}", op);
+    match op {
+        OpCode::Constant | OpCode::GetGlobal | OpCode::SetGlobal | OpCode::DefineGlobal => {
+            let index = chunk.read_u16(offset + 1);
+            match chunk.constants.get(index as usize) {
+                Some(value) => writeln!(out, "{:<16} {:4} {}", name, index, value),
+                None => writeln!(out, "{:<16} {:4} <invalid>", name, index),
+            }
+        }
+        OpCode::GetLocal | OpCode::SetLocal | OpCode::Call => {
+            writeln!(out, "{:<16} {:4}", name, chunk.code[offset + 1])
+        }
+        OpCode::Convert => match ScalarType::from_byte(chunk.code[offset + 1]) {
+            Some(to) => writeln!(out, "{:<16} {:>4}", name, to.name()),
+            None => writeln!(out, "{:<16} <invalid>", name),
+        },
+        OpCode::Jump | OpCode::JumpIfFalse => {
+            let distance = chunk.read_u16(offset + 1) as usize;
+            writeln!(out, "{:<16} {:4} -> {:04}", name, distance, next + distance)
+        }
+        OpCode::Loop => {
+            let distance = chunk.read_u16(offset + 1) as usize;
+            let target = next.saturating_sub(distance);
+            writeln!(out, "{:<16} {:4} -> {:04}", name, distance, target)
+        }
+        _ => writeln!(out, "{}", name),
+    }
+    .ok();
+    next
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::span::{Position, Span};
+    use std::rc::Rc;
+
+    fn line(line: usize) -> Span {
+        Span::new(Position::new(0, line, 1), Position::new(1, line, 2))
+    }
+
+    #[test]
+    fn disassembles_operands_and_lines() {
+        let mut inner = Chunk::new();
+        inner.write_op(OpCode::GetLocal, line(1));
+        inner.write(0, line(1));
+        inner.write_op(OpCode::Return, line(1));
+        let f = Function {
+            name: "f".to_string(),
+            arity: 1,
+            chunk: inner,
+        };
+
+        let mut chunk = Chunk::new();
+        let index = chunk.add_constant(Value::Function(Rc::new(f))).unwrap();
+        chunk.write_op(OpCode::Constant, line(1));
+        chunk.write_u16(index, line(1));
+        chunk.write_op(OpCode::JumpIfFalse, line(2));
+        chunk.write_u16(1, line(2));
+        chunk.write_op(OpCode::Pop, line(2));
+        chunk.write_op(OpCode::Loop, line(3));
+        chunk.write_u16(10, line(3));
+        chunk.write(0xEE, line(3));
+        let script = Function {
+            name: "<script>".to_string(),
+            arity: 0,
+            chunk,
+        };
+
+        let expected = "\
+== <script>/0 ==
+0000    1 Constant            0 <fn f>
+0003    2 JumpIfFalse         1 -> 0007
+0006    | Pop
+0007    3 Loop               10 -> 0000
+0010    | <invalid 0xee>
+
+== f/1 ==
+0000    1 GetLocal            0
+0002    | Return
+";
+        assert_eq!(disassemble(&script), expected);
+    }
+}
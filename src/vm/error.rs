@@ -0,0 +1,183 @@
+use crate::diagnostic::{codes, Diagnostic};
+use crate::span::Span;
+use std::fmt::{Display, Formatter};
+
+// Past this many backtrace notes only the innermost and outermost frames
+// are shown, half of them each.
+const BACKTRACE_NOTES_MAX: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeErrorKind {
+    DivisionByZero,
+    StackOverflow,
+    StackUnderflow,
+    InvalidOpcode(u8),
+    /// The instruction pointer ran past the end of the chunk.
+    UnexpectedEndOfCode,
+    InvalidConstant(u16),
+    InvalidConversion(u8),
+    TypeMismatch {
+        operation: &'static str,
+        found: &'static str,
+    },
+    UndefinedGlobal(String),
+    NotCallable(&'static str),
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A native function failed, with the message it returned.
+    Native {
+        name: String,
+        message: String,
+    },
+    Aborted,
+    /// The embedder passed an entry point an argument of the wrong type.
+    InvalidArgument {
+        name: String,
+        index: usize,
+        expected: String,
+        found: &'static str,
+    },
+}
+
+/// An error raised while executing bytecode, located with the chunk's line table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    pub span: Span,
+    /// Names of the functions on the call stack, innermost first.
+    pub backtrace: Vec<String>,
+}
+
+impl RuntimeError {
+    pub fn line(&self) -> usize {
+        self.span.start.line
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self.kind {
+            RuntimeErrorKind::DivisionByZero => codes::DIVISION_BY_ZERO,
+            RuntimeErrorKind::StackOverflow => codes::STACK_OVERFLOW,
+            RuntimeErrorKind::StackUnderflow
+            | RuntimeErrorKind::InvalidOpcode(_)
+            | RuntimeErrorKind::UnexpectedEndOfCode
+            | RuntimeErrorKind::InvalidConstant(_)
+            | RuntimeErrorKind::InvalidConversion(_) => codes::INVALID_BYTECODE,
+            RuntimeErrorKind::TypeMismatch { .. } => codes::TYPE_MISMATCH_AT_RUNTIME,
+            RuntimeErrorKind::UndefinedGlobal(_) => codes::UNDEFINED_GLOBAL,
+            RuntimeErrorKind::NotCallable(_) => codes::NOT_CALLABLE,
+            RuntimeErrorKind::ArityMismatch { .. } => codes::ARITY_MISMATCH_AT_RUNTIME,
+            RuntimeErrorKind::Native { .. } => codes::NATIVE_ERROR,
+            RuntimeErrorKind::Aborted => codes::ABORTED,
+            RuntimeErrorKind::InvalidArgument { .. } => codes::INVALID_ARGUMENT,
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let mut diagnostic = Diagnostic::error(self.code(), self.kind.to_string(), self.span);
+        for note in self.backtrace_notes() {
+            diagnostic = diagnostic.with_note(note);
+        }
+        diagnostic
+    }
+
+    // One note per run of calls to the same function, so deep recursion
+    // takes a line rather than a screenful.
+    fn backtrace_notes(&self) -> Vec<String> {
+        let mut runs: Vec<(&str, usize)> = Vec::new();
+        for name in &self.backtrace {
+            match runs.last_mut() {
+                Some((last, count)) if last == name => *count += 1,
+                _ => runs.push((name, 1)),
+            }
+        }
+        let note = |&(name, count): &(&str, usize)| match count {
+            1 => format!("in function `{}`", name),
+            _ => format!("in function `{}` (repeated {} times)", name, count),
+        };
+        if runs.len() <= BACKTRACE_NOTES_MAX {
+            return runs.iter().map(note).collect();
+        }
+        let half = BACKTRACE_NOTES_MAX / 2;
+        let (inner, rest) = runs.split_at(half);
+        let (omitted, outer) = rest.split_at(rest.len() - half);
+        let frames: usize = omitted.iter().map(|(_, count)| count).sum();
+        let mut notes: Vec<String> = inner.iter().map(note).collect();
+        notes.push(format!("... {} more frame(s) ...", frames));
+        notes.extend(outer.iter().map(note));
+        notes
+    }
+}
+
+impl Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeErrorKind::DivisionByZero => write!(f, "division by zero"),
+            RuntimeErrorKind::StackOverflow => write!(f, "stack overflow"),
+            RuntimeErrorKind::StackUnderflow => write!(f, "stack underflow"),
+            RuntimeErrorKind::InvalidOpcode(byte) => write!(f, "invalid opcode {:#04x}", byte),
+            RuntimeErrorKind::UnexpectedEndOfCode => write!(f, "unexpected end of bytecode"),
+            RuntimeErrorKind::InvalidConstant(index) => {
+                write!(f, "invalid constant index {}", index)
+            }
+            RuntimeErrorKind::InvalidConversion(byte) => {
+                write!(f, "invalid conversion target {:#04x}", byte)
+            }
+            RuntimeErrorKind::TypeMismatch { operation, found } => {
+                write!(
+                    f,
+                    "cannot apply `{}` to a value of type `{}`",
+                    operation, found
+                )
+            }
+            RuntimeErrorKind::UndefinedGlobal(name) => {
+                write!(f, "undefined global `{}`", name)
+            }
+            RuntimeErrorKind::NotCallable(found) => {
+                write!(f, "a value of type `{}` is not callable", found)
+            }
+            RuntimeErrorKind::ArityMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{}` takes {} argument(s) but {} were supplied",
+                name, expected, found
+            ),
+            RuntimeErrorKind::Native { name, message } => {
+                write!(f, "`{}` failed: {}", name, message)
+            }
+            RuntimeErrorKind::Aborted => write!(f, "program aborted"),
+            RuntimeErrorKind::InvalidArgument {
+                name,
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "argument {} of `{}` expects `{}`, found a value of type `{}`",
+                index + 1,
+                name,
+                expected,
+                found
+            ),
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "runtime error: {} at line {}", self.kind, self.line())
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+impl From<RuntimeError> for Diagnostic {
+    fn from(error: RuntimeError) -> Diagnostic {
+        error.to_diagnostic()
+    }
+}
@@ -0,0 +1,26 @@
+pub mod bytecode;
+mod chunk;
+#[cfg(test)]
+mod chunk_tests;
+mod disassemble;
+mod error;
+mod native;
+mod opcode;
+pub mod stdlib;
+#[cfg(test)]
+mod stdlib_tests;
+mod value;
+#[allow(clippy::module_inception)]
+mod vm;
+#[cfg(test)]
+mod vm_tests;
+
+pub use chunk::Chunk;
+pub use disassemble::{disassemble, disassemble_instruction};
+pub use error::{RuntimeError, RuntimeErrorKind};
+pub use native::{Halt, Native, NativeContext, NativeRegistry, NativeResult, Signature};
+pub use opcode::OpCode;
+pub use value::{Function, ScalarType, Value};
+#[cfg(test)]
+pub(crate) use vm::SharedBuffer;
+pub use vm::{Vm, FRAMES_MAX, STACK_MAX};
@@ -0,0 +1,159 @@
+use super::Value;
+use crate::parser::ast::Type;
+use std::fmt::{self, Debug, Formatter};
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+/// What a native returns: its result, or a message the VM reports as a
+/// runtime error in the calling function.
+pub type NativeResult = Result<Value, String>;
+
+type NativeFn = dyn Fn(&mut NativeContext, &[Value]) -> NativeResult;
+
+/// The C type of a native function, declared to the resolver and the type
+/// checker so calls to it are checked like calls to any other function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub ret: Type,
+    pub params: Vec<Type>,
+    /// Whether arguments of any type may follow `params`, as for `printf`.
+    pub variadic: bool,
+}
+
+impl Signature {
+    pub fn new(ret: Type, params: Vec<Type>) -> Signature {
+        Signature {
+            ret,
+            params,
+            variadic: false,
+        }
+    }
+
+    pub fn variadic(mut self) -> Signature {
+        self.variadic = true;
+        self
+    }
+
+    pub fn accepts(&self, argc: usize) -> bool {
+        argc == self.params.len() || (self.variadic && argc > self.params.len())
+    }
+}
+
+/// A request from a native to stop the program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Halt {
+    Exit(i32),
+    Abort,
+}
+
+/// What a native can reach while it runs: the VM's standard streams and a
+/// way to stop the program once it returns.
+pub struct NativeContext<'a> {
+    pub output: &'a mut dyn Write,
+    pub input: &'a mut dyn BufRead,
+    pub(super) halt: Option<Halt>,
+}
+
+impl<'a> NativeContext<'a> {
+    pub fn new(output: &'a mut dyn Write, input: &'a mut dyn BufRead) -> NativeContext<'a> {
+        NativeContext {
+            output,
+            input,
+            halt: None,
+        }
+    }
+
+    /// Ends the program with `status` as its result.
+    pub fn exit(&mut self, status: i32) {
+        self.halt = Some(Halt::Exit(status));
+    }
+
+    /// Ends the program with a runtime error.
+    pub fn abort(&mut self) {
+        self.halt = Some(Halt::Abort);
+    }
+}
+
+/// A function implemented in Rust and called from C like any other.
+pub struct Native {
+    pub name: String,
+    pub signature: Signature,
+    function: Box<NativeFn>,
+}
+
+impl Native {
+    pub fn call(&self, context: &mut NativeContext, args: &[Value]) -> NativeResult {
+        (self.function)(context, args)
+    }
+}
+
+impl Debug for Native {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Native")
+            .field("name", &self.name)
+            .field("signature", &self.signature)
+            .finish_non_exhaustive()
+    }
+}
+
+// Closures cannot be compared, a native is only ever equal to itself.
+impl PartialEq for Native {
+    fn eq(&self, other: &Native) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// The natives a program may call, in registration order. The same registry
+/// is handed to the resolver, the type checker, the compiler and the VM.
+#[derive(Debug, Clone, Default)]
+pub struct NativeRegistry {
+    natives: Vec<Rc<Native>>,
+}
+
+impl NativeRegistry {
+    pub fn new() -> NativeRegistry {
+        NativeRegistry::default()
+    }
+
+    /// A registry holding the standard library, see [`super::stdlib`].
+    pub fn with_stdlib() -> NativeRegistry {
+        let mut registry = NativeRegistry::new();
+        super::stdlib::register(&mut registry);
+        registry
+    }
+
+    /// Adds a native, replacing any earlier one with the same name.
+    pub fn register(
+        &mut self,
+        name: &str,
+        signature: Signature,
+        function: impl Fn(&mut NativeContext, &[Value]) -> NativeResult + 'static,
+    ) -> &mut NativeRegistry {
+        let native = Rc::new(Native {
+            name: name.to_string(),
+            signature,
+            function: Box::new(function),
+        });
+        match self.natives.iter_mut().find(|n| n.name == name) {
+            Some(existing) => *existing = native,
+            None => self.natives.push(native),
+        }
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Rc<Native>> {
+        self.natives.iter().find(|native| native.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rc<Native>> {
+        self.natives.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.natives.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.natives.is_empty()
+    }
+}
@@ -0,0 +1,125 @@
+/// The VM instruction set. Every instruction is a one byte opcode followed by
+/// its operands; multi-byte operands are big-endian.
+///
+/// The VM is stack based: operands are popped from the operand stack and
+/// results pushed back. Comments describe the stack effect as `before -> after`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OpCode {
+    /// `u16` constant index. `-> value`
+    Constant,
+    /// `-> void`, the value of calls to `void` functions.
+    Void,
+    /// `value ->`
+    Pop,
+    /// `value -> value value`
+    Dup,
+    /// `u8` slot in the current frame. `-> value`
+    GetLocal,
+    /// `u8` slot in the current frame. `value -> value`
+    SetLocal,
+    /// `u16` constant index of the global's name. `-> value`
+    GetGlobal,
+    /// `u16` constant index of the global's name. `value -> value`
+    SetGlobal,
+    /// `u16` constant index of the global's name. `value ->`
+    DefineGlobal,
+    /// `a b -> a + b`
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    /// `a -> -a`
+    Negate,
+    /// `a -> !a`
+    Not,
+    /// `a -> ~a`
+    BitNot,
+    /// `a b -> a == b`
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    /// `u16` forward offset from the end of the instruction.
+    Jump,
+    /// `u16` forward offset from the end of the instruction. `condition ->`
+    JumpIfFalse,
+    /// `u16` backward offset from the end of the instruction.
+    Loop,
+    /// `u8` argument count. `callee arg1..argN -> result`
+    Call,
+    /// Returns the top of the stack to the caller. `result ->`
+    Return,
+    /// Stops execution with the top of the stack as the result.
+    Halt,
+    /// `u8` [`ScalarType`](super::ScalarType) tag. `value -> converted`
+    Convert,
+}
+
+impl OpCode {
+    // Indexed by opcode byte, `opcode_round_trip` checks the order matches.
+    const ALL: [OpCode; 35] = [
+        OpCode::Constant,
+        OpCode::Void,
+        OpCode::Pop,
+        OpCode::Dup,
+        OpCode::GetLocal,
+        OpCode::SetLocal,
+        OpCode::GetGlobal,
+        OpCode::SetGlobal,
+        OpCode::DefineGlobal,
+        OpCode::Add,
+        OpCode::Sub,
+        OpCode::Mul,
+        OpCode::Div,
+        OpCode::Mod,
+        OpCode::BitAnd,
+        OpCode::BitOr,
+        OpCode::BitXor,
+        OpCode::Shl,
+        OpCode::Shr,
+        OpCode::Negate,
+        OpCode::Not,
+        OpCode::BitNot,
+        OpCode::Equal,
+        OpCode::NotEqual,
+        OpCode::Less,
+        OpCode::LessEqual,
+        OpCode::Greater,
+        OpCode::GreaterEqual,
+        OpCode::Jump,
+        OpCode::JumpIfFalse,
+        OpCode::Loop,
+        OpCode::Call,
+        OpCode::Return,
+        OpCode::Halt,
+        OpCode::Convert,
+    ];
+
+    pub fn from_byte(byte: u8) -> Option<OpCode> {
+        OpCode::ALL.get(byte as usize).copied()
+    }
+
+    /// Number of operand bytes following the opcode.
+    pub fn operand_len(self) -> usize {
+        match self {
+            OpCode::GetLocal | OpCode::SetLocal | OpCode::Call | OpCode::Convert => 1,
+            OpCode::Constant
+            | OpCode::GetGlobal
+            | OpCode::SetGlobal
+            | OpCode::DefineGlobal
+            | OpCode::Jump
+            | OpCode::JumpIfFalse
+            | OpCode::Loop => 2,
+            _ => 0,
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! The natives every program can call: a small slice of C's `stdio.h` and
+//! `stdlib.h`. Output goes to the VM's output and input comes from its input,
+//! standard out and standard in unless the embedder redirects them.
+
+use super::native::{NativeContext, NativeRegistry, NativeResult, Signature};
+use super::Value;
+use crate::parser::ast::Type;
+use std::io;
+
+pub fn register(registry: &mut NativeRegistry) {
+    let string = Type::Pointer(Box::new(Type::Char));
+    registry
+        .register(
+            "print_int",
+            Signature::new(Type::Void, vec![Type::Int]),
+            print_int,
+        )
+        .register(
+            "print_char",
+            Signature::new(Type::Void, vec![Type::Char]),
+            print_char,
+        )
+        .register(
+            "puts",
+            Signature::new(Type::Int, vec![string.clone()]),
+            puts,
+        )
+        .register(
+            "printf",
+            Signature::new(Type::Int, vec![string]).variadic(),
+            printf,
+        )
+        .register("read_int", Signature::new(Type::Int, vec![]), read_int)
+        .register("getchar", Signature::new(Type::Int, vec![]), getchar)
+        .register("exit", Signature::new(Type::Void, vec![Type::Int]), exit)
+        .register("abort", Signature::new(Type::Void, vec![]), abort);
+}
+
+fn print_int(context: &mut NativeContext, args: &[Value]) -> NativeResult {
+    let value = int_arg(&args[0])?;
+    write(context, &value.to_string())?;
+    Ok(Value::Void)
+}
+
+fn print_char(context: &mut NativeContext, args: &[Value]) -> NativeResult {
+    let value = char_arg(&args[0])?;
+    write(context, value.encode_utf8(&mut [0; 4]))?;
+    Ok(Value::Void)
+}
+
+/// Writes the string and a newline, returning a non-negative number as C does.
+fn puts(context: &mut NativeContext, args: &[Value]) -> NativeResult {
+    let string = str_arg(&args[0])?;
+    write(context, string)?;
+    write(context, "\n")?;
+    Ok(Value::Int(0))
+}
+
+/// Returns the number of bytes written.
+fn printf(context: &mut NativeContext, args: &[Value]) -> NativeResult {
+    let text = format(str_arg(&args[0])?, &args[1..])?;
+    write(context, &text)?;
+    Ok(Value::Int(text.len() as i64))
+}
+
+/// Skips leading whitespace and reads a decimal integer with an optional sign.
+fn read_int(context: &mut NativeContext, _: &[Value]) -> NativeResult {
+    while peek_byte(context)?.is_some_and(|byte| byte.is_ascii_whitespace()) {
+        context.input.consume(1);
+    }
+    let mut digits = String::new();
+    if let Some(sign @ (b'-' | b'+')) = peek_byte(context)? {
+        digits.push(sign as char);
+        context.input.consume(1);
+    }
+    while let Some(digit) = peek_byte(context)?.filter(u8::is_ascii_digit) {
+        digits.push(digit as char);
+        context.input.consume(1);
+    }
+    match digits.parse() {
+        Ok(value) => Ok(Value::Int(value)),
+        Err(_) if digits.trim_start_matches(['-', '+']).is_empty() => {
+            Err("expected an integer on standard input".to_string())
+        }
+        Err(_) => Err(format!("integer `{}` is out of range", digits)),
+    }
+}
+
+/// Reads one byte, or returns -1 at the end of input.
+fn getchar(context: &mut NativeContext, _: &[Value]) -> NativeResult {
+    match peek_byte(context)? {
+        Some(byte) => {
+            context.input.consume(1);
+            Ok(Value::Int(byte as i64))
+        }
+        None => Ok(Value::Int(-1)),
+    }
+}
+
+fn exit(context: &mut NativeContext, args: &[Value]) -> NativeResult {
+    let status = int_arg(&args[0])?;
+    context.exit(status as i32);
+    Ok(Value::Void)
+}
+
+fn abort(context: &mut NativeContext, _: &[Value]) -> NativeResult {
+    context.abort();
+    Ok(Value::Void)
+}
+
+/// Expands a `printf` format string. Supports `%d`, `%s`, `%c`, `%x` and `%%`;
+/// arguments beyond those the format uses are ignored.
+pub fn format(format: &str, args: &[Value]) -> Result<String, String> {
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let conversion = chars
+            .next()
+            .ok_or("incomplete conversion at the end of the format")?;
+        if conversion == '%' {
+            out.push('%');
+            continue;
+        }
+        let arg = args
+            .next()
+            .ok_or_else(|| format!("missing argument for `%{}`", conversion))?;
+        match conversion {
+            'd' => out.push_str(&int_arg(arg)?.to_string()),
+            'x' => out.push_str(&format!("{:x}", int_arg(arg)?)),
+            'c' => out.push(char_arg(arg)?),
+            's' => out.push_str(str_arg(arg)?),
+            other => return Err(format!("unsupported conversion `%{}`", other)),
+        }
+    }
+    Ok(out)
+}
+
+fn int_arg(value: &Value) -> Result<i64, String> {
+    match value {
+        Value::Int(value) => Ok(*value),
+        Value::Char(value) => Ok(*value as i64),
+        Value::Bool(value) => Ok(*value as i64),
+        other => Err(expected("an integer", other)),
+    }
+}
+
+fn char_arg(value: &Value) -> Result<char, String> {
+    match value {
+        Value::Char(value) => Ok(*value),
+        Value::Int(code) => u32::try_from(*code)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("{} is not a valid character", code)),
+        other => Err(expected("a character", other)),
+    }
+}
+
+fn str_arg(value: &Value) -> Result<&str, String> {
+    match value {
+        Value::Str(value) => Ok(value),
+        other => Err(expected("a string", other)),
+    }
+}
+
+fn expected(what: &str, found: &Value) -> String {
+    format!(
+        "expected {}, found a value of type `{}`",
+        what,
+        found.type_name()
+    )
+}
+
+fn write(context: &mut NativeContext, text: &str) -> Result<(), String> {
+    context.output.write_all(text.as_bytes()).map_err(io_error)
+}
+
+fn peek_byte(context: &mut NativeContext) -> Result<Option<u8>, String> {
+    let buffer = context.input.fill_buf().map_err(io_error)?;
+    Ok(buffer.first().copied())
+}
+
+fn io_error(err: io::Error) -> String {
+    err.to_string()
+}
@@ -0,0 +1,139 @@
+use super::*;
+use crate::parser::ast::Type;
+use std::io::Cursor;
+
+// Calls the standard library native `name`, returning its result and output.
+fn call(name: &str, args: &[Value], input: &str) -> (NativeResult, String) {
+    let natives = NativeRegistry::with_stdlib();
+    let native = natives.get(name).expect("no such native");
+    let mut output = Vec::new();
+    let mut input = Cursor::new(input.as_bytes());
+    let result = native.call(&mut NativeContext::new(&mut output, &mut input), args);
+    (result, String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn test_printf_conversions() {
+    let args = [
+        Value::Int(-42),
+        Value::Str("str".into()),
+        Value::Char('é'),
+        Value::Int(255),
+    ];
+    assert_eq!(
+        stdlib::format("%d %s %c %x 100%%", &args),
+        Ok("-42 str é ff 100%".to_string())
+    );
+    // characters print as numbers and numbers as characters
+    assert_eq!(
+        stdlib::format("%d%c", &[Value::Char('a'), Value::Int(98)]),
+        Ok("97b".to_string())
+    );
+    // extra arguments are ignored
+    assert_eq!(
+        stdlib::format("none", &[Value::Int(1)]),
+        Ok("none".to_string())
+    );
+
+    let errors = [
+        ("%d", vec![], "missing argument for `%d`"),
+        ("%f", vec![Value::Float(1.0)], "unsupported conversion `%f`"),
+        (
+            "50%",
+            vec![],
+            "incomplete conversion at the end of the format",
+        ),
+        (
+            "%s",
+            vec![Value::Int(1)],
+            "expected a string, found a value of type `int`",
+        ),
+        ("%c", vec![Value::Int(-1)], "-1 is not a valid character"),
+    ];
+    for (format, args, message) in errors {
+        assert_eq!(
+            stdlib::format(format, &args),
+            Err(message.to_string()),
+            "format: {:?}",
+            format
+        );
+    }
+}
+
+#[test]
+fn test_output() {
+    let (result, output) = call(
+        "printf",
+        &[Value::Str("%d-%d\n".into()), Value::Int(1), Value::Int(2)],
+        "",
+    );
+    assert_eq!(result, Ok(Value::Int(4)));
+    assert_eq!(output, "1-2\n");
+
+    assert_eq!(
+        call("puts", &[Value::Str("line".into())], ""),
+        (Ok(Value::Int(0)), "line\n".to_string())
+    );
+    assert_eq!(
+        call("print_int", &[Value::Int(-7)], ""),
+        (Ok(Value::Void), "-7".to_string())
+    );
+    assert_eq!(
+        call("print_char", &[Value::Char('x')], ""),
+        (Ok(Value::Void), "x".to_string())
+    );
+}
+
+#[test]
+fn test_input() {
+    assert_eq!(call("read_int", &[], "  \n-15 rest").0, Ok(Value::Int(-15)));
+    assert_eq!(call("read_int", &[], "+8").0, Ok(Value::Int(8)));
+    assert_eq!(
+        call("read_int", &[], "abc").0,
+        Err("expected an integer on standard input".to_string())
+    );
+    assert_eq!(
+        call("read_int", &[], "").0,
+        Err("expected an integer on standard input".to_string())
+    );
+    assert_eq!(
+        call("read_int", &[], "99999999999999999999").0,
+        Err("integer `99999999999999999999` is out of range".to_string())
+    );
+
+    assert_eq!(call("getchar", &[], "A").0, Ok(Value::Int(65)));
+    assert_eq!(call("getchar", &[], "").0, Ok(Value::Int(-1)));
+
+    // reads continue where the previous one stopped
+    let natives = NativeRegistry::with_stdlib();
+    let mut output = Vec::new();
+    let mut input = Cursor::new("12 34".as_bytes());
+    let mut context = NativeContext::new(&mut output, &mut input);
+    let read_int = natives.get("read_int").unwrap();
+    let getchar = natives.get("getchar").unwrap();
+    assert_eq!(read_int.call(&mut context, &[]), Ok(Value::Int(12)));
+    assert_eq!(getchar.call(&mut context, &[]), Ok(Value::Int(' ' as i64)));
+    assert_eq!(read_int.call(&mut context, &[]), Ok(Value::Int(34)));
+}
+
+#[test]
+fn test_registering_replaces_natives_of_the_same_name() {
+    let mut natives = NativeRegistry::with_stdlib();
+    let stdlib_len = natives.len();
+    natives
+        .register("puts", Signature::new(Type::Int, vec![]), |_, _| {
+            Ok(Value::Int(1))
+        })
+        .register(
+            "twice",
+            Signature::new(Type::Int, vec![Type::Int]),
+            |_, args| match args {
+                [Value::Int(n)] => Ok(Value::Int(n * 2)),
+                _ => Err("expected an integer".to_string()),
+            },
+        );
+    assert_eq!(natives.len(), stdlib_len + 1);
+    assert_eq!(natives.get("puts").unwrap().signature.params, vec![]);
+    assert!(natives.get("printf").unwrap().signature.variadic);
+    assert!(natives.get("missing").is_none());
+}
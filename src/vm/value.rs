@@ -0,0 +1,126 @@
+use super::{Chunk, Native};
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    Str(Rc<str>),
+    Function(Rc<Function>),
+    Native(Rc<Native>),
+    Void,
+}
+
+/// The target of a `Convert` instruction, encoded as its operand byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ScalarType {
+    Int,
+    Float,
+    Char,
+    Bool,
+}
+
+impl ScalarType {
+    pub fn from_byte(byte: u8) -> Option<ScalarType> {
+        match byte {
+            0 => Some(ScalarType::Int),
+            1 => Some(ScalarType::Float),
+            2 => Some(ScalarType::Char),
+            3 => Some(ScalarType::Bool),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ScalarType::Int => "int",
+            ScalarType::Float => "float",
+            ScalarType::Char => "char",
+            ScalarType::Bool => "bool",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub arity: u8,
+    pub chunk: Chunk,
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::Char(_) => "char",
+            Value::Str(_) => "string",
+            Value::Function(_) => "function",
+            Value::Native(_) => "native function",
+            Value::Void => "void",
+        }
+    }
+
+    /// Converts a scalar as C would: floats truncate toward zero, integers
+    /// outside the `char` range become U+FFFD and anything non-zero is `true`.
+    /// Returns `None` for values that are not scalars.
+    pub fn convert(&self, to: ScalarType) -> Option<Value> {
+        if let Value::Float(value) = *self {
+            return Some(match to {
+                ScalarType::Int => Value::Int(value as i64),
+                ScalarType::Float => Value::Float(value),
+                ScalarType::Char => Value::Char(int_to_char(value as i64)),
+                ScalarType::Bool => Value::Bool(value != 0.0),
+            });
+        }
+        let value = match *self {
+            Value::Int(value) => value,
+            Value::Char(value) => value as i64,
+            Value::Bool(value) => value as i64,
+            _ => return None,
+        };
+        Some(match to {
+            ScalarType::Int => Value::Int(value),
+            ScalarType::Float => Value::Float(value as f64),
+            ScalarType::Char => Value::Char(int_to_char(value)),
+            ScalarType::Bool => Value::Bool(value != 0),
+        })
+    }
+
+    /// The process exit status for a value returned from `main`.
+    pub fn exit_status(&self) -> i32 {
+        match self {
+            Value::Int(value) => *value as i32,
+            Value::Char(value) => *value as i32,
+            Value::Bool(value) => *value as i32,
+            _ => 0,
+        }
+    }
+}
+
+fn int_to_char(value: i64) -> char {
+    u32::try_from(value)
+        .ok()
+        .and_then(char::from_u32)
+        .unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Char(value) => write!(f, "{:?}", value),
+            Value::Str(value) => write!(f, "{:?}", value),
+            Value::Function(function) => write!(f, "<fn {}>", function.name),
+            Value::Native(native) => write!(f, "<native {}>", native.name),
+            Value::Void => write!(f, "void"),
+        }
+    }
+}
@@ -0,0 +1,583 @@
+use super::{
+    Function, Halt, Native, NativeContext, NativeRegistry, OpCode, RuntimeError, RuntimeErrorKind,
+    ScalarType, Value,
+};
+use crate::span::Span;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::rc::Rc;
+
+pub const STACK_MAX: usize = 64 * 1024;
+/// The default limit on nested calls, see [`Vm::with_max_call_depth`].
+pub const FRAMES_MAX: usize = 256;
+
+type Result<T> = std::result::Result<T, RuntimeErrorKind>;
+
+struct CallFrame {
+    function: Rc<Function>,
+    ip: usize,
+    // Stack index of slot 0, the first argument. The callee sits just below.
+    base: usize,
+}
+
+pub struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+    globals: HashMap<Rc<str>, Value>,
+    max_call_depth: usize,
+    output: Box<dyn Write>,
+    input: Box<dyn BufRead>,
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Vm::new()
+    }
+}
+
+impl Vm {
+    pub fn new() -> Vm {
+        Vm {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            globals: HashMap::new(),
+            max_call_depth: FRAMES_MAX,
+            output: Box::new(io::stdout()),
+            input: Box::new(BufReader::new(io::stdin())),
+        }
+    }
+
+    /// Defines each native as a global, replacing any global of the same name.
+    pub fn with_natives(mut self, natives: &NativeRegistry) -> Vm {
+        self.define_natives(natives);
+        self
+    }
+
+    /// Sends what natives print to `output` instead of standard out.
+    pub fn with_output(mut self, output: impl Write + 'static) -> Vm {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Makes natives read from `input` instead of standard in.
+    pub fn with_input(mut self, input: impl BufRead + 'static) -> Vm {
+        self.input = Box::new(input);
+        self
+    }
+
+    /// Limits how many calls may be active at once, counting the script
+    /// itself. A call beyond the limit is a stack overflow.
+    pub fn with_max_call_depth(mut self, depth: usize) -> Vm {
+        self.max_call_depth = depth;
+        self
+    }
+
+    pub fn global(&self, name: &str) -> Option<&Value> {
+        self.globals.get(name)
+    }
+
+    /// Defines `name`, or replaces its value if it is already defined.
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.globals.insert(name.into(), value);
+    }
+
+    /// Defines each native as a global, see [`Vm::with_natives`].
+    pub fn define_natives(&mut self, natives: &NativeRegistry) {
+        for native in natives.iter() {
+            self.set_global(&native.name, Value::Native(Rc::clone(native)));
+        }
+    }
+
+    /// Forgets every global, natives included.
+    pub fn clear_globals(&mut self) {
+        self.globals.clear();
+    }
+
+    /// Runs `function` as the top-level script until it returns or halts and
+    /// yields its result, or the status passed to a native's `exit`. Globals
+    /// persist across runs.
+    pub fn run(&mut self, function: Rc<Function>) -> std::result::Result<Value, RuntimeError> {
+        self.invoke(Value::Function(function), &[])
+    }
+
+    /// Calls the function held by the global `name` with `args` as the
+    /// outermost call, typically after a script has defined it.
+    pub fn call_global(
+        &mut self,
+        name: &str,
+        args: &[Value],
+    ) -> std::result::Result<Value, RuntimeError> {
+        match self.globals.get(name) {
+            Some(callee) => self.invoke(callee.clone(), args),
+            None => Err(RuntimeError {
+                kind: RuntimeErrorKind::UndefinedGlobal(name.to_string()),
+                span: Span::default(),
+                backtrace: Vec::new(),
+            }),
+        }
+    }
+
+    fn invoke(
+        &mut self,
+        callee: Value,
+        args: &[Value],
+    ) -> std::result::Result<Value, RuntimeError> {
+        self.stack.clear();
+        self.frames.clear();
+        self.stack.push(callee);
+        self.stack.extend_from_slice(args);
+
+        let result = match self.call(args.len()) {
+            Ok(Some(status)) => Ok(Value::Int(status as i64)),
+            // a native returns straight away, with nothing left to execute
+            Ok(None) if self.frames.is_empty() => Ok(self.stack.pop().unwrap_or(Value::Void)),
+            Ok(None) => self.execute(),
+            Err(kind) => Err(RuntimeError {
+                kind,
+                span: Span::default(),
+                backtrace: Vec::new(),
+            }),
+        };
+        // whatever the script printed should be visible before its result is
+        let _ = self.output.flush();
+        result
+    }
+
+    fn execute(&mut self) -> std::result::Result<Value, RuntimeError> {
+        loop {
+            let start = self.frame().ip;
+            match self.step() {
+                Ok(Some(result)) => return Ok(result),
+                Ok(None) => {}
+                Err(kind) => return Err(self.error(kind, start)),
+            }
+        }
+    }
+
+    fn error(&self, kind: RuntimeErrorKind, offset: usize) -> RuntimeError {
+        let span = self.frame().function.chunk.span_at(offset);
+        RuntimeError {
+            kind,
+            span: span.unwrap_or_default(),
+            backtrace: self
+                .frames
+                .iter()
+                .rev()
+                .map(|frame| frame.function.name.clone())
+                .collect(),
+        }
+    }
+
+    // Executes one instruction, returning the final result once the script is done.
+    fn step(&mut self) -> Result<Option<Value>> {
+        let byte = self.read_byte()?;
+        let op = OpCode::from_byte(byte).ok_or(RuntimeErrorKind::InvalidOpcode(byte))?;
+
+        match op {
+            OpCode::Constant => {
+                let value = self.read_constant()?;
+                self.push(value)?;
+            }
+            OpCode::Void => self.push(Value::Void)?,
+            OpCode::Pop => {
+                self.pop()?;
+            }
+            OpCode::Dup => {
+                let value = self.peek(0)?.clone();
+                self.push(value)?;
+            }
+            OpCode::GetLocal => {
+                let slot = self.local_slot()?;
+                let value = self.stack[slot].clone();
+                self.push(value)?;
+            }
+            OpCode::SetLocal => {
+                let slot = self.local_slot()?;
+                self.stack[slot] = self.peek(0)?.clone();
+            }
+            OpCode::GetGlobal => {
+                let name = self.read_global_name()?;
+                let value = self
+                    .globals
+                    .get(&name)
+                    .cloned()
+                    .ok_or_else(|| RuntimeErrorKind::UndefinedGlobal(name.to_string()))?;
+                self.push(value)?;
+            }
+            OpCode::SetGlobal => {
+                let name = self.read_global_name()?;
+                let value = self.peek(0)?.clone();
+                match self.globals.get_mut(&name) {
+                    Some(global) => *global = value,
+                    None => return Err(RuntimeErrorKind::UndefinedGlobal(name.to_string())),
+                }
+            }
+            OpCode::DefineGlobal => {
+                let name = self.read_global_name()?;
+                let value = self.pop()?;
+                self.globals.insert(name, value);
+            }
+            OpCode::Add
+            | OpCode::Sub
+            | OpCode::Mul
+            | OpCode::Div
+            | OpCode::Mod
+            | OpCode::BitAnd
+            | OpCode::BitOr
+            | OpCode::BitXor
+            | OpCode::Shl
+            | OpCode::Shr
+            | OpCode::Equal
+            | OpCode::NotEqual
+            | OpCode::Less
+            | OpCode::LessEqual
+            | OpCode::Greater
+            | OpCode::GreaterEqual => {
+                let right = self.pop()?;
+                let left = self.pop()?;
+                self.push(binary(op, left, right)?)?;
+            }
+            OpCode::Negate | OpCode::Not | OpCode::BitNot => {
+                let operand = self.pop()?;
+                self.push(unary(op, operand)?)?;
+            }
+            OpCode::Jump => {
+                let offset = self.read_u16()?;
+                self.frame_mut().ip += offset as usize;
+            }
+            OpCode::JumpIfFalse => {
+                let offset = self.read_u16()?;
+                if !truthy("if", &self.pop()?)? {
+                    self.frame_mut().ip += offset as usize;
+                }
+            }
+            OpCode::Loop => {
+                let offset = self.read_u16()?;
+                let frame = self.frame_mut();
+                frame.ip = frame
+                    .ip
+                    .checked_sub(offset as usize)
+                    .ok_or(RuntimeErrorKind::UnexpectedEndOfCode)?;
+            }
+            OpCode::Call => {
+                let argc = self.read_byte()?;
+                if let Some(status) = self.call(argc as usize)? {
+                    return Ok(Some(Value::Int(status as i64)));
+                }
+            }
+            OpCode::Return => {
+                let result = self.pop()?;
+                let frame = self.frames.pop().expect("no call frame");
+                if self.frames.is_empty() {
+                    return Ok(Some(result));
+                }
+                // drop the arguments, locals and the callee itself
+                self.stack.truncate(frame.base - 1);
+                self.push(result)?;
+            }
+            OpCode::Halt => return Ok(Some(self.stack.pop().unwrap_or(Value::Void))),
+            OpCode::Convert => {
+                let byte = self.read_byte()?;
+                let to =
+                    ScalarType::from_byte(byte).ok_or(RuntimeErrorKind::InvalidConversion(byte))?;
+                let value = self.pop()?;
+                let converted = value.convert(to).ok_or(RuntimeErrorKind::TypeMismatch {
+                    operation: to.name(),
+                    found: value.type_name(),
+                })?;
+                self.push(converted)?;
+            }
+        }
+        Ok(None)
+    }
+
+    // Returns the exit status if the callee was a native that ended the program.
+    fn call(&mut self, argc: usize) -> Result<Option<i32>> {
+        let callee = self.peek(argc)?;
+        let function = match callee {
+            Value::Function(function) => Rc::clone(function),
+            Value::Native(native) => {
+                let native = Rc::clone(native);
+                return self.call_native(&native, argc);
+            }
+            other => return Err(RuntimeErrorKind::NotCallable(other.type_name())),
+        };
+        if function.arity as usize != argc {
+            return Err(RuntimeErrorKind::ArityMismatch {
+                name: function.name.clone(),
+                expected: function.arity as usize,
+                found: argc,
+            });
+        }
+        if self.frames.len() >= self.max_call_depth {
+            return Err(RuntimeErrorKind::StackOverflow);
+        }
+        let base = self.stack.len() - argc;
+        self.frames.push(CallFrame {
+            function,
+            ip: 0,
+            base,
+        });
+        Ok(None)
+    }
+
+    fn call_native(&mut self, native: &Native, argc: usize) -> Result<Option<i32>> {
+        if !native.signature.accepts(argc) {
+            return Err(RuntimeErrorKind::ArityMismatch {
+                name: native.name.clone(),
+                expected: native.signature.params.len(),
+                found: argc,
+            });
+        }
+        let base = self.stack.len() - argc;
+        let mut context = NativeContext::new(&mut *self.output, &mut *self.input);
+        let result = native
+            .call(&mut context, &self.stack[base..])
+            .map_err(|message| RuntimeErrorKind::Native {
+                name: native.name.clone(),
+                message,
+            })?;
+        let status = match context.halt {
+            Some(Halt::Exit(status)) => Some(status),
+            Some(Halt::Abort) => return Err(RuntimeErrorKind::Aborted),
+            None => None,
+        };
+        // drop the arguments and the callee itself
+        self.stack.truncate(base - 1);
+        self.push(result)?;
+        Ok(status)
+    }
+
+    fn frame(&self) -> &CallFrame {
+        self.frames.last().expect("no call frame")
+    }
+
+    fn frame_mut(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().expect("no call frame")
+    }
+
+    fn read_byte(&mut self) -> Result<u8> {
+        let frame = self.frames.last_mut().expect("no call frame");
+        let byte = *frame
+            .function
+            .chunk
+            .code
+            .get(frame.ip)
+            .ok_or(RuntimeErrorKind::UnexpectedEndOfCode)?;
+        frame.ip += 1;
+        Ok(byte)
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        let high = self.read_byte()?;
+        let low = self.read_byte()?;
+        Ok(u16::from_be_bytes([high, low]))
+    }
+
+    fn read_constant(&mut self) -> Result<Value> {
+        let index = self.read_u16()?;
+        self.frame()
+            .function
+            .chunk
+            .constants
+            .get(index as usize)
+            .cloned()
+            .ok_or(RuntimeErrorKind::InvalidConstant(index))
+    }
+
+    fn read_global_name(&mut self) -> Result<Rc<str>> {
+        match self.read_constant()? {
+            Value::Str(name) => Ok(name),
+            other => Err(RuntimeErrorKind::TypeMismatch {
+                operation: "global",
+                found: other.type_name(),
+            }),
+        }
+    }
+
+    fn local_slot(&mut self) -> Result<usize> {
+        let slot = self.frame().base + self.read_byte()? as usize;
+        if slot >= self.stack.len() {
+            return Err(RuntimeErrorKind::StackUnderflow);
+        }
+        Ok(slot)
+    }
+
+    fn push(&mut self, value: Value) -> Result<()> {
+        if self.stack.len() >= STACK_MAX {
+            return Err(RuntimeErrorKind::StackOverflow);
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Value> {
+        // never pop into the current frame's callee
+        if self.stack.len() <= self.frame().base {
+            return Err(RuntimeErrorKind::StackUnderflow);
+        }
+        Ok(self.stack.pop().expect("checked above"))
+    }
+
+    fn peek(&self, distance: usize) -> Result<&Value> {
+        self.stack
+            .len()
+            .checked_sub(distance + 1)
+            .and_then(|index| self.stack.get(index))
+            .ok_or(RuntimeErrorKind::StackUnderflow)
+    }
+}
+
+/// Output for [`Vm::with_output`] in tests, readable after the VM owning it
+/// is done.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuffer(Rc<std::cell::RefCell<Vec<u8>>>);
+
+#[cfg(test)]
+impl SharedBuffer {
+    /// Everything written so far, leaving the buffer empty.
+    pub(crate) fn take(&self) -> String {
+        String::from_utf8(self.0.take()).expect("output is UTF-8")
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+}
+
+#[cfg(test)]
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn mismatch(op: OpCode, value: &Value) -> RuntimeErrorKind {
+    RuntimeErrorKind::TypeMismatch {
+        operation: symbol(op),
+        found: value.type_name(),
+    }
+}
+
+fn symbol(op: OpCode) -> &'static str {
+    match op {
+        OpCode::Add => "+",
+        OpCode::Sub | OpCode::Negate => "-",
+        OpCode::Mul => "*",
+        OpCode::Div => "/",
+        OpCode::Mod => "%",
+        OpCode::BitAnd => "&",
+        OpCode::BitOr => "|",
+        OpCode::BitXor => "^",
+        OpCode::Shl => "<<",
+        OpCode::Shr => ">>",
+        OpCode::Not => "!",
+        OpCode::BitNot => "~",
+        OpCode::Equal => "==",
+        OpCode::NotEqual => "!=",
+        OpCode::Less => "<",
+        OpCode::LessEqual => "<=",
+        OpCode::Greater => ">",
+        OpCode::GreaterEqual => ">=",
+        _ => "?",
+    }
+}
+
+fn truthy(operation: &'static str, value: &Value) -> Result<bool> {
+    match value {
+        Value::Bool(value) => Ok(*value),
+        Value::Int(value) => Ok(*value != 0),
+        Value::Char(value) => Ok(*value != '\0'),
+        Value::Float(value) => Ok(*value != 0.0),
+        // a pointer is a string, never null, or the integer 0
+        Value::Str(_) => Ok(true),
+        other => Err(RuntimeErrorKind::TypeMismatch {
+            operation,
+            found: other.type_name(),
+        }),
+    }
+}
+
+// Chars and bools take part in integer arithmetic, as in C.
+fn as_int(value: &Value) -> Option<i64> {
+    match value {
+        Value::Int(value) => Some(*value),
+        Value::Char(value) => Some(*value as i64),
+        Value::Bool(value) => Some(*value as i64),
+        _ => None,
+    }
+}
+
+fn binary(op: OpCode, left: Value, right: Value) -> Result<Value> {
+    if let (Some(a), Some(b)) = (as_int(&left), as_int(&right)) {
+        return int_binary(op, a, b);
+    }
+    match (&left, &right) {
+        (Value::Float(a), Value::Float(b)) => float_binary(op, *a, *b),
+        (Value::Float(_), _) => Err(mismatch(op, &right)),
+        _ if matches!(op, OpCode::Equal | OpCode::NotEqual) => {
+            Ok(Value::Bool((left == right) == (op == OpCode::Equal)))
+        }
+        _ => Err(mismatch(op, &left)),
+    }
+}
+
+fn int_binary(op: OpCode, a: i64, b: i64) -> Result<Value> {
+    let value = match op {
+        OpCode::Add => a.wrapping_add(b),
+        OpCode::Sub => a.wrapping_sub(b),
+        OpCode::Mul => a.wrapping_mul(b),
+        OpCode::Div | OpCode::Mod if b == 0 => return Err(RuntimeErrorKind::DivisionByZero),
+        OpCode::Div => a.wrapping_div(b),
+        OpCode::Mod => a.wrapping_rem(b),
+        OpCode::BitAnd => a & b,
+        OpCode::BitOr => a | b,
+        OpCode::BitXor => a ^ b,
+        OpCode::Shl => a.wrapping_shl(b as u32),
+        OpCode::Shr => a.wrapping_shr(b as u32),
+        OpCode::Equal => return Ok(Value::Bool(a == b)),
+        OpCode::NotEqual => return Ok(Value::Bool(a != b)),
+        OpCode::Less => return Ok(Value::Bool(a < b)),
+        OpCode::LessEqual => return Ok(Value::Bool(a <= b)),
+        OpCode::Greater => return Ok(Value::Bool(a > b)),
+        OpCode::GreaterEqual => return Ok(Value::Bool(a >= b)),
+        _ => unreachable!("not a binary opcode: {:?}", op),
+    };
+    Ok(Value::Int(value))
+}
+
+fn float_binary(op: OpCode, a: f64, b: f64) -> Result<Value> {
+    let value = match op {
+        OpCode::Add => a + b,
+        OpCode::Sub => a - b,
+        OpCode::Mul => a * b,
+        OpCode::Div => a / b,
+        OpCode::Equal => return Ok(Value::Bool(a == b)),
+        OpCode::NotEqual => return Ok(Value::Bool(a != b)),
+        OpCode::Less => return Ok(Value::Bool(a < b)),
+        OpCode::LessEqual => return Ok(Value::Bool(a <= b)),
+        OpCode::Greater => return Ok(Value::Bool(a > b)),
+        OpCode::GreaterEqual => return Ok(Value::Bool(a >= b)),
+        _ => return Err(mismatch(op, &Value::Float(a))),
+    };
+    Ok(Value::Float(value))
+}
+
+fn unary(op: OpCode, operand: Value) -> Result<Value> {
+    match (op, &operand) {
+        (OpCode::Not, _) => Ok(Value::Bool(!truthy("!", &operand)?)),
+        (OpCode::Negate, Value::Float(value)) => Ok(Value::Float(-value)),
+        (OpCode::Negate, _) => as_int(&operand)
+            .map(|value| Value::Int(value.wrapping_neg()))
+            .ok_or_else(|| mismatch(op, &operand)),
+        (OpCode::BitNot, _) => as_int(&operand)
+            .map(|value| Value::Int(!value))
+            .ok_or_else(|| mismatch(op, &operand)),
+        _ => unreachable!("not a unary opcode: {:?}", op),
+    }
+}
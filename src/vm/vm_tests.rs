@@ -0,0 +1,443 @@
+use super::*;
+use crate::diagnostic::codes;
+use crate::parser::ast::Type;
+use crate::span::{Position, Span};
+use std::rc::Rc;
+
+fn line(line: usize) -> Span {
+    Span::new(Position::new(0, line, 1), Position::new(1, line, 2))
+}
+
+// Builds chunks by hand, one source line per `at` call.
+struct Asm {
+    chunk: Chunk,
+    span: Span,
+}
+
+impl Asm {
+    fn new() -> Asm {
+        Asm {
+            chunk: Chunk::new(),
+            span: line(1),
+        }
+    }
+
+    fn at(&mut self, n: usize) -> &mut Asm {
+        self.span = line(n);
+        self
+    }
+
+    fn op(&mut self, op: OpCode) -> &mut Asm {
+        self.chunk.write_op(op, self.span);
+        self
+    }
+
+    fn byte(&mut self, op: OpCode, operand: u8) -> &mut Asm {
+        self.op(op);
+        self.chunk.write(operand, self.span);
+        self
+    }
+
+    fn short(&mut self, op: OpCode, operand: u16) -> &mut Asm {
+        self.op(op);
+        self.chunk.write_u16(operand, self.span);
+        self
+    }
+
+    fn constant(&mut self, value: Value) -> &mut Asm {
+        let index = self.chunk.add_constant(value).unwrap();
+        self.short(OpCode::Constant, index)
+    }
+
+    fn global(&mut self, op: OpCode, name: &str) -> &mut Asm {
+        let index = self.chunk.add_constant(Value::Str(name.into())).unwrap();
+        self.short(op, index)
+    }
+
+    fn function(&mut self, name: &str, arity: u8) -> Rc<Function> {
+        Rc::new(Function {
+            name: name.to_string(),
+            arity,
+            chunk: std::mem::take(&mut self.chunk),
+        })
+    }
+}
+
+fn run(function: Rc<Function>) -> Result<Value, RuntimeError> {
+    Vm::new().run(function)
+}
+
+#[test]
+fn test_arithmetic() {
+    // (10 + 1) * 3 - 8 / 2
+    let script = Asm::new()
+        .constant(Value::Int(10))
+        .constant(Value::Int(1))
+        .op(OpCode::Add)
+        .constant(Value::Int(3))
+        .op(OpCode::Mul)
+        .constant(Value::Int(8))
+        .constant(Value::Int(2))
+        .op(OpCode::Div)
+        .op(OpCode::Sub)
+        .op(OpCode::Return)
+        .function("script", 0);
+
+    assert_eq!(run(script), Ok(Value::Int(29)));
+}
+
+#[test]
+fn test_comparison_and_logic() {
+    let script = Asm::new()
+        .constant(Value::Int(1))
+        .constant(Value::Int(2))
+        .op(OpCode::Less)
+        .op(OpCode::Not)
+        .op(OpCode::Not)
+        .op(OpCode::Return)
+        .function("script", 0);
+    assert_eq!(run(script), Ok(Value::Bool(true)));
+
+    let script = Asm::new()
+        .constant(Value::Float(1.5))
+        .op(OpCode::Negate)
+        .constant(Value::Float(2.0))
+        .op(OpCode::Mul)
+        .op(OpCode::Return)
+        .function("script", 0);
+    assert_eq!(run(script), Ok(Value::Float(-3.0)));
+}
+
+#[test]
+fn test_locals_and_loops() {
+    // slot 0 = i = 0; slot 1 = sum = 0; while (i < 5) { sum = sum + i; i = i + 1; } return sum;
+    let mut asm = Asm::new();
+    asm.constant(Value::Int(0)).constant(Value::Int(0));
+    let loop_start = asm.chunk.len();
+    asm.byte(OpCode::GetLocal, 0)
+        .constant(Value::Int(5))
+        .op(OpCode::Less)
+        .short(OpCode::JumpIfFalse, 0);
+    let exit_jump = asm.chunk.len() - 2;
+    asm.byte(OpCode::GetLocal, 1)
+        .byte(OpCode::GetLocal, 0)
+        .op(OpCode::Add)
+        .byte(OpCode::SetLocal, 1)
+        .op(OpCode::Pop)
+        .byte(OpCode::GetLocal, 0)
+        .constant(Value::Int(1))
+        .op(OpCode::Add)
+        .byte(OpCode::SetLocal, 0)
+        .op(OpCode::Pop);
+    let back = asm.chunk.len() + 3 - loop_start;
+    asm.short(OpCode::Loop, back as u16);
+    let exit = asm.chunk.len() - exit_jump - 2;
+    asm.chunk.patch_u16(exit_jump, exit as u16);
+    asm.byte(OpCode::GetLocal, 1).op(OpCode::Return);
+
+    assert_eq!(run(asm.function("script", 0)), Ok(Value::Int(10)));
+}
+
+#[test]
+fn test_globals_and_calls() {
+    // int add(int a, int b) { return a + b; }
+    let add = Asm::new()
+        .byte(OpCode::GetLocal, 0)
+        .byte(OpCode::GetLocal, 1)
+        .op(OpCode::Add)
+        .op(OpCode::Return)
+        .function("add", 2);
+
+    // int main() { return add(40, 2); }
+    let main = Asm::new()
+        .global(OpCode::GetGlobal, "add")
+        .constant(Value::Int(40))
+        .constant(Value::Int(2))
+        .byte(OpCode::Call, 2)
+        .op(OpCode::Return)
+        .function("main", 0);
+
+    let script = Asm::new()
+        .constant(Value::Function(add))
+        .global(OpCode::DefineGlobal, "add")
+        .constant(Value::Function(main))
+        .global(OpCode::DefineGlobal, "main")
+        .global(OpCode::GetGlobal, "main")
+        .byte(OpCode::Call, 0)
+        .op(OpCode::Halt)
+        .function("script", 0);
+
+    let mut vm = Vm::new();
+    let result = vm.run(script).unwrap();
+    assert_eq!(result, Value::Int(42));
+    assert_eq!(result.exit_status(), 42);
+    assert!(matches!(vm.global("add"), Some(Value::Function(_))));
+    assert_eq!(vm.global("missing"), None);
+}
+
+#[test]
+fn test_division_by_zero_reports_line() {
+    let script = Asm::new()
+        .constant(Value::Int(1))
+        .at(2)
+        .constant(Value::Int(0))
+        .at(3)
+        .op(OpCode::Div)
+        .op(OpCode::Return)
+        .function("script", 0);
+
+    let err = run(script).unwrap_err();
+    assert_eq!(err.kind, RuntimeErrorKind::DivisionByZero);
+    assert_eq!(err.line(), 3);
+    assert_eq!(err.to_string(), "runtime error: division by zero at line 3");
+
+    let diagnostic = err.to_diagnostic();
+    assert_eq!(diagnostic.code, codes::DIVISION_BY_ZERO);
+    assert_eq!(diagnostic.notes, vec!["in function `script`"]);
+}
+
+#[test]
+fn test_stack_overflow() {
+    // int f() { return f(); }
+    let mut asm = Asm::new();
+    asm.at(4)
+        .global(OpCode::GetGlobal, "f")
+        .byte(OpCode::Call, 0)
+        .op(OpCode::Return);
+    let f = asm.function("f", 0);
+
+    let script = Asm::new()
+        .constant(Value::Function(f))
+        .global(OpCode::DefineGlobal, "f")
+        .global(OpCode::GetGlobal, "f")
+        .byte(OpCode::Call, 0)
+        .op(OpCode::Halt)
+        .function("script", 0);
+
+    let err = run(script).unwrap_err();
+    assert_eq!(err.kind, RuntimeErrorKind::StackOverflow);
+    assert_eq!(err.line(), 4);
+    assert_eq!(err.backtrace.len(), FRAMES_MAX);
+    assert_eq!(err.backtrace.last().unwrap(), "script");
+    assert_eq!(
+        err.to_diagnostic().notes,
+        vec![
+            format!("in function `f` (repeated {} times)", FRAMES_MAX - 1),
+            "in function `script`".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_conversions() {
+    let convert = |value: Value, to: ScalarType| {
+        let script = Asm::new()
+            .constant(value)
+            .byte(OpCode::Convert, to as u8)
+            .op(OpCode::Return)
+            .function("script", 0);
+        run(script)
+    };
+    assert_eq!(
+        convert(Value::Int(3), ScalarType::Float),
+        Ok(Value::Float(3.0))
+    );
+    assert_eq!(
+        convert(Value::Float(-2.7), ScalarType::Int),
+        Ok(Value::Int(-2))
+    );
+    assert_eq!(
+        convert(Value::Int(65), ScalarType::Char),
+        Ok(Value::Char('A'))
+    );
+    assert_eq!(
+        convert(Value::Int(-1), ScalarType::Char),
+        Ok(Value::Char(char::REPLACEMENT_CHARACTER))
+    );
+    assert_eq!(
+        convert(Value::Char('a'), ScalarType::Int),
+        Ok(Value::Int(97))
+    );
+    assert_eq!(
+        convert(Value::Float(0.5), ScalarType::Bool),
+        Ok(Value::Bool(true))
+    );
+    assert_eq!(
+        convert(Value::Int(0), ScalarType::Bool),
+        Ok(Value::Bool(false))
+    );
+    assert_eq!(
+        convert(Value::Str("a".into()), ScalarType::Int)
+            .unwrap_err()
+            .kind,
+        RuntimeErrorKind::TypeMismatch {
+            operation: "int",
+            found: "string"
+        }
+    );
+    assert_eq!(
+        run(Asm::new()
+            .constant(Value::Int(1))
+            .byte(OpCode::Convert, 9)
+            .function("script", 0))
+        .unwrap_err()
+        .kind,
+        RuntimeErrorKind::InvalidConversion(9)
+    );
+}
+
+#[test]
+fn test_bad_opcode() {
+    let mut asm = Asm::new();
+    asm.constant(Value::Int(1));
+    asm.chunk.write(0xFF, line(7));
+    let err = run(asm.function("script", 0)).unwrap_err();
+    assert_eq!(err.kind, RuntimeErrorKind::InvalidOpcode(0xFF));
+    assert_eq!(err.line(), 7);
+    assert_eq!(err.to_diagnostic().code, codes::INVALID_BYTECODE);
+
+    let script = Asm::new().constant(Value::Int(1)).function("script", 0);
+    assert_eq!(
+        run(script).unwrap_err().kind,
+        RuntimeErrorKind::UnexpectedEndOfCode
+    );
+}
+
+#[test]
+fn test_type_errors() {
+    let script = Asm::new()
+        .constant(Value::Str("a".into()))
+        .constant(Value::Int(1))
+        .op(OpCode::Add)
+        .op(OpCode::Return)
+        .function("script", 0);
+    assert_eq!(
+        run(script).unwrap_err().kind,
+        RuntimeErrorKind::TypeMismatch {
+            operation: "+",
+            found: "string"
+        }
+    );
+
+    let script = Asm::new()
+        .constant(Value::Int(1))
+        .byte(OpCode::Call, 0)
+        .function("script", 0);
+    assert_eq!(
+        run(script).unwrap_err().kind,
+        RuntimeErrorKind::NotCallable("int")
+    );
+
+    let script = Asm::new()
+        .global(OpCode::GetGlobal, "x")
+        .function("script", 0);
+    assert_eq!(
+        run(script).unwrap_err().kind,
+        RuntimeErrorKind::UndefinedGlobal("x".to_string())
+    );
+}
+
+#[test]
+fn test_arity_mismatch() {
+    let f = Asm::new()
+        .constant(Value::Void)
+        .op(OpCode::Return)
+        .function("f", 1);
+    let script = Asm::new()
+        .constant(Value::Function(f))
+        .byte(OpCode::Call, 0)
+        .function("script", 0);
+    assert_eq!(
+        run(script).unwrap_err().kind,
+        RuntimeErrorKind::ArityMismatch {
+            name: "f".to_string(),
+            expected: 1,
+            found: 0
+        }
+    );
+}
+
+#[test]
+fn test_natives() {
+    let calls = Rc::new(std::cell::Cell::new(0));
+    let counter = Rc::clone(&calls);
+    let mut natives = NativeRegistry::new();
+    natives.register(
+        "scale",
+        Signature::new(Type::Int, vec![Type::Int, Type::Int]),
+        move |_, args| {
+            counter.set(counter.get() + 1);
+            match args {
+                [Value::Int(a), Value::Int(b)] => Ok(Value::Int(a * b)),
+                _ => Err("expected two integers".to_string()),
+            }
+        },
+    );
+
+    // scale(scale(2, 3), 7) + 1
+    let script = Asm::new()
+        .global(OpCode::GetGlobal, "scale")
+        .global(OpCode::GetGlobal, "scale")
+        .constant(Value::Int(2))
+        .constant(Value::Int(3))
+        .byte(OpCode::Call, 2)
+        .constant(Value::Int(7))
+        .byte(OpCode::Call, 2)
+        .constant(Value::Int(1))
+        .op(OpCode::Add)
+        .op(OpCode::Halt)
+        .function("script", 0);
+    let mut vm = Vm::new().with_natives(&natives);
+    assert_eq!(vm.run(script), Ok(Value::Int(43)));
+    assert_eq!(calls.get(), 2);
+    assert_eq!(vm.global("scale").unwrap().to_string(), "<native scale>");
+
+    let script = Asm::new()
+        .global(OpCode::GetGlobal, "scale")
+        .constant(Value::Int(2))
+        .constant(Value::Bool(true))
+        .byte(OpCode::Call, 2)
+        .op(OpCode::Halt)
+        .function("script", 0);
+    let err = vm.run(script).unwrap_err();
+    assert_eq!(err.code(), codes::NATIVE_ERROR);
+    assert_eq!(
+        err.kind.to_string(),
+        "`scale` failed: expected two integers"
+    );
+
+    let script = Asm::new()
+        .global(OpCode::GetGlobal, "scale")
+        .byte(OpCode::Call, 0)
+        .function("script", 0);
+    assert!(matches!(
+        vm.run(script).unwrap_err().kind,
+        RuntimeErrorKind::ArityMismatch { expected: 2, .. }
+    ));
+}
+
+#[test]
+fn test_natives_may_stop_the_program() {
+    let natives = NativeRegistry::with_stdlib();
+    // exit(3) stops the script before it returns 1
+    let script = Asm::new()
+        .global(OpCode::GetGlobal, "exit")
+        .constant(Value::Int(3))
+        .byte(OpCode::Call, 1)
+        .constant(Value::Int(1))
+        .op(OpCode::Halt)
+        .function("script", 0);
+    let mut vm = Vm::new().with_natives(&natives);
+    assert_eq!(vm.run(script), Ok(Value::Int(3)));
+
+    let script = Asm::new()
+        .at(4)
+        .global(OpCode::GetGlobal, "abort")
+        .byte(OpCode::Call, 0)
+        .op(OpCode::Halt)
+        .function("script", 0);
+    let err = vm.run(script).unwrap_err();
+    assert_eq!(err.kind, RuntimeErrorKind::Aborted);
+    assert_eq!(err.code(), codes::ABORTED);
+    assert_eq!(err.line(), 4);
+}